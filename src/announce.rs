@@ -0,0 +1,90 @@
+use std::error::Error;
+use std::process::Command;
+
+use crate::selection::Pick;
+use crate::theme::{SongLang, Theme};
+
+/// The sentence spoken for a pick, with the song title in the script of `song_lang`
+pub fn announcement(pick: &Pick, song_lang: SongLang) -> String {
+    let song = song_lang.apply(pick.song, &pick.show.native_titles);
+    format!(
+        "Next up: {}, from {}",
+        Theme::parse(&song).title,
+        pick.show.title
+    )
+}
+
+/// The variable PowerShell reads the text from
+const TEXT_VARIABLE: &str = "RANDOM_SHOW_THEMES_ANNOUNCEMENT";
+
+/// Speaks `text` with `System.Speech` through PowerShell
+///
+/// PowerShell runs every argument after `-Command` as part of the script, so the text is passed
+/// in the environment instead, where it can only ever be read as a string.
+fn powershell(text: &str) -> Command {
+    let mut command = Command::new("powershell");
+    command
+        .args([
+            "-NoProfile",
+            "-Command",
+            "Add-Type -AssemblyName System.Speech; \
+             (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($env:RANDOM_SHOW_THEMES_ANNOUNCEMENT)",
+        ])
+        .env(TEXT_VARIABLE, text);
+    command
+}
+
+/// Speaks the pick using the text-to-speech engine of the OS, waiting until it's done
+///
+/// Uses `say` on macOS, `System.Speech` through PowerShell on Windows and `espeak` everywhere else.
+pub fn announce(pick: &Pick, song_lang: SongLang) -> Result<(), Box<dyn Error>> {
+    let text = announcement(pick, song_lang);
+
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("say");
+        command.arg(&text);
+        command
+    } else if cfg!(windows) {
+        powershell(&text)
+    } else {
+        let mut command = Command::new("espeak");
+        command.arg(&text);
+        command
+    };
+
+    let status = command
+        .status()
+        .map_err(|e| format!("couldn't start the text-to-speech engine: {}", e))?;
+    if !status.success() {
+        return Err(format!("text-to-speech engine exited with {}", status).into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::fixtures;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn titles_spoken_literally() {
+        let show = fixtures::show("Show; calc", "#1: \"Song'); calc; ('\" by Artist");
+        let text = announcement(&fixtures::opening(&show), SongLang::Both);
+        assert_eq!(text, "Next up: Song'); calc; (', from Show; calc");
+
+        let command = powershell(&text);
+        // The script is fixed and only reads the text from the environment
+        assert!(command
+            .get_args()
+            .all(|arg| !arg.to_string_lossy().contains("calc")));
+        assert!(command.get_args().any(|arg| arg
+            .to_string_lossy()
+            .contains(&format!("$env:{}", TEXT_VARIABLE))));
+        assert_eq!(
+            command.get_envs().collect::<Vec<_>>(),
+            [(OsStr::new(TEXT_VARIABLE), Some(OsStr::new(&text)))]
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::ffi::OsString;
+use std::fs;
+
+/// How deep argument files may include other argument files
+const MAX_DEPTH: usize = 8;
+
+/// Replaces every `@file` argument with the arguments read from that file
+///
+/// Arguments in the file are separated by whitespace and may be quoted with single or double
+/// quotes. Lines starting with `#` are ignored and files may include other files.
+/// `@@` at the start of an argument passes a literal `@`.
+/// The first argument is the program name and is never expanded.
+pub fn expand_arg_files<I>(args: I) -> Result<Vec<OsString>, String>
+where
+    I: IntoIterator<Item = OsString>,
+{
+    let mut args = args.into_iter();
+    let mut expanded: Vec<OsString> = args.next().into_iter().collect();
+    for arg in args {
+        expand(arg, &mut expanded, 0)?;
+    }
+    Ok(expanded)
+}
+
+fn expand(arg: OsString, expanded: &mut Vec<OsString>, depth: usize) -> Result<(), String> {
+    // Arguments that aren't valid UTF-8 can't be argument files
+    let arg = match arg.into_string() {
+        Ok(arg) => arg,
+        Err(arg) => {
+            expanded.push(arg);
+            return Ok(());
+        }
+    };
+
+    if let Some(literal) = arg.strip_prefix("@@") {
+        expanded.push(format!("@{}", literal).into());
+    } else if let Some(path) = arg.strip_prefix('@').filter(|p| !p.is_empty()) {
+        if depth == MAX_DEPTH {
+            return Err(format!(
+                "argument files are nested more than {} levels deep at '{}'",
+                MAX_DEPTH, path
+            ));
+        }
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("couldn't read argument file '{}': {}", path, e))?;
+        for arg in
+            split_args(&contents).map_err(|e| format!("in argument file '{}': {}", path, e))?
+        {
+            expand(arg.into(), expanded, depth + 1)?;
+        }
+    } else {
+        expanded.push(arg.into());
+    }
+
+    Ok(())
+}
+
+/// Splits the contents of an argument file into arguments
+fn split_args(contents: &str) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+    for line in contents.lines() {
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let mut current: Option<String> = None;
+        let mut quote: Option<char> = None;
+        for c in line.chars() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), c) => current.get_or_insert_with(String::new).push(c),
+                (None, '"') | (None, '\'') => {
+                    quote = Some(c);
+                    // Quotes make empty arguments possible
+                    current.get_or_insert_with(String::new);
+                }
+                (None, c) if c.is_whitespace() => args.extend(current.take()),
+                (None, c) => current.get_or_insert_with(String::new).push(c),
+            }
+        }
+        if let Some(q) = quote {
+            return Err(format!("unclosed {} quote in '{}'", q, line));
+        }
+        args.extend(current);
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn argument_splitting() {
+        let contents = r#"
+# quotas for music night
+--quota "genre:Music=2"   --quota 'tag:favorite>=1'
+-d dict.json ""
+"#;
+        assert_eq!(
+            split_args(contents).unwrap(),
+            vec![
+                "--quota",
+                "genre:Music=2",
+                "--quota",
+                "tag:favorite>=1",
+                "-d",
+                "dict.json",
+                ""
+            ]
+        );
+        assert!(split_args("--quota \"genre:Music=2").is_err());
+    }
+
+    #[test]
+    fn argument_files() {
+        let dir = std::env::temp_dir();
+        let outer = dir.join("random-show-themes-outer.args");
+        let inner = dir.join("random-show-themes-inner.args");
+        fs::write(&inner, "--csv").unwrap();
+        fs::write(&outer, format!("-d dict.json @{}", inner.display())).unwrap();
+
+        let args: Vec<OsString> = ["prog", "5", "@@literal", "@"]
+            .iter()
+            .map(|s| s.into())
+            .chain(Some(format!("@{}", outer.display()).into()))
+            .collect();
+        assert_eq!(
+            expand_arg_files(args).unwrap(),
+            vec!["prog", "5", "@literal", "@", "-d", "dict.json", "--csv"]
+        );
+        assert!(expand_arg_files(vec!["prog".into(), "@does-not-exist.args".into()]).is_err());
+
+        // Including itself can't go on forever
+        fs::write(&inner, format!("@{}", inner.display())).unwrap();
+        assert!(
+            expand_arg_files(vec!["prog".into(), format!("@{}", inner.display()).into()]).is_err()
+        );
+
+        fs::remove_file(outer).unwrap();
+        fs::remove_file(inner).unwrap();
+    }
+}
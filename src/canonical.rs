@@ -0,0 +1,137 @@
+//! The canonical formatting of data files, which keeps diffs in version-controlled data minimal
+//!
+//! Objects are written with their keys sorted, numeric keys (like show ids) in numeric order
+//! before any others. Arrays that are sets, like genres and tags, are sorted and deduplicated,
+//! every other array keeps its order since it's meaningful (theme numbering, list order).
+//! Indentation is two spaces and the file ends with a newline.
+
+use std::cmp::Ordering;
+
+use serde_json::Value;
+
+/// The keys of arrays whose order doesn't matter
+const SET_KEYS: &[&str] = &["genres", "tags"];
+
+/// Formats a value canonically
+pub fn to_string(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(value, None, 0, &mut out);
+    out.push('\n');
+    out
+}
+
+fn write_value(value: &Value, key: Option<&str>, indent: usize, out: &mut String) {
+    match value {
+        Value::Array(values) if values.is_empty() => out.push_str("[]"),
+        Value::Array(values) => {
+            let mut values: Vec<&Value> = values.iter().collect();
+            if key.is_some_and(|key| SET_KEYS.contains(&key)) {
+                values.sort_by(|a, b| value_order(a, b));
+                values.dedup();
+            }
+            out.push('[');
+            for (i, value) in values.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                newline(indent + 1, out);
+                write_value(value, None, indent + 1, out);
+            }
+            newline(indent, out);
+            out.push(']');
+        }
+        Value::Object(map) if map.is_empty() => out.push_str("{}"),
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| key_order(a, b));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                newline(indent + 1, out);
+                out.push_str(&Value::from(key.as_str()).to_string());
+                out.push_str(": ");
+                write_value(value, Some(key), indent + 1, out);
+            }
+            newline(indent, out);
+            out.push('}');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+fn newline(indent: usize, out: &mut String) {
+    out.push('\n');
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+/// Numeric keys in numeric order, then all other keys in lexicographic order
+fn key_order(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+/// Orders the values of sets, which are expected to be strings
+fn value_order(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        _ => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_formatting() {
+        let value: Value = serde_json::from_str(
+            r##"{"10": {"title": "B", "id": 10, "tags": ["x", "a", "x"], "opening_themes": ["#2", "#1"]},
+                "2": {"id": 2, "title": "A \"quoted\"", "genres": [], "rating": null},
+                "slug": {"id": "slug", "title": "C", "score": 8.5}}"##,
+        )
+        .unwrap();
+        let formatted = to_string(&value);
+        assert_eq!(
+            formatted,
+            r##"{
+  "2": {
+    "genres": [],
+    "id": 2,
+    "rating": null,
+    "title": "A \"quoted\""
+  },
+  "10": {
+    "id": 10,
+    "opening_themes": [
+      "#2",
+      "#1"
+    ],
+    "tags": [
+      "a",
+      "x"
+    ],
+    "title": "B"
+  },
+  "slug": {
+    "id": "slug",
+    "score": 8.5,
+    "title": "C"
+  }
+}
+"##
+        );
+
+        // Formatting is stable
+        let reparsed: Value = serde_json::from_str(&formatted).unwrap();
+        assert_eq!(to_string(&reparsed), formatted);
+        assert_eq!(to_string(&serde_json::json!([3, 1])), "[\n  3,\n  1\n]\n");
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::Value;
+
+use crate::list::ListEntry;
+use crate::{read_json_file, ShowId};
+
+/// The shows that were added to or modified in the dictionary, or added to the list, since the
+/// git revision `since`
+///
+/// Both files are expected in a git repository. A file that didn't exist at the revision counts
+/// as entirely new.
+pub fn changed_since(
+    dictionary: &Path,
+    list: &Path,
+    since: &str,
+) -> Result<HashSet<ShowId>, Box<dyn Error>> {
+    let mut changed = HashSet::new();
+
+    let current: HashMap<ShowId, Value> = read_json_file(dictionary)?;
+    let previous: HashMap<ShowId, Value> = match file_at(dictionary, since)? {
+        Some(contents) => serde_json::from_str(&contents)?,
+        None => HashMap::new(),
+    };
+    for (id, show) in current {
+        if previous.get(&id) != Some(&show) {
+            changed.insert(id);
+        }
+    }
+
+    let current: Vec<ListEntry> = read_json_file(list)?;
+    let previous: HashSet<ShowId> = match file_at(list, since)? {
+        Some(contents) => serde_json::from_str::<Vec<ListEntry>>(&contents)?
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect(),
+        None => HashSet::new(),
+    };
+    changed.extend(
+        current
+            .into_iter()
+            .map(|entry| entry.id)
+            .filter(|id| !previous.contains(id)),
+    );
+
+    Ok(changed)
+}
+
+/// The contents of a file at a git revision, or `None` if it didn't exist yet
+fn file_at(path: &Path, revision: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| format!("{} isn't a valid file name", path.display()))?;
+
+    // Make sure the revision exists, so a typo isn't mistaken for a file that didn't exist yet
+    let verified = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{}^{{commit}}", revision))
+        .output()
+        .map_err(|e| format!("couldn't start git: {}", e))?;
+    if !verified.status.success() {
+        return Err(format!("'{}' isn't a git revision of {}", revision, dir.display()).into());
+    }
+
+    // `./` makes the path relative to the directory instead of the root of the repository
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!("{}:./{}", revision, file_name))
+        .output()
+        .map_err(|e| format!("couldn't start git: {}", e))?;
+    if output.status.success() {
+        Ok(Some(String::from_utf8(output.stdout)?))
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn changes_since_revision() {
+        let dir = std::env::temp_dir().join("random-show-themes-changes");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let dictionary = dir.join("dictionary.json");
+        let list = dir.join("list.json");
+
+        git(&dir, &["init", "--quiet"]);
+        std::fs::write(
+            &dictionary,
+            r#"{"1": {"id": 1, "title": "A"}, "2": {"id": 2, "title": "B"}}"#,
+        )
+        .unwrap();
+        std::fs::write(&list, "[1, 2]").unwrap();
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "--quiet", "-m", "initial"]);
+
+        std::fs::write(
+            &dictionary,
+            r#"{"1": {"id": 1, "title": "A"}, "2": {"id": 2, "title": "B", "tags": ["new"]},
+                "3": {"id": 3, "title": "C"}, "4": {"id": 4, "title": "D"}}"#,
+        )
+        .unwrap();
+        std::fs::write(&list, "[1, 2, 3, 4]").unwrap();
+
+        let changed = changed_since(&dictionary, &list, "HEAD").unwrap();
+        let mut changed: Vec<ShowId> = changed.into_iter().collect();
+        changed.sort();
+        assert_eq!(changed, [2usize, 3, 4].map(ShowId::from));
+
+        assert!(changed_since(&dictionary, &list, "no-such-revision").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
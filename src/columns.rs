@@ -0,0 +1,155 @@
+use clap::ArgMatches;
+
+use crate::links::LinkTemplate;
+use crate::notes::{pick_hash, Notes};
+use crate::selection::Pick;
+use crate::theme::SongLang;
+
+/// A field of a pick that can be output
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    /// The 1-based position of the pick in the output
+    Number,
+    Song(SongLang),
+    Show,
+    Type,
+    Id,
+    Url,
+    /// A short hash of the show and the song, to key notes of a single theme by
+    Hash,
+    Link(LinkTemplate),
+    /// A custom field of the dictionary
+    Custom(String),
+    /// The note of the theme or show given with `--notes`
+    Notes(Notes),
+}
+
+impl Column {
+    /// The columns that are output unless `--columns` chooses others
+    pub const DEFAULT: [Column; 3] = [Column::Song(SongLang::Both), Column::Show, Column::Type];
+
+    /// The names of the columns `--columns` can choose
+    pub const NAMES: &'static [&'static str] =
+        &["number", "song", "show", "type", "id", "url", "hash"];
+
+    /// The columns chosen with `--columns`, or else the default ones, followed by the id with
+    /// `--with-id`, the url with `--with-url`, any requested links and custom fields and the
+    /// notes, after the number with `--numbered`
+    pub fn from_matches(matches: &ArgMatches) -> Result<Vec<Column>, String> {
+        let mut templates = LinkTemplate::built_in();
+        if let Some(values) = matches.values_of("link-template") {
+            for value in values {
+                // Validated by clap
+                let template: LinkTemplate = value.parse().unwrap();
+                // Later definitions replace earlier ones, including the built-in ones
+                templates.retain(|t| t.kind != template.kind);
+                templates.push(template);
+            }
+        }
+
+        let mut columns: Vec<Column> = match matches.values_of("columns") {
+            // Validated by clap
+            Some(names) => names.map(|name| Column::from_name(name).unwrap()).collect(),
+            None => Column::DEFAULT.to_vec(),
+        };
+        let song_lang = SongLang::from_matches(matches);
+        for column in &mut columns {
+            if let Column::Song(lang) = column {
+                *lang = song_lang;
+            }
+        }
+        if matches.is_present("numbered") && !columns.contains(&Column::Number) {
+            columns.insert(0, Column::Number);
+        }
+        if matches.is_present("with-id") && !columns.contains(&Column::Id) {
+            columns.push(Column::Id);
+        }
+        if matches.is_present("with-url") && !columns.contains(&Column::Url) {
+            columns.push(Column::Url);
+        }
+        for kind in matches.values_of("links").into_iter().flatten() {
+            let template = templates.iter().find(|t| t.kind == kind).ok_or_else(|| {
+                let kinds: Vec<&str> = templates.iter().map(|t| t.kind.as_str()).collect();
+                format!(
+                    "unknown link kind '{}', expected one of: {}",
+                    kind,
+                    kinds.join(", ")
+                )
+            })?;
+            columns.push(Column::Link(LinkTemplate {
+                song_lang,
+                ..template.clone()
+            }));
+        }
+        for field in matches.values_of("custom-column").into_iter().flatten() {
+            columns.push(Column::Custom(field.to_owned()));
+        }
+        if let Some(path) = matches.value_of("notes") {
+            let notes =
+                Notes::load(path).map_err(|e| format!("couldn't read notes {}: {}", path, e))?;
+            columns.push(Column::Notes(notes));
+        }
+
+        Ok(columns)
+    }
+
+    /// The column called `name`, one of `NAMES`
+    fn from_name(name: &str) -> Option<Column> {
+        match name {
+            "number" => Some(Column::Number),
+            "song" => Some(Column::Song(SongLang::Both)),
+            "show" => Some(Column::Show),
+            "type" => Some(Column::Type),
+            "id" => Some(Column::Id),
+            "url" => Some(Column::Url),
+            "hash" => Some(Column::Hash),
+            _ => None,
+        }
+    }
+
+    /// Checks if the columns start with the song, show and type, as they do unless `--columns`
+    /// chooses others
+    pub fn starts_with_default(columns: &[Column]) -> bool {
+        matches!(columns, [Column::Song(_), Column::Show, Column::Type, ..])
+    }
+
+    /// The song column, for outputs that are about songs even when `--columns` leaves it out
+    pub fn song(columns: &[Column]) -> Column {
+        columns
+            .iter()
+            .find(|column| matches!(column, Column::Song(_)))
+            .cloned()
+            .unwrap_or(Column::Song(SongLang::Both))
+    }
+
+    pub fn header(&self) -> &str {
+        match self {
+            Column::Number => "Number",
+            Column::Song(_) => "Song",
+            Column::Show => "Show",
+            Column::Type => "Type",
+            Column::Id => "ID",
+            Column::Url => "URL",
+            Column::Hash => "Hash",
+            Column::Link(template) => &template.kind,
+            Column::Custom(field) => field,
+            Column::Notes(_) => "Notes",
+        }
+    }
+
+    /// The value for the pick at the 1-based position `number`
+    pub fn value(&self, number: usize, pick: &Pick) -> String {
+        match self {
+            Column::Number => number.to_string(),
+            Column::Song(lang) => lang.apply(pick.song, &pick.show.native_titles),
+            Column::Show => pick.show.title.clone(),
+            Column::Type => pick.show.song_type(pick.song).to_owned(),
+            Column::Id => pick.show.id.to_string(),
+            Column::Url => pick.show.url.clone().unwrap_or_default(),
+            Column::Hash => pick_hash(pick),
+            Column::Link(template) => template.render(pick),
+            Column::Custom(field) => pick.show.custom_field(field).unwrap_or_default(),
+            Column::Notes(notes) => notes.get(pick).unwrap_or_default().to_owned(),
+        }
+    }
+}
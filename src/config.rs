@@ -0,0 +1,811 @@
+//! Defaults for `pick` from a JSON config file
+//!
+//! Settings are layered: built-in defaults, then the config file, then environment variables,
+//! then the command line. The config file is `config.json` in the config directory (see
+//! `crate::paths`), or the file named by `RANDOM_SHOW_THEMES_CONFIG`.
+
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::columns::Column;
+use crate::links::{link_template_validate, LinkTemplate};
+use crate::list::{ListDuplicates, WatchStatus};
+use crate::logging::LEVELS;
+use crate::quota::quota_validate;
+use crate::selection::ThemeOrder;
+use crate::sort::SortKey;
+use crate::spacing::Adjacency;
+use crate::style::{readable_format_validate, type_color_validate, Coloring};
+use crate::table::WidthMode;
+use crate::theme::SongLang;
+use crate::weights::{song_type_validate, type_weight_validate};
+use crate::{csv_delimiter, OutputMode, CSV_QUOTE_STYLES};
+
+/// The environment variable naming the config file
+pub const ENV: &str = "RANDOM_SHOW_THEMES_CONFIG";
+
+/// The `pick` options a config file can set, named like their flags
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub data_dir: Option<PathBuf>,
+    /// One of `OutputMode::NAMES`, e.g. "readable" or "json"
+    pub output: Option<String>,
+    pub table_width: Option<usize>,
+    pub max_title_width: Option<usize>,
+    /// One of `WidthMode::NAMES`
+    pub width_mode: Option<String>,
+    pub csv_delimiter: Option<String>,
+    pub csv_quote_style: Option<String>,
+    pub song_lang: Option<String>,
+    pub readable_format: Option<String>,
+    #[serde(default)]
+    pub type_colors: Vec<String>,
+    #[serde(default)]
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub numbered: bool,
+    #[serde(default)]
+    pub group_by_show: bool,
+    #[serde(default)]
+    pub summary: bool,
+    pub sort: Option<String>,
+    #[serde(default)]
+    pub reverse: bool,
+    #[serde(default)]
+    pub links: Vec<String>,
+    #[serde(default)]
+    pub link_templates: Vec<String>,
+    #[serde(default)]
+    pub custom_columns: Vec<String>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub quotas: Vec<String>,
+    #[serde(default)]
+    pub type_weights: Vec<String>,
+    #[serde(default)]
+    pub interleave_types: Vec<String>,
+    #[serde(default)]
+    pub status: Vec<String>,
+    #[serde(default)]
+    pub family_friendly: bool,
+    pub min_popularity: Option<u64>,
+    #[serde(default)]
+    pub hipster: bool,
+    pub no_adjacent: Option<String>,
+    #[serde(default)]
+    pub unique_artist: bool,
+    pub order_themes: Option<String>,
+    pub list_duplicates: Option<String>,
+    /// Overridden by any log option on the command line
+    pub log_level: Option<String>,
+    pub color: Option<String>,
+}
+
+/// A problem with the config file, pointing at where it is when possible
+#[derive(Debug, PartialEq)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "config file {}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ", line {}", line)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// A config setting turned into command line arguments
+struct Setting {
+    flag: &'static str,
+    values: Vec<String>,
+    /// Settings are left out when any of these are given, on the command line or otherwise,
+    /// settings that conflict with other arguments are left out by `Config::apply`
+    overridden_by: &'static [&'static str],
+    env: Option<&'static str>,
+}
+
+impl Config {
+    /// Reads and validates the config file, if there is one
+    pub fn load() -> Result<Option<Config>, ConfigError> {
+        let path = match std::env::var_os(ENV) {
+            Some(path) => PathBuf::from(path),
+            None => match crate::paths::config_file() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(None),
+            },
+        };
+        let contents = std::fs::read_to_string(&path).map_err(|e| ConfigError {
+            path: path.clone(),
+            line: None,
+            message: e.to_string(),
+        })?;
+        Config::parse(&contents, &path).map(Some)
+    }
+
+    /// Parses and validates a config file, `path` is only used for diagnostics
+    pub fn parse(contents: &str, path: &Path) -> Result<Config, ConfigError> {
+        let error = |key: Option<&str>, message: String| ConfigError {
+            path: path.to_owned(),
+            line: key.and_then(|key| line_of(contents, key)),
+            message,
+        };
+
+        // Unknown keys and type mismatches are reported by serde, with the line they're on
+        let config: Config = serde_json::from_str(contents).map_err(|e| ConfigError {
+            path: path.to_owned(),
+            line: Some(e.line()).filter(|&line| line > 0),
+            message: strip_position(&e.to_string()),
+        })?;
+
+        if let Some(output) = &config.output {
+            if !OutputMode::NAMES.contains(&output.as_str()) {
+                return Err(error(
+                    Some("output"),
+                    format!(
+                        "invalid output '{}', expected one of: {}",
+                        output,
+                        OutputMode::NAMES.join(", ")
+                    ),
+                ));
+            }
+        }
+        for (key, set) in [
+            ("table-width", config.table_width.is_some()),
+            ("max-title-width", config.max_title_width.is_some()),
+            ("width-mode", config.width_mode.is_some()),
+        ] {
+            if set && config.output.as_deref().is_some_and(|o| o != "table") {
+                return Err(error(
+                    Some(key),
+                    format!(
+                        "{} conflicts with output '{}', it only applies to tables",
+                        key,
+                        config.output.as_deref().unwrap_or_default()
+                    ),
+                ));
+            }
+        }
+        if config.max_title_width == Some(0) {
+            return Err(error(
+                Some("max-title-width"),
+                "max-title-width must be a positive, non-zero integer".to_owned(),
+            ));
+        }
+        if let Some(value) = &config.csv_delimiter {
+            csv_delimiter(value).map_err(|e| error(Some("csv-delimiter"), e))?;
+        }
+        if let Some(value) = &config.width_mode {
+            value
+                .parse::<WidthMode>()
+                .map_err(|e| error(Some("width-mode"), e))?;
+        }
+        if let Some(value) = &config.csv_quote_style {
+            if !CSV_QUOTE_STYLES.contains(&value.as_str()) {
+                return Err(error(
+                    Some("csv-quote-style"),
+                    format!(
+                        "invalid quote style '{}', expected one of: {}",
+                        value,
+                        CSV_QUOTE_STYLES.join(", ")
+                    ),
+                ));
+            }
+        }
+        if config.min_popularity == Some(0) {
+            return Err(error(
+                Some("min-popularity"),
+                "min-popularity must be a positive, non-zero integer".to_owned(),
+            ));
+        }
+        if config.hipster && config.min_popularity.is_none() {
+            return Err(error(
+                Some("hipster"),
+                "hipster needs a min-popularity to pick shows below".to_owned(),
+            ));
+        }
+        if let Some(value) = &config.log_level {
+            if !LEVELS.contains(&value.as_str()) {
+                return Err(error(
+                    Some("log-level"),
+                    format!(
+                        "invalid log level '{}', expected one of: {}",
+                        value,
+                        LEVELS.join(", ")
+                    ),
+                ));
+            }
+        }
+        if let Some(value) = &config.color {
+            if !Coloring::NAMES.contains(&value.as_str()) {
+                return Err(error(
+                    Some("color"),
+                    format!(
+                        "invalid color '{}', expected one of: {}",
+                        value,
+                        Coloring::NAMES.join(", ")
+                    ),
+                ));
+            }
+        }
+        if let Some(value) = &config.song_lang {
+            value
+                .parse::<SongLang>()
+                .map_err(|e| error(Some("song-lang"), e))?;
+        }
+        if let Some(value) = &config.readable_format {
+            readable_format_validate(value.clone())
+                .map_err(|e| error(Some("readable-format"), e))?;
+        }
+        for value in &config.type_colors {
+            type_color_validate(value.clone()).map_err(|e| error(Some("type-colors"), e))?;
+        }
+        for value in &config.quotas {
+            quota_validate(value.clone()).map_err(|e| error(Some("quotas"), e))?;
+        }
+        for value in &config.type_weights {
+            type_weight_validate(value.clone()).map_err(|e| error(Some("type-weights"), e))?;
+        }
+        for value in &config.interleave_types {
+            song_type_validate(value.clone()).map_err(|e| error(Some("interleave-types"), e))?;
+        }
+        for value in &config.link_templates {
+            link_template_validate(value.clone()).map_err(|e| error(Some("link-templates"), e))?;
+        }
+        for value in &config.status {
+            value
+                .parse::<WatchStatus>()
+                .map_err(|e| error(Some("status"), e))?;
+        }
+        if let Some(value) = &config.no_adjacent {
+            value
+                .parse::<Adjacency>()
+                .map_err(|e| error(Some("no-adjacent"), e))?;
+        }
+        if let Some(value) = &config.order_themes {
+            value
+                .parse::<ThemeOrder>()
+                .map_err(|e| error(Some("order-themes"), e))?;
+        }
+        if let Some(value) = &config.sort {
+            value
+                .parse::<SortKey>()
+                .map_err(|e| error(Some("sort"), e))?;
+        }
+        if config.reverse && config.sort.is_none() {
+            return Err(error(
+                Some("reverse"),
+                "reverse needs a sort to reverse".to_owned(),
+            ));
+        }
+        if let Some(value) = &config.list_duplicates {
+            value
+                .parse::<ListDuplicates>()
+                .map_err(|e| error(Some("list-duplicates"), e))?;
+        }
+
+        // Links have to refer to a built-in kind or one defined in the config
+        let mut kinds: Vec<String> = LinkTemplate::built_in()
+            .into_iter()
+            .map(|t| t.kind)
+            .collect();
+        for template in &config.link_templates {
+            // Validated above
+            kinds.push(template.parse::<LinkTemplate>().unwrap().kind);
+        }
+        for name in &config.columns {
+            if !Column::NAMES.contains(&name.as_str()) {
+                return Err(error(
+                    Some("columns"),
+                    format!(
+                        "unknown column '{}', expected one of: {}",
+                        name,
+                        Column::NAMES.join(", ")
+                    ),
+                ));
+            }
+        }
+        for kind in &config.links {
+            if !kinds.contains(kind) {
+                return Err(error(
+                    Some("links"),
+                    format!(
+                        "unknown link kind '{}', expected one of: {} (or define it in link-templates)",
+                        kind,
+                        kinds.join(", ")
+                    ),
+                ));
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn settings(&self) -> Vec<Setting> {
+        let mut settings = vec![];
+        let mut add = |flag, values: Vec<String>, overridden_by, env| {
+            if !values.is_empty() {
+                settings.push(Setting {
+                    flag,
+                    values,
+                    overridden_by,
+                    env,
+                });
+            }
+        };
+        let display: &[&str] = &[
+            "--table",
+            "-t",
+            "--readable",
+            "--plain",
+            "--csv",
+            "--tsv",
+            "--markdown",
+            "--json",
+            "--ndjson",
+            "--msgpack",
+            "--m3u",
+            "--sql",
+            "--emit-shell",
+            "--format",
+            "--format-file",
+            "--template",
+        ];
+
+        add(
+            "--data-dir",
+            self.data_dir
+                .iter()
+                .map(|d| d.display().to_string())
+                .collect(),
+            &["--data-dir"],
+            Some("RANDOM_SHOW_THEMES_DATA_DIR"),
+        );
+        if let Some(output) = &self.output {
+            let flag = match output.as_str() {
+                "table" => "--table",
+                "plain" => "--plain",
+                "csv" => "--csv",
+                "tsv" => "--tsv",
+                "markdown" => "--markdown",
+                "json" => "--json",
+                "ndjson" => "--ndjson",
+                "msgpack" => "--msgpack",
+                "m3u" => "--m3u",
+                "sql" => "--sql",
+                "emit-shell" => "--emit-shell",
+                _ => "--readable",
+            };
+            add(flag, vec![String::new()], display, None);
+        }
+        add(
+            "--log-level",
+            self.log_level.iter().cloned().collect(),
+            &["--log-level", "--log", "-q", "--quiet", "-v"],
+            None,
+        );
+        add(
+            "--color",
+            self.color.iter().cloned().collect(),
+            &["--color"],
+            None,
+        );
+        add(
+            "--csv-delimiter",
+            self.csv_delimiter.iter().cloned().collect(),
+            &["--csv-delimiter"],
+            None,
+        );
+        add(
+            "--csv-quote-style",
+            self.csv_quote_style.iter().cloned().collect(),
+            &["--csv-quote-style"],
+            None,
+        );
+        add(
+            "--table-width",
+            self.table_width.iter().map(|w| w.to_string()).collect(),
+            &["--table-width"],
+            None,
+        );
+        add(
+            "--max-title-width",
+            self.max_title_width.iter().map(|w| w.to_string()).collect(),
+            &["--max-title-width"],
+            None,
+        );
+        add(
+            "--width-mode",
+            self.width_mode.iter().cloned().collect(),
+            &["--width-mode"],
+            None,
+        );
+        add(
+            "--song-lang",
+            self.song_lang.iter().cloned().collect(),
+            &["--song-lang"],
+            None,
+        );
+        add(
+            "--readable-format",
+            self.readable_format.iter().cloned().collect(),
+            &["--readable-format"],
+            None,
+        );
+        add(
+            "--type-color",
+            self.type_colors.clone(),
+            &["--type-color"],
+            None,
+        );
+        add("--columns", self.columns.clone(), &["--columns"], None);
+        if self.numbered {
+            add("--numbered", vec![String::new()], &["--no-numbered"], None);
+        }
+        if self.group_by_show {
+            add(
+                "--group-by-show",
+                vec![String::new()],
+                &["--no-group-by-show"],
+                None,
+            );
+        }
+        if self.summary {
+            add("--summary", vec![String::new()], &["--no-summary"], None);
+        }
+        add(
+            "--sort",
+            self.sort.iter().cloned().collect(),
+            &["--sort"],
+            None,
+        );
+        if self.reverse {
+            add("--reverse", vec![String::new()], &["--no-reverse"], None);
+        }
+        add("--links", self.links.clone(), &["--links"], None);
+        add(
+            "--link-template",
+            self.link_templates.clone(),
+            &["--link-template"],
+            None,
+        );
+        add(
+            "--custom-column",
+            self.custom_columns.clone(),
+            &["--custom-column"],
+            None,
+        );
+        add(
+            "--notes",
+            self.notes.iter().cloned().collect(),
+            &["--notes"],
+            None,
+        );
+        add("--quota", self.quotas.clone(), &["--quota"], None);
+        add(
+            "--type-weight",
+            self.type_weights.clone(),
+            &["--type-weight"],
+            None,
+        );
+        add(
+            "--interleave-types",
+            self.interleave_types.clone(),
+            &["--interleave-types"],
+            None,
+        );
+        add("--status", self.status.clone(), &["--status"], None);
+        if self.family_friendly {
+            add(
+                "--family-friendly",
+                vec![String::new()],
+                &["--no-family-friendly"],
+                None,
+            );
+        }
+        add(
+            "--min-popularity",
+            self.min_popularity.iter().map(|m| m.to_string()).collect(),
+            &["--min-popularity"],
+            None,
+        );
+        if self.hipster {
+            add("--hipster", vec![String::new()], &["--no-hipster"], None);
+        }
+        if self.unique_artist {
+            add(
+                "--unique-artist",
+                vec![String::new()],
+                &["--no-unique-artist"],
+                None,
+            );
+        }
+        add(
+            "--no-adjacent",
+            self.no_adjacent.iter().cloned().collect(),
+            &["--no-adjacent"],
+            None,
+        );
+        add(
+            "--order-themes",
+            self.order_themes.iter().cloned().collect(),
+            &["--order-themes"],
+            None,
+        );
+        add(
+            "--list-duplicates",
+            self.list_duplicates.iter().cloned().collect(),
+            &["--list-duplicates"],
+            None,
+        );
+        settings
+    }
+
+    /// Appends the settings as arguments of the `pick` subcommand, unless the command line or the
+    /// environment already sets them
+    ///
+    /// They go at the end so options with several values can't take positional arguments. Settings
+    /// that conflict with the command line, e.g. an output with `--all`, are left out, and so are
+    /// settings that would turn a valid command line into an invalid one, e.g. a table width when
+    /// the command line asks for csv. The command line always wins.
+    pub fn apply(&self, args: &mut Vec<OsString>) {
+        match crate::subcommand_position(args) {
+            Some(i) if args[i] == "pick" || args[i] == "p" => {}
+            _ => return,
+        }
+        // Global options, like the log ones, can also come before the subcommand
+        let given = |flag: &str| {
+            args[1..].iter().any(|arg| {
+                let arg = arg.to_string_lossy();
+                arg == flag
+                    || arg.starts_with(&format!("{}=", flag))
+                    || is_repeated_flag(&arg, flag)
+            })
+        };
+
+        let app = crate::create_clap_app();
+        let parse = |args: &[OsString]| {
+            app.clone()
+                .get_matches_from_safe(args)
+                .map(|_| ())
+                .map_err(|e| e.kind)
+        };
+        let mut applied = args.clone();
+        let mut valid = parse(&applied).is_ok();
+        for setting in self.settings() {
+            if setting.overridden_by.iter().any(|flag| given(flag))
+                || setting
+                    .env
+                    .is_some_and(|env| std::env::var_os(env).is_some())
+            {
+                continue;
+            }
+            let mut with_setting = applied.clone();
+            for value in setting.values {
+                with_setting.push(setting.flag.into());
+                if !value.is_empty() {
+                    with_setting.push(value.into());
+                }
+            }
+            // A command line that is invalid without the setting may need it, e.g. --reverse
+            // with the sort of the config
+            match parse(&with_setting) {
+                Ok(()) => valid = true,
+                Err(clap::ErrorKind::ArgumentConflict) => continue,
+                Err(_) if valid => continue,
+                Err(_) => {}
+            }
+            applied = with_setting;
+        }
+        *args = applied;
+    }
+}
+
+/// Checks if `arg` is the short flag `flag` given more than once, e.g. `-vv` for `-v`
+fn is_repeated_flag(arg: &str, flag: &str) -> bool {
+    match (arg.strip_prefix('-'), flag.strip_prefix('-')) {
+        (Some(repeats), Some(letter)) if letter.len() == 1 && !repeats.is_empty() => {
+            repeats.chars().all(|c| letter.starts_with(c))
+        }
+        _ => false,
+    }
+}
+
+/// The 1-based line a key is defined on
+fn line_of(contents: &str, key: &str) -> Option<usize> {
+    let quoted = format!("\"{}\"", key);
+    contents
+        .lines()
+        .position(|line| line.contains(&quoted))
+        .map(|i| i + 1)
+}
+
+/// Removes the " at line N column M" serde adds, since the line is reported separately
+fn strip_position(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(i) => message[..i].to_owned(),
+        None => message.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Result<Config, ConfigError> {
+        Config::parse(contents, Path::new("config.json"))
+    }
+
+    #[test]
+    fn config_diagnostics() {
+        let error = parse("{\n  \"output\": \"table\",\n  \"colums\": []\n}").unwrap_err();
+        assert_eq!(error.line, Some(3));
+        assert!(error.message.starts_with("unknown field `colums`"));
+
+        let error = parse("{\n  \"table-width\": \"wide\"\n}").unwrap_err();
+        assert_eq!(error.line, Some(2));
+        assert!(error.message.starts_with("invalid type"));
+
+        let error = parse("{\n  \"output\": \"csv\",\n  \"table-width\": 80\n}").unwrap_err();
+        assert_eq!(error.line, Some(3));
+        assert_eq!(
+            error.to_string(),
+            "config file config.json, line 3: table-width conflicts with output 'csv', it only applies to tables"
+        );
+        let error = parse("{\"output\": \"json\", \"max-title-width\": 40}").unwrap_err();
+        assert!(error
+            .message
+            .starts_with("max-title-width conflicts with output 'json'"));
+        let error = parse("{\"width-mode\": \"wide\"}").unwrap_err();
+        assert!(error.message.starts_with("invalid width mode 'wide'"));
+
+        let error = parse("{\"links\": [\"anidb\"]}").unwrap_err();
+        assert!(error.message.starts_with("unknown link kind 'anidb'"));
+        assert!(parse(
+            r#"{"links": ["anidb"], "link-templates": ["anidb=https://anidb.net/a{id}"]}"#
+        )
+        .is_ok());
+        assert!(parse(r#"{"quotas": ["genre:Music"]}"#).is_err());
+        assert!(parse(r#"{"song-lang": "kanji"}"#).is_err());
+        assert!(parse(r#"{"log-level": "off"}"#).is_err());
+        assert!(parse(r#"{"color": "sometimes"}"#).is_err());
+        assert!(parse(r#"{"columns": ["song", "studio"]}"#).is_err());
+    }
+
+    /// The arguments `config` adds to the command line `args`
+    fn applied(config: &Config, args: &[&str]) -> Vec<OsString> {
+        let mut applied: Vec<OsString> = args.iter().map(OsString::from).collect();
+        config.apply(&mut applied);
+        applied.split_off(args.len())
+    }
+
+    #[test]
+    fn config_layering() {
+        let config = parse(
+            r#"{"output": "table", "table-width": 100, "quotas": ["genre:Music=1"], "family-friendly": true, "order-themes": "earliest"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            applied(
+                &config,
+                &[
+                    "prog",
+                    "-v",
+                    "pick",
+                    "5",
+                    "--demo",
+                    "--order-themes",
+                    "latest"
+                ]
+            ),
+            [
+                "--table",
+                "--table-width",
+                "100",
+                "--quota",
+                "genre:Music=1",
+                "--family-friendly"
+            ]
+        );
+
+        // Another output on the command line drops the table and its width
+        assert_eq!(
+            applied(
+                &config,
+                &[
+                    "prog",
+                    "pick",
+                    "5",
+                    "--demo",
+                    "--csv",
+                    "--sample-with-replacement"
+                ]
+            ),
+            ["--family-friendly", "--order-themes", "earliest"]
+        );
+
+        // Log options anywhere on the command line win over the log level of the config
+        let config = parse(r#"{"log-level": "error"}"#).unwrap();
+        assert!(applied(&config, &["prog", "-vv", "pick", "5", "--demo"]).is_empty());
+        assert_eq!(
+            applied(&config, &["prog", "pick", "5", "--demo"]),
+            ["--log-level", "error"]
+        );
+
+        // Only pick is configured
+        assert!(applied(&config, &["prog", "selftest"]).is_empty());
+    }
+
+    #[test]
+    fn config_conflicts() {
+        // Settings that conflict with the command line are left out
+        let config =
+            parse(r#"{"output": "table", "sort": "song", "group-by-show": true}"#).unwrap();
+        assert!(applied(&config, &["prog", "pick", "--demo", "--all", "--csv"]).is_empty());
+        assert_eq!(
+            applied(&config, &["prog", "pick", "--demo", "--all"]),
+            ["--group-by-show"]
+        );
+        assert_eq!(
+            applied(&config, &["prog", "pick", "5", "--demo", "--csv"]),
+            ["--sort", "song"]
+        );
+
+        let config = parse(r#"{"quotas": ["genre:Music=1"], "unique-artist": true}"#).unwrap();
+        for strategy in [
+            ["--sample-with-replacement"].as_slice(),
+            ["--pick-strategy", "with-replacement"].as_slice(),
+        ] {
+            let mut args = vec!["prog", "pick", "5", "--demo"];
+            args.extend(strategy);
+            assert!(applied(&config, &args).is_empty());
+        }
+
+        // Given twice, flags would be an error
+        let config = parse(r#"{"numbered": true, "reverse": true, "sort": "show"}"#).unwrap();
+        assert_eq!(
+            applied(&config, &["prog", "pick", "5", "--demo", "--numbered"]),
+            ["--sort", "show", "--reverse"]
+        );
+        // A setting the command line needs is kept
+        assert_eq!(
+            applied(&config, &["prog", "pick", "5", "--demo", "--reverse"]),
+            ["--numbered", "--sort", "show"]
+        );
+
+        // Every boolean can be turned off
+        let config = parse(
+            r#"{"numbered": true, "group-by-show": true, "summary": true, "family-friendly": true,
+                "min-popularity": 100, "hipster": true, "unique-artist": true, "sort": "song", "reverse": true}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            applied(
+                &config,
+                &[
+                    "prog",
+                    "pick",
+                    "5",
+                    "--demo",
+                    "--no-numbered",
+                    "--no-group-by-show",
+                    "--no-summary",
+                    "--no-family-friendly",
+                    "--no-hipster",
+                    "--no-unique-artist",
+                    "--no-reverse",
+                ]
+            ),
+            ["--sort", "song", "--min-popularity", "100"]
+        );
+    }
+}
@@ -0,0 +1,39 @@
+//! A small dataset built into the program, so `--demo` can be tried without any data files
+
+use std::collections::HashMap;
+
+use crate::list::ListEntry;
+use crate::{Show, ShowId};
+
+/// What the demo data files are called wherever data files are named, like by `--check`
+pub const DICTIONARY_NAME: &str = "(demo dictionary)";
+pub const LIST_NAME: &str = "(demo list)";
+
+const DICTIONARY: &str = include_str!("../demo/dictionary.json");
+const LIST: &str = include_str!("../demo/list.json");
+
+pub fn dictionary() -> HashMap<ShowId, Show> {
+    serde_json::from_str(DICTIONARY).expect("the demo dictionary is valid")
+}
+
+pub fn list() -> Vec<ListEntry> {
+    serde_json::from_str(LIST).expect("the demo list is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_data() {
+        let dictionary = dictionary();
+        let list = list();
+        assert!(list.iter().all(|entry| dictionary.contains_key(&entry.id)));
+
+        // Every type of theme, to show off every output
+        let shows = || list.iter().map(|entry| &dictionary[&entry.id]);
+        assert!(shows().any(|show| !show.opening_themes.is_empty()));
+        assert!(shows().any(|show| !show.ending_themes.is_empty()));
+        assert!(shows().any(|show| !show.other_soundtrack.is_empty()));
+    }
+}
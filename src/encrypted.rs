@@ -0,0 +1,105 @@
+use std::error::Error;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
+
+/// The tools data files can be encrypted with, recognised by the extension of the file
+#[derive(Debug, PartialEq)]
+pub enum Encryption {
+    /// `.age`, decrypted with `age` and the configured identity file
+    Age,
+    /// `.gpg` or `.asc`, decrypted with `gpg` and whatever key the keyring has for it
+    Gpg,
+}
+
+impl Encryption {
+    pub fn of(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "age" => Some(Self::Age),
+            "gpg" | "asc" => Some(Self::Gpg),
+            _ => None,
+        }
+    }
+}
+
+/// Reads a JSON data file like `crate::read_json_file`, decrypting it first if it's encrypted
+///
+/// The decrypted contents are only ever kept in memory.
+pub fn read_json_file<P, T>(path: P, identity: Option<&str>) -> Result<T, Box<dyn Error>>
+where
+    P: AsRef<Path>,
+    for<'de> T: Deserialize<'de>,
+{
+    let path = path.as_ref();
+    match Encryption::of(path) {
+        Some(encryption) => Ok(serde_json::from_slice(&decrypt(
+            path, encryption, identity,
+        )?)?),
+        None => crate::read_json_file(path),
+    }
+}
+
+fn decrypt(
+    path: &Path,
+    encryption: Encryption,
+    identity: Option<&str>,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut command = match encryption {
+        Encryption::Age => {
+            let identity = identity.ok_or_else(|| {
+                format!(
+                    "{} is encrypted with age, an identity file is needed to decrypt it",
+                    path.display()
+                )
+            })?;
+            let mut command = Command::new("age");
+            command.arg("--decrypt").arg("--identity").arg(identity);
+            command
+        }
+        Encryption::Gpg => {
+            let mut command = Command::new("gpg");
+            command.args(["--decrypt", "--quiet", "--batch"]);
+            command
+        }
+    };
+    command.arg(path).stderr(Stdio::inherit());
+
+    let output = command
+        .output()
+        .map_err(|e| format!("couldn't start {:?}: {}", command.get_program(), e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "couldn't decrypt {}, {:?} exited with {}",
+            path.display(),
+            command.get_program(),
+            output.status
+        )
+        .into());
+    }
+
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encryption_by_extension() {
+        assert_eq!(
+            Encryption::of(Path::new("list.json.age")),
+            Some(Encryption::Age)
+        );
+        assert_eq!(
+            Encryption::of(Path::new("list.json.gpg")),
+            Some(Encryption::Gpg)
+        );
+        assert_eq!(
+            Encryption::of(Path::new("list.json.asc")),
+            Some(Encryption::Gpg)
+        );
+        assert_eq!(Encryption::of(Path::new("list.json")), None);
+        assert_eq!(Encryption::of(Path::new("age")), None);
+    }
+}
@@ -0,0 +1,208 @@
+use std::error::Error;
+use std::process::Command;
+
+use crate::columns::Column;
+use crate::links::{placeholder_value, PLACEHOLDERS};
+use crate::selection::Pick;
+use crate::shell;
+use crate::theme::SongLang;
+
+/// The environment variable a placeholder is passed in, e.g. `PICK_SONG` for `{song}`
+fn variable(placeholder: &str) -> String {
+    format!("PICK_{}", placeholder.to_uppercase())
+}
+
+/// Turns the placeholders of an `--exec` template into references to the environment variables
+/// holding their values
+///
+/// Values are never pasted into the command itself, so whatever a title contains can't be run
+/// as part of the command. Placeholders should be quoted like any other variable, e.g. `"{song}"`.
+/// Any other braces are left alone, since they mean something to the shell.
+fn command_line(template: &str) -> String {
+    PLACEHOLDERS
+        .iter()
+        .fold(template.to_owned(), |line, placeholder| {
+            let reference = format!("${{{}}}", variable(placeholder));
+            line.replace(&format!("{{{}}}", placeholder), &reference)
+        })
+}
+
+/// Splits an `--exec` template into the program and its arguments, and fills in the placeholders
+/// within each of them
+///
+/// For Windows, where `cmd` expands variables before it parses the command, so a title with `&`
+/// or `|` would be run as part of it. Arguments are separated by whitespace and can be quoted
+/// with double or single quotes, there is nothing else a shell would do.
+fn arguments(template: &str, value: impl Fn(&str) -> String) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in template.chars() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => word.get_or_insert_with(String::new).push(c),
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, _) if c.is_whitespace() => words.extend(word.take()),
+            (None, _) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err(format!("unclosed quote in '{}'", template));
+    }
+    words.extend(word);
+    if words.is_empty() {
+        return Err("the command is empty".to_owned());
+    }
+
+    // Filled in after splitting, so values are always a single argument
+    Ok(words
+        .into_iter()
+        .map(|word| {
+            PLACEHOLDERS.iter().fold(word, |word, placeholder| {
+                word.replace(&format!("{{{}}}", placeholder), &value(placeholder))
+            })
+        })
+        .collect())
+}
+
+pub(crate) fn shell(command_line: &str) -> Command {
+    if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(command_line);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(command_line);
+        command
+    }
+}
+
+fn run(mut command: Command, command_line: &str) -> Result<(), Box<dyn Error>> {
+    let status = command
+        .status()
+        .map_err(|e| format!("couldn't run '{}': {}", command_line, e))?;
+    if !status.success() {
+        return Err(format!("'{}' exited with {}", command_line, status).into());
+    }
+    Ok(())
+}
+
+/// Runs the `--exec` template for the pick at the 1-based position `number`, with the song
+/// title in the script of `song_lang`
+pub fn exec(
+    template: &str,
+    number: usize,
+    pick: &Pick,
+    song_lang: SongLang,
+) -> Result<(), Box<dyn Error>> {
+    let value = |placeholder: &str| placeholder_value(placeholder, pick, song_lang);
+    let (mut command, command_line) = if cfg!(windows) {
+        // Without cmd, which would run whatever the values contain
+        let arguments = arguments(template, value)?;
+        let mut command = Command::new(&arguments[0]);
+        command.args(&arguments[1..]);
+        (command, arguments.join(" "))
+    } else {
+        let command_line = command_line(template);
+        (shell(&command_line), command_line)
+    };
+    command.env("PICK_NUMBER", number.to_string());
+    for placeholder in PLACEHOLDERS {
+        command.env(variable(placeholder), value(placeholder));
+    }
+    run(command, &command_line)
+}
+
+/// Runs the `--exec-after` command once for all picks
+///
+/// The picks are passed in the same variables `--emit-shell` outputs. On Windows the command is
+/// run without `cmd` like `--exec`, so only the program it runs can read them.
+pub fn exec_after(
+    command_line: &str,
+    picks: &[Pick],
+    columns: &[Column],
+) -> Result<(), Box<dyn Error>> {
+    let mut command = if cfg!(windows) {
+        let arguments = arguments(command_line, |_| String::new())?;
+        let mut command = Command::new(&arguments[0]);
+        command.args(&arguments[1..]);
+        command
+    } else {
+        shell(command_line)
+    };
+    command.env("PICK_COUNT", picks.len().to_string());
+    for (i, pick) in picks.iter().enumerate() {
+        for column in columns {
+            command.env(
+                shell::variable_name(i + 1, column.header()),
+                column.value(i + 1, pick),
+            );
+        }
+    }
+    run(command, command_line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::fixtures;
+
+    #[test]
+    fn splitting_arguments() {
+        let value = |placeholder: &str| match placeholder {
+            "song" => "Tank! & rm -rf".to_owned(),
+            _ => "Cowboy \"Bebop\"".to_owned(),
+        };
+        assert_eq!(
+            arguments(r#"notify-send "{song}" '{show}' {id}.txt {print}"#, value).unwrap(),
+            [
+                "notify-send",
+                "Tank! & rm -rf",
+                "Cowboy \"Bebop\"",
+                "Cowboy \"Bebop\".txt",
+                "{print}"
+            ]
+        );
+        assert_eq!(
+            arguments(r#"  say  ""  "it's" "#, value).unwrap(),
+            ["say", "", "it's"]
+        );
+        assert!(arguments("say \"{song}", value).is_err());
+        assert!(arguments("  ", value).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exec_commands() {
+        assert_eq!(
+            command_line(r#"notify-send "{song}" "{show}""#),
+            r#"notify-send "${PICK_SONG}" "${PICK_SHOW}""#
+        );
+        assert_eq!(
+            command_line("awk '{print}' {id}.txt"),
+            "awk '{print}' ${PICK_ID}.txt"
+        );
+
+        let show = fixtures::show("$(false); \"Show\"", "#1: \"Song\" by Artist");
+        let pick = fixtures::opening(&show);
+        // The title is passed as is instead of being run
+        exec(
+            r#"test "{show}" = '$(false); "Show"' && test "{type}" = OP && test "$PICK_NUMBER" = 2"#,
+            2,
+            &pick,
+            SongLang::Both,
+        )
+        .unwrap();
+        assert!(exec("exit 3", 1, &pick, SongLang::Both).is_err());
+
+        exec_after(
+            r#"test "$PICK_COUNT" = 1 && test "$PICK_1_SONG" = '#1: "Song" by Artist'"#,
+            &[pick],
+            &Column::DEFAULT,
+        )
+        .unwrap();
+    }
+}
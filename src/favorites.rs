@@ -0,0 +1,56 @@
+use std::error::Error;
+use std::path::Path;
+
+use crate::list::ListEntry;
+use crate::{read_json_file, ShowId};
+
+/// The name of the favorites list in the data directory
+pub const FILE_NAME: &str = "favorites.json";
+
+/// Reads the favorites list, which is empty until the first show is added
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<ListEntry>, Box<dyn Error>> {
+    if path.as_ref().exists() {
+        read_json_file(path)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Adds the show to the end of the favorites, returns false if it already was a favorite
+pub fn add(favorites: &mut Vec<ListEntry>, id: ShowId) -> bool {
+    if favorites.iter().any(|entry| entry.id == id) {
+        return false;
+    }
+    favorites.push(id.into());
+    true
+}
+
+/// Removes the show from the favorites, returns false if it wasn't a favorite
+pub fn remove(favorites: &mut Vec<ListEntry>, id: &ShowId) -> bool {
+    let len = favorites.len();
+    favorites.retain(|entry| entry.id != *id);
+    favorites.len() != len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::list::WatchStatus;
+
+    #[test]
+    fn managing_favorites() {
+        let mut favorites: Vec<ListEntry> =
+            serde_json::from_str(r#"[1, {"id": "my-show", "status": "on_hold"}]"#).unwrap();
+        assert!(add(&mut favorites, ShowId::from("2")));
+        assert!(!add(&mut favorites, ShowId::from("my-show")));
+        assert!(remove(&mut favorites, &ShowId::from("1")));
+        assert!(!remove(&mut favorites, &ShowId::from("1")));
+
+        // Metadata of the remaining entries is kept
+        assert_eq!(favorites[0].status, Some(WatchStatus::OnHold));
+        assert_eq!(
+            serde_json::to_string(&favorites).unwrap(),
+            r#"[{"id":"my-show","status":"on-hold"},2]"#
+        );
+    }
+}
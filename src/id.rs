@@ -0,0 +1,120 @@
+use std::fmt;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+/// The key of a show, either a number (like MAL ids) or any other string (like slugs)
+///
+/// Numeric ids are normalised, so `24833`, `"24833"` and `"024833"` are the same id.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShowId(String);
+
+impl ShowId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The numeric value of the id, if it has one
+    pub fn as_number(&self) -> Option<u64> {
+        self.0.parse().ok()
+    }
+}
+
+impl From<&str> for ShowId {
+    fn from(id: &str) -> Self {
+        match id.parse::<u64>() {
+            Ok(number) => ShowId::from(number),
+            Err(_) => ShowId(id.to_owned()),
+        }
+    }
+}
+
+impl From<u64> for ShowId {
+    fn from(id: u64) -> Self {
+        ShowId(id.to_string())
+    }
+}
+
+impl From<usize> for ShowId {
+    fn from(id: usize) -> Self {
+        ShowId(id.to_string())
+    }
+}
+
+impl fmt::Display for ShowId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for ShowId {
+    /// Numeric ids are written as numbers to stay compatible with existing files
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.as_number() {
+            Some(number) => serializer.serialize_u64(number),
+            None => serializer.serialize_str(&self.0),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ShowId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ShowIdVisitor;
+
+        impl<'de> Visitor<'de> for ShowIdVisitor {
+            type Value = ShowId;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a positive integer or a string")
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<ShowId, E> {
+                Ok(ShowId::from(value))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<ShowId, E> {
+                if value < 0 {
+                    return Err(E::invalid_value(de::Unexpected::Signed(value), &self));
+                }
+                Ok(ShowId::from(value as u64))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<ShowId, E> {
+                if value.is_empty() {
+                    return Err(E::invalid_value(de::Unexpected::Str(value), &self));
+                }
+                Ok(ShowId::from(value))
+            }
+        }
+
+        deserializer.deserialize_any(ShowIdVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn show_ids() {
+        let ids: Vec<ShowId> =
+            serde_json::from_str(r#"[24833, "24833", "024833", "my-show"]"#).unwrap();
+        assert_eq!(ids[0], ids[1]);
+        assert_eq!(ids[0], ids[2]);
+        assert_eq!(ids[3].as_str(), "my-show");
+        assert_eq!(ids[3].as_number(), None);
+        assert_eq!(
+            serde_json::to_string(&ids).unwrap(),
+            r#"[24833,24833,24833,"my-show"]"#
+        );
+
+        let map: HashMap<ShowId, u8> = serde_json::from_str(r#"{"1": 1, "slug": 2}"#).unwrap();
+        assert_eq!(map[&ShowId::from(1usize)], 1);
+        assert_eq!(map[&ShowId::from("slug")], 2);
+
+        assert!(serde_json::from_str::<ShowId>("-1").is_err());
+        assert!(serde_json::from_str::<ShowId>(r#""""#).is_err());
+        assert!(serde_json::from_str::<ShowId>("1.5").is_err());
+    }
+}
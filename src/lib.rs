@@ -1,19 +1,82 @@
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::ffi::OsString;
 use std::fs::File;
-use std::io::BufReader;
-use std::path::Path;
+use std::io::{self, BufReader, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use clap::{
-    crate_authors, crate_description, crate_name, crate_version, App, Arg, ArgGroup, ArgMatches,
+    crate_authors, crate_description, crate_name, crate_version, App, AppSettings, Arg, ArgGroup,
+    ArgMatches, ArgSettings, SubCommand,
 };
-use serde::Deserialize;
-use term_table::{row::Row, table_cell::TableCell, Table, TableStyle};
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug, Hash, Eq, PartialEq)]
+use columns::Column;
+pub use id::ShowId;
+use list::{ListDuplicates, WatchStatus};
+use log::LevelFilter;
+use logging::{log_spec_validate, LogSpec, ScopedLogger, LEVELS};
+use selection::{Pick, SelectionStrategy};
+use sort::SortKey;
+use style::{readable_format_validate, type_color_validate, Coloring, ReadableStyle};
+use table::{Table, WidthMode};
+use template::{Template, Totals};
+use theme::SongLang;
+use truncate::{truncate, ColumnWidths};
+
+#[cfg(feature = "announce")]
+pub mod announce;
+pub mod argfile;
+pub mod canonical;
+#[cfg(feature = "git")]
+pub mod changes;
+pub mod columns;
+pub mod config;
+pub mod demo;
+#[cfg(feature = "encrypted")]
+pub mod encrypted;
+pub mod exec;
+pub mod favorites;
+mod id;
+pub mod links;
+pub mod list;
+pub mod lock;
+pub mod logging;
+pub mod m3u;
+pub mod markdown;
+pub mod msgpack;
+pub mod normalize;
+pub mod notes;
+pub mod paths;
+pub mod progress;
+pub mod quota;
+pub mod receipt;
+pub mod rng;
+pub mod rss;
+pub mod selection;
+pub mod selftest;
+pub mod session;
+mod sha256;
+pub mod shell;
+pub mod sink;
+pub mod sort;
+pub mod spacing;
+pub mod sql;
+pub mod style;
+pub mod table;
+pub mod template;
+pub mod theme;
+pub mod timezone;
+pub mod truncate;
+pub mod weights;
+pub mod xlsx;
+mod xml;
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
 pub struct Show {
     #[serde(alias = "mal_id")]
-    pub id: usize,
+    pub id: ShowId,
     pub title: String,
     pub url: Option<String>,
     #[serde(default)]
@@ -22,46 +85,543 @@ pub struct Show {
     pub ending_themes: Vec<String>,
     #[serde(default, alias = "soundtrack")]
     pub other_soundtrack: Vec<String>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Shows that belong together, e.g. every season of the same series
+    pub franchise: Option<String>,
+    /// Audience rating as reported by MAL, e.g. "PG-13 - Teens 13 or older"
+    pub rating: Option<String>,
+    #[serde(default)]
+    pub nsfw: bool,
+    /// Benched by a curator: left out of selections without losing its data
+    #[serde(default)]
+    pub disabled: bool,
+    /// How many users have the show on their list, as reported by MAL (e.g. through Jikan)
+    pub members: Option<u64>,
+    /// How many users have the show as a favorite on MAL
+    pub favorites: Option<u64>,
+    /// Breaks ties in favour of shows with a higher priority, like filling quotas or settling
+    /// conflicts between picks
+    #[serde(default)]
+    pub priority: i32,
+    /// Native titles of songs whose themes only have the romanized title, by that title
+    #[serde(default)]
+    pub native_titles: BTreeMap<String, String>,
+    /// Any other fields of the dictionary entry, e.g. a studio from an enriched dataset
+    #[serde(flatten)]
+    pub custom: BTreeMap<String, serde_json::Value>,
+}
+
+impl Show {
+    /// Checks if the show is suitable for a general audience
+    ///
+    /// Shows flagged as nsfw or rated R (including R+ and Rx) are not. Unrated shows are assumed to be fine.
+    pub fn is_family_friendly(&self) -> bool {
+        let restricted = self
+            .rating
+            .as_ref()
+            .is_some_and(|rating| rating.trim_start().starts_with('R'));
+        !self.nsfw && !restricted
+    }
+
+    /// The text of a custom field, with anything but strings written as JSON and null as nothing
+    pub fn custom_field(&self, name: &str) -> Option<String> {
+        self.custom.get(name).map(|value| match value {
+            serde_json::Value::String(text) => text.clone(),
+            serde_json::Value::Null => String::new(),
+            other => other.to_string(),
+        })
+    }
+
+    /// The type of one of the show's themes: "OP", "ED" or "ST"
+    pub fn song_type(&self, song: &str) -> &'static str {
+        if self.opening_themes.iter().any(|t| t == song) {
+            "OP"
+        } else if self.ending_themes.iter().any(|t| t == song) {
+            "ED"
+        } else {
+            "ST"
+        }
+    }
 }
 
 pub enum OutputMode {
-    Table,
-    Readable,
-    CSV,
+    /// A table with the values of columns cut off at their `widths`
+    Table(ColumnWidths),
+    Readable(ReadableStyle),
+    /// Only the song of every pick, each ended by a NUL instead of a newline with `null`
+    Plain {
+        null: bool,
+    },
+    /// Records ended by a NUL instead of a newline with `null`, and values separated by
+    /// `delimiter`, below a header row unless `header` is false
+    CSV {
+        null: bool,
+        delimiter: u8,
+        quote_style: csv::QuoteStyle,
+        header: bool,
+    },
+    /// Tab-separated values without any quoting, below a header row unless `header` is false
+    Tsv {
+        header: bool,
+    },
+    /// A GitHub-flavored Markdown table, written one row at a time
+    Markdown,
+    /// A JSON array, written one element per line as the picks are output
+    Json,
+    /// An extended M3U playlist
+    M3u,
+    /// SQL statements inserting the picks into this table
+    Sql(String),
+    NdJson,
+    /// A stream of MessagePack maps, one per pick
+    MsgPack,
+    Shell,
+    Template(Template),
 }
 
 impl OutputMode {
-    pub fn from_matches(matches: &ArgMatches) -> Self {
-        if matches.is_present("table") {
-            Self::Table
-        } else if matches.is_present("readable") {
-            Self::Readable
-        } else if matches.is_present("csv") {
-            Self::CSV
-        } else {
-            Self::Readable
+    /// The names of the output modes besides templates, which are also their flags
+    pub const NAMES: &'static [&'static str] = &[
+        "table",
+        "readable",
+        "plain",
+        "csv",
+        "tsv",
+        "markdown",
+        "json",
+        "ndjson",
+        "msgpack",
+        "m3u",
+        "sql",
+        "emit-shell",
+    ];
+
+    /// Fails if the template file can't be read or isn't valid
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self, String> {
+        let song_lang = SongLang::from_matches(matches);
+        if let Some(format) = matches.value_of("format") {
+            return Ok(Self::Template(
+                Template::line(format).with_song_lang(song_lang),
+            ));
+        }
+        if let Some(path) = matches.value_of("format-file") {
+            let template = std::fs::read_to_string(path)
+                .map_err(|e| format!("couldn't read {}: {}", path, e))?;
+            return template
+                .parse()
+                .map(|template: Template| Self::Template(template.with_song_lang(song_lang)))
+                .map_err(|e| format!("invalid template {}: {}", path, e));
+        }
+        // Not a template
+        let name = Self::chosen_name(matches).unwrap();
+        if matches.is_present("null") && !["plain", "csv"].contains(&name) {
+            return Err("--null only works with --plain and --csv".to_owned());
+        }
+        if matches.is_present("no-header") && !["csv", "tsv"].contains(&name) {
+            return Err("--no-header only works with --csv and --tsv".to_owned());
+        }
+        Self::from_name(name, matches)
+    }
+
+    /// The name of the output mode the flags choose, none for templates
+    pub fn chosen_name(matches: &ArgMatches) -> Option<&'static str> {
+        if matches.is_present("format") || matches.is_present("format-file") {
+            return None;
+        }
+        Some(
+            Self::NAMES
+                .iter()
+                .find(|name| matches.is_present(name))
+                .unwrap_or(&"readable"),
+        )
+    }
+
+    /// The output mode called `name`, `matches` are only used for the style of readable output
+    /// and the separators, quoting and header of plain, CSV and TSV output
+    pub fn from_name(name: &str, matches: &ArgMatches) -> Result<Self, String> {
+        Ok(match name {
+            "table" => Self::Table(ColumnWidths::from_matches(matches)),
+            "readable" => Self::Readable(ReadableStyle::from_matches(matches)?),
+            "plain" => Self::Plain {
+                null: matches.is_present("null"),
+            },
+            "csv" => Self::CSV {
+                null: matches.is_present("null"),
+                // Validated by clap
+                delimiter: matches
+                    .value_of("csv-delimiter")
+                    .map_or(b',', |v| csv_delimiter(v).unwrap()),
+                quote_style: matches
+                    .value_of("csv-quote-style")
+                    .map_or(csv::QuoteStyle::Necessary, |v| csv_quote_style(v).unwrap()),
+                header: !matches.is_present("no-header"),
+            },
+            "tsv" => Self::Tsv {
+                header: !matches.is_present("no-header"),
+            },
+            "markdown" => Self::Markdown,
+            "json" => Self::Json,
+            "ndjson" => Self::NdJson,
+            "msgpack" => Self::MsgPack,
+            "m3u" => Self::M3u,
+            "sql" => Self::Sql(
+                matches
+                    .value_of("sql")
+                    .unwrap_or(sql::DEFAULT_TABLE)
+                    .to_owned(),
+            ),
+            "emit-shell" => Self::Shell,
+            _ => {
+                return Err(format!(
+                    "invalid output '{}', expected one of: {}",
+                    name,
+                    Self::NAMES.join(", ")
+                ))
+            }
+        })
+    }
+
+    /// Checks if the output is meant to be read by people rather than other programs, so
+    /// anything can be added to it
+    pub fn is_for_people(&self) -> bool {
+        matches!(self, Self::Table(_) | Self::Readable(_))
+    }
+
+    /// The same output without colors, for anywhere but the terminal
+    pub fn without_colors(self) -> Self {
+        match self {
+            Self::Readable(style) => Self::Readable(style.without_colors()),
+            other => other,
+        }
+    }
+
+    /// The same output mode without the header row of CSV and TSV
+    pub fn without_header(self) -> Self {
+        match self {
+            Self::CSV {
+                null,
+                delimiter,
+                quote_style,
+                ..
+            } => Self::CSV {
+                null,
+                delimiter,
+                quote_style,
+                header: false,
+            },
+            Self::Tsv { .. } => Self::Tsv { header: false },
+            other => other,
         }
     }
 }
 
+/// The names and aliases of all subcommands, `pick` is implied when none is given
+const SUBCOMMANDS: &[&str] = &[
+    "pick",
+    "p",
+    "selftest",
+    "self-test",
+    "verify",
+    "fav",
+    "disable",
+    "enable",
+    "fmt",
+    "paths",
+    "help",
+];
+
 pub fn create_clap_app<'a>() -> App<'a, 'a> {
     App::new(crate_name!())
         .about(crate_description!())
         .author(crate_authors!())
         .version(crate_version!())
+        .after_help("Arguments can also be read from a file with @file, e.g. `random-show-themes pick 10 @music-night.args`")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .setting(AppSettings::VersionlessSubcommands)
+        .args(&global_args())
+        .subcommand(pick_subcommand())
+        .subcommand(
+            SubCommand::with_name("fav")
+                .about("Manages the favorites list, favorites.json in the data directory")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .arg(
+                    Arg::with_name("data-dir")
+                        .help("Directory to find favorites.json and dictionary.json in")
+                        .long("data-dir")
+                        .env("RANDOM_SHOW_THEMES_DATA_DIR")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .subcommand(
+                    SubCommand::with_name("add")
+                        .about("Adds shows to the favorites")
+                        .arg(Arg::with_name("id").help("The ids of the shows").required(true).multiple(true)),
+                )
+                .subcommand(
+                    SubCommand::with_name("remove")
+                        .about("Removes shows from the favorites")
+                        .arg(Arg::with_name("id").help("The ids of the shows").required(true).multiple(true)),
+                )
+                .subcommand(SubCommand::with_name("list").about("Lists the favorites")),
+        )
+        .subcommand(disabled_subcommand(
+            "disable",
+            "Disables shows in the dictionary, which leaves them out of selections",
+        ))
+        .subcommand(disabled_subcommand(
+            "enable",
+            "Enables disabled shows in the dictionary again",
+        ))
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("Rewrites data files in the canonical format, to keep diffs minimal")
+                .long_about(
+"Rewrites data files in the canonical format, to keep diffs minimal
+Keys are sorted, with show ids in numeric order, genres and tags are sorted and deduplicated, and indentation is two spaces."
+                )
+                .args(&[
+                    Arg::with_name("file")
+                        .help("The data files to format")
+                        .required(true)
+                        .multiple(true),
+                    Arg::with_name("check")
+                        .help("Don't rewrite anything, exit with exit code 1 if any file isn't formatted")
+                        .long("check"),
+                ]),
+        )
+        .subcommand(
+            SubCommand::with_name("paths")
+                .about("Prints where the config file, data files and state are kept")
+                .arg(
+                    Arg::with_name("data-dir")
+                        .help("The data directory to show the data files of")
+                        .long("data-dir")
+                        .takes_value(true)
+                        .env("RANDOM_SHOW_THEMES_DATA_DIR"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Checks that the data files of a past selection haven't changed, using its receipt")
+                .args(&[
+                    Arg::with_name("receipt")
+                        .help("The receipt written with --receipt")
+                        .long("receipt")
+                        .takes_value(true)
+                        .required(true),
+                    Arg::with_name("dictionary")
+                        .help("Check this file instead of the dictionary recorded in the receipt")
+                        .takes_value(true)
+                        .short("d"),
+                    Arg::with_name("list")
+                        .help("Check this file instead of the list recorded in the receipt")
+                        .takes_value(true)
+                        .short("l"),
+                ]),
+        )
+        .subcommand(
+            SubCommand::with_name("selftest")
+                .visible_alias("self-test")
+                .about("Checks the sampler against the expected frequencies on a synthetic dictionary")
+                .args(&[
+                    Arg::with_name("iterations")
+                        .help("The number of times to run the sampler")
+                        .long("iterations")
+                        .takes_value(true)
+                        .default_value("100000")
+                        .validator(pos_int_validate),
+                    Arg::with_name("shows")
+                        .help("The number of shows in the synthetic dictionary")
+                        .long("shows")
+                        .takes_value(true)
+                        .default_value("10")
+                        .validator(pos_int_validate),
+                    Arg::with_name("picks")
+                        .help("The number of results to select each iteration")
+                        .long("picks")
+                        .takes_value(true)
+                        .default_value("3")
+                        .validator(pos_int_validate),
+                    Arg::with_name("sample-with-replacement")
+                        .help("Test sampling with replacement")
+                        .long("sample-with-replacement"),
+                ]),
+        )
+}
+
+/// The arguments of every subcommand, which can also come before it
+fn global_args<'a>() -> Vec<Arg<'a, 'a>> {
+    vec![
+        Arg::with_name("verbosity")
+            .short("v")
+            .multiple(true)
+            .help("Increase message verbosity")
+            .global(true),
+        Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .help("Only log errors, and leave out other messages on stderr")
+            .global(true),
+        Arg::with_name("log-level")
+            .long("log-level")
+            .help("Set the log level, instead of -v or -q")
+            .long_help(
+"Set the log level, instead of -v or -q
+The first of these that is given sets the level: an entry without a module in --log, --log-level, -q (errors only), -v (info, debug and trace for -v, -vv and -vvv), and the log-level of the config file. Without any of them warnings and errors are logged."
+            )
+            .takes_value(true)
+            .value_name("LEVEL")
+            .possible_values(LEVELS)
+            .global(true),
+        Arg::with_name("color")
+            .long("color")
+            .help("When to color readable output and logs")
+            .long_help(
+"When to color readable output and logs
+auto (the default) colors them when writing to a terminal, unless NO_COLOR is set. Readable lines are colored by the type of theme, see --type-color, with the show title dimmed."
+            )
+            .takes_value(true)
+            .value_name("WHEN")
+            .possible_values(Coloring::NAMES)
+            .global(true),
+        Arg::with_name("timestamp")
+            .long("timestamp")
+            .help("Prepend log lines with a timestamp")
+            .takes_value(true)
+            .possible_values(&["none", "sec", "ms", "ns"])
+            .global(true),
+        Arg::with_name("progress")
+            .long("progress")
+            .help("Report the progress of long operations on stderr, as one JSON object per line")
+            .long_help(
+"Report the progress of long operations on stderr, as one JSON object per line
+Events look like {\"done\":120,\"event\":\"progress\",\"task\":\"export\",\"total\":310}, between a start and a finish event. Used by --all exports and selftest."
+            )
+            .takes_value(true)
+            .value_name("FORMAT")
+            .possible_values(&["json"])
+            .global(true),
+        Arg::with_name("log")
+            .long("log")
+            .help("Set log levels by module, e.g. 'selection=debug,quota=trace'")
+            .long_help(
+"Set log levels by module, e.g. 'selection=debug,quota=trace'
+Modules are named like the source files. An entry without a module, like 'info', sets the level of every other module instead of -v."
+            )
+            .takes_value(true)
+            .value_name("SPEC")
+            .validator(log_spec_validate)
+            .global(true),
+    ]
+}
+
+/// The `disable` or `enable` subcommand, which only differ in what they do
+fn disabled_subcommand<'a>(name: &'a str, about: &'a str) -> App<'a, 'a> {
+    SubCommand::with_name(name).about(about).args(&[
+        Arg::with_name("id")
+            .help("The ids of the shows")
+            .required(true)
+            .multiple(true),
+        Arg::with_name("dictionary")
+            .help("The dictionary to change")
+            .takes_value(true)
+            .short("d")
+            .required_unless("data-dir"),
+        Arg::with_name("data-dir")
+            .help("Directory to find dictionary.json in")
+            .long("data-dir")
+            .env("RANDOM_SHOW_THEMES_DATA_DIR")
+            .takes_value(true),
+    ])
+}
+
+/// A flag of a cargo feature
+struct FeatureFlag {
+    name: &'static str,
+    feature: &'static str,
+    takes_value: bool,
+}
+
+/// The flags of the features this build doesn't have
+const MISSING_FEATURE_FLAGS: &[FeatureFlag] = &[
+    #[cfg(not(feature = "announce"))]
+    FeatureFlag {
+        name: "announce",
+        feature: "announce",
+        takes_value: false,
+    },
+    #[cfg(not(feature = "encrypted"))]
+    FeatureFlag {
+        name: "identity",
+        feature: "encrypted",
+        takes_value: true,
+    },
+    #[cfg(not(feature = "git"))]
+    FeatureFlag {
+        name: "changed-since",
+        feature: "git",
+        takes_value: true,
+    },
+];
+
+/// Fails with the feature to build with if a flag of a feature this build doesn't have is given
+pub fn check_features(matches: &ArgMatches) -> Result<(), String> {
+    match MISSING_FEATURE_FLAGS
+        .iter()
+        .find(|flag| matches.is_present(flag.name))
+    {
+        Some(flag) => Err(format!(
+            "--{} needs the {} feature, which this build doesn't have, install it with `cargo install random-show-themes --features {}`",
+            flag.name, flag.feature, flag.feature
+        )),
+        None => Ok(()),
+    }
+}
+
+fn pick_subcommand<'a>() -> App<'a, 'a> {
+    let app = SubCommand::with_name("pick")
+        .visible_alias("p")
+        .about("Picks random themes from the shows in a list")
         .args(&[
             Arg::with_name("dictionary")
                 .help("The list of all known shows")
                 .takes_value(true)
                 .short("d")
                 // .long("dictionary")
-                .required(true),
+                .required_unless_one(&["explain", "data-dir", "demo"]),
             Arg::with_name("list")
                 .help("The subset of shows to choose from the dictionary")
                 .takes_value(true)
                 .short("l")
                 // .long("list")
-                .required(true),
+                .required_unless_one(&["explain", "data-dir", "demo"]),
+            Arg::with_name("data-dir")
+                .help("Directory to find dictionary.json and list.json in")
+                .long_help(
+"Directory to find dictionary.json and list.json in
+-d and -l take precedence over the files in this directory."
+                )
+                .long("data-dir")
+                .env("RANDOM_SHOW_THEMES_DATA_DIR")
+                .takes_value(true),
+            Arg::with_name("demo")
+                .help("Pick from a small dataset built into the program, to try it without any data files")
+                .long_help(
+"Pick from a small dataset built into the program, to try it without any data files
+The dictionary and list have a few well-known shows with openings, endings and soundtrack, and take the place of --data-dir. Every output mode works with them."
+                )
+                .long("demo")
+                .conflicts_with_all(&["dictionary", "list", "from", "changed-since"]),
+            Arg::with_name("from")
+                .help("Pick from favorites.json in the data directory instead of list.json")
+                .long("from")
+                .takes_value(true)
+                .possible_values(&["list", "favorites"])
+                .requires("data-dir")
+                .conflicts_with("list"),
             Arg::with_name("number")
                 .help("The number of results to output")
                 .long_help(
@@ -71,8 +631,28 @@ Note: The program is not guarranteed to output the number of results specified i
                 .takes_value(true)
                 .short("n")
                 .index(1)
-                .required(true)
+                .required_unless_one(&["explain", "all", "check"])
                 .validator(pos_int_validate),
+            Arg::with_name("all")
+                .help("Output every theme of every show in the list, in list order")
+                .long_help(
+"Output every theme of every show in the list, in list order
+Filters still apply. Themes are output as they are picked, so huge lists can be exported with --ndjson, --json or --csv without holding everything in memory."
+                )
+                .long("all")
+                .conflicts_with_all(&[
+                    "number", "table", "emit-shell", "format", "format-file", "receipt", "explain", "expect-exact",
+                    "take-first", "sample-with-replacement", "pick-strategy", "quota", "no-adjacent",
+                    "unique-artist", "order-themes", "interleave-types", "sort", "exec", "exec-after", "out", "output", "xlsx", "rss",
+                ]),
+            Arg::with_name("check")
+                .help("Check the config, data files and filters without picking anything, e.g. in CI")
+                .long_help(
+"Check the config, data files and filters without picking anything, e.g. in CI
+Everything is loaded like for a pick and reported on stdout. Exits with exit code 1 when shows of the list aren't in the dictionary, nothing can be picked, or fewer shows than the number given can be picked."
+                )
+                .long("check")
+                .conflicts_with_all(&["all", "explain"]),
             Arg::with_name("hard-fail")
                 .help("Exit with exit code 1 on any error")
                 .long_help(
@@ -80,22 +660,191 @@ Note: The program is not guarranteed to output the number of results specified i
 Note: this will not necessarily prevent some output from reaching stdout before exiting."
                 )
                 .long("hard-fail"),
+            Arg::with_name("expect-exact")
+                .help("Exit with exit code 3, without any output, if fewer results than requested can be selected")
+                .long_help(
+"Exit with exit code 3, without any output, if fewer results than requested can be selected
+This applies regardless of --hard-fail."
+                )
+                .long("expect-exact"),
+            Arg::with_name("receipt")
+                .help("Write a record of how the results were selected to this file")
+                .long_help(
+"Write a record of how the results were selected to this file
+When used with --explain the receipt is read instead."
+                )
+                .long("receipt")
+                .takes_value(true),
+            Arg::with_name("timezone")
+                .help("The time zone of timestamps, 'local' (the default), 'utc' or an offset like '+09:00'")
+                .long_help(
+"The time zone of timestamps, 'local' (the default), 'utc' or an offset like '+09:00'
+Timestamps, like when a receipt was made, are written in RFC 3339."
+                )
+                .long("timezone")
+                .takes_value(true)
+                .value_name("ZONE")
+                .validator(timezone::timezone_validate),
+            Arg::with_name("explain")
+                .help("Explain how a pick of a previous run came to be, using its receipt")
+                .long_help(
+"Explain how a pick of a previous run came to be, using its receipt
+Takes the position of the pick in the output, starting at 1."
+                )
+                .long("explain")
+                .takes_value(true)
+                .requires("receipt")
+                .validator(pos_int_validate),
         ])
-        // Logging arguments
+        // Selection arguments
         .args(&[
-            Arg::with_name("verbosity")
-                .short("v")
+            Arg::with_name("take-first")
+                .help("Take the first shows in list order instead of picking at random")
+                .long_help(
+"Take the first shows in list order instead of picking at random
+The first theme of each show is used. Useful when the list is already shuffled or ordered by priority."
+                )
+                .long("take-first")
+                .alias("respect-list-order"),
+            Arg::with_name("sample-with-replacement")
+                .help("Allow the same show and theme to be picked more than once")
+                .long_help(
+"Allow the same show and theme to be picked more than once
+Every pick is drawn from all eligible shows, so more results than there are shows in the list can be requested."
+                )
+                .long("sample-with-replacement")
+                .conflicts_with_all(&["take-first", "quota"]),
+            Arg::with_name("pick-strategy")
+                .help("How shows and themes are picked")
+                .long_help(
+"How shows and themes are picked
+'random' picks shows at random without repeats, 'take-first' and 'with-replacement' are the same as --take-first and --sample-with-replacement.
+'exhaust-then-reshuffle' picks at random like a music player on shuffle: every eligible theme is played once over consecutive runs before any is repeated. What was played is kept in the session file (see the paths subcommand) once the picks were output, the progress is logged after every run with -v."
+                )
+                .long("pick-strategy")
+                .takes_value(true)
+                .value_name("STRATEGY")
+                .possible_values(&SelectionStrategy::NAMES)
+                .conflicts_with_all(&["take-first", "sample-with-replacement"]),
+            // clap can't make arguments conflict with a single value of another, so the
+            // with-replacement strategy sets this one, which is what they conflict with instead
+            Arg::with_name("with-replacement")
+                .long("pick-strategy with-replacement")
+                .hidden(true)
+                .takes_value(true)
+                .value_names(&[])
+                .default_value_if("pick-strategy", Some("with-replacement"), "")
+                .conflicts_with_all(&["quota", "unique-artist"]),
+            Arg::with_name("quota")
+                .help("Soft constraint on the number of picks with a genre or tag")
+                .long_help(
+"Soft constraint on the number of picks with a genre or tag, e.g. 'genre:Music=2' or 'tag:favorite>=1'
+Supports '=', '>=' and '<='. Quotas are satisfied on a best-effort basis, unmet quotas are reported as warnings."
+                )
+                .long("quota")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(quota::quota_validate),
+            Arg::with_name("no-adjacent")
+                .help("Order the results so that no two neighbours share the same artist, franchise or show")
+                .long("no-adjacent")
+                .takes_value(true)
+                .possible_values(&["artist", "franchise", "show"]),
+            Arg::with_name("unique-artist")
+                .help("Never pick two themes by the same artist")
+                .long_help(
+"Never pick two themes by the same artist
+Like --no-adjacent, more themes are drawn than requested and the ones that break the constraint are left out. If not enough themes are left, fewer results are output with a warning."
+                )
+                .long("unique-artist")
+                .conflicts_with("sample-with-replacement"),
+            Arg::with_name("no-unique-artist")
+                .help("Allow several themes by the same artist, e.g. when the config file doesn't")
+                .long("no-unique-artist")
+                .overrides_with("unique-artist"),
+            Arg::with_name("order-themes")
+                .help("Prefer the earliest or latest theme of each show instead of a random one")
+                .long_help(
+"Prefer the earliest or latest theme of each show instead of a random one
+Themes are ordered by their #N numbering, openings before endings with the same number."
+                )
+                .long("order-themes")
+                .takes_value(true)
+                .possible_values(&["earliest", "latest"]),
+            Arg::with_name("status")
+                .help("Only pick shows with this watch status on the list")
+                .long_help(
+"Only pick shows with this watch status on the list, e.g. 'completed' or 'completed,watching'
+Shows without a status on the list are left out when this is given."
+                )
+                .long("status")
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .possible_values(WatchStatus::NAMES),
+            Arg::with_name("list-duplicates")
+                .help("What to do with shows that are on the list more than once")
+                .long_help(
+"What to do with shows that are on the list more than once
+dedupe (the default) only counts the first entry, weight makes a show as much more likely to be picked as it is on the list more often, and error refuses lists with duplicates."
+                )
+                .long("list-duplicates")
+                .takes_value(true)
+                .value_name("POLICY")
+                .possible_values(ListDuplicates::NAMES),
+            Arg::with_name("type-weight")
+                .help("How likely a type of theme is to be chosen within a show, e.g. 'op=3' or 'ost=0.2'")
+                .long_help(
+"How likely a type of theme is to be chosen within a show, e.g. 'op=3' or 'ost=0.2'
+The types are op, ed and ost, and default to a weight of 1. A weight of 0 leaves the type out entirely."
+                )
+                .long("type-weight")
+                .takes_value(true)
                 .multiple(true)
-                .help("Increase message verbosity"),
-            Arg::with_name("quiet")
-                .short("q")
-                .long("quiet")
-                .help("Silence all output"),
-            Arg::with_name("timestamp")
-                .long("timestamp")
-                .help("Prepend log lines with a timestamp")
-                .takes_value(true)
-                .possible_values(&["none", "sec", "ms", "ns"]),
+                .number_of_values(1)
+                .validator(weights::type_weight_validate),
+            Arg::with_name("interleave-types")
+                .help("Cycle the types of the results through a pattern, e.g. 'op,ed,op,ed,ost'")
+                .long_help(
+"Cycle the types of the results through a pattern, e.g. 'op,ed,op,ed,ost'
+The types are op, ed and ost. Every result takes the next show with a theme of its type in the pattern. Once no show has a theme of a type left, the pattern continues without it with a warning, which leaves fewer results than requested, see --hard-fail and --expect-exact."
+                )
+                .long("interleave-types")
+                .takes_value(true)
+                .value_name("PATTERN")
+                .multiple(true)
+                .require_delimiter(true)
+                .validator(weights::song_type_validate)
+                .conflicts_with_all(&["quota", "no-adjacent", "unique-artist"]),
+            Arg::with_name("family-friendly")
+                .help("Exclude shows flagged as nsfw or rated R and above")
+                .long("family-friendly"),
+            Arg::with_name("no-family-friendly")
+                .help("Include shows flagged as nsfw, e.g. when the config file excludes them")
+                .long("no-family-friendly")
+                .overrides_with("family-friendly"),
+            Arg::with_name("include-disabled")
+                .help("Include shows that are disabled in the dictionary")
+                .long("include-disabled"),
+            Arg::with_name("min-popularity")
+                .help("Exclude shows with fewer members than this on MAL")
+                .long_help(
+"Exclude shows with fewer members than this on MAL
+Members come from the members field of the dictionary, shows without it are kept."
+                )
+                .long("min-popularity")
+                .takes_value(true)
+                .value_name("MEMBERS")
+                .validator(pos_int_validate),
+            Arg::with_name("hipster")
+                .help("Only pick shows with fewer members than --min-popularity instead")
+                .long("hipster")
+                .requires("min-popularity"),
+            Arg::with_name("no-hipster")
+                .help("Exclude the shows below --min-popularity as usual, e.g. when the config file sets --hipster")
+                .long("no-hipster")
+                .overrides_with("hipster"),
         ])
         // Output format arguments
         .args(&[
@@ -104,17 +853,480 @@ Note: this will not necessarily prevent some output from reaching stdout before
                 .short("t")
                 .long("table"),
             Arg::with_name("table width")
-                .help("The number of results to show")
+                .help("The maximum width of table columns, 0 for unbounded")
+                .long_help(
+"The maximum width of table columns, 0 for unbounded
+Defaults to the width of the terminal, or else the COLUMNS environment variable, or else 60."
+                )
                 .takes_value(true)
                 .long("table-width")
                 .requires("table")
+                .validator(table_width_validate),
+            Arg::with_name("max-title-width")
+                .help("Cut off songs and shows in tables after this many characters, with an ellipsis")
+                .long_help(
+"Cut off songs and shows in tables after this many characters, with an ellipsis
+Unlike --table-width, which wraps long titles onto more lines, one long title can't make every row of the table taller. Wide characters like those of Japanese titles count twice."
+                )
+                .long("max-title-width")
+                .takes_value(true)
+                .value_name("WIDTH")
+                .requires("table")
                 .validator(pos_int_validate),
+            Arg::with_name("width-mode")
+                .help("How wide the terminal draws characters of ambiguous width, for laying out tables")
+                .long_help(
+"How wide the terminal draws characters of ambiguous width, for laying out tables
+Japanese, Chinese and Korean characters always take two columns. Terminals set up for those languages also draw characters like …, ☆, ♪ and × two columns wide, so tables with them only line up with cjk. Defaults to standard, where they take one."
+                )
+                .long("width-mode")
+                .takes_value(true)
+                .value_name("MODE")
+                .possible_values(WidthMode::NAMES)
+                .requires("table"),
+            Arg::with_name("column-width")
+                .help("Cut off a column of tables after this many characters, e.g. 'show=40', can be given more than once")
+                .long_help(
+"Cut off a column of tables after this many characters, e.g. 'show=40', can be given more than once
+The column is named like its header, in any case, so custom fields and links can be cut off as well. It takes precedence over --max-title-width."
+                )
+                .long("column-width")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("COLUMN=WIDTH")
+                .requires("table")
+                .validator(truncate::column_width_validate),
             Arg::with_name("readable")
                 .help("Sets output to human readable text")
                 .long("readable"),
+            Arg::with_name("summary")
+                .help("End the output with the number of picks, shows and each type of theme")
+                .long_help(
+"End the output with the number of picks, shows and each type of theme, e.g. '3 picks from 2 shows: 2 OP, 1 ED, 0 ST'
+Tables and readable output end with it, other output modes are left as they are and it goes to stderr instead."
+                )
+                .long("summary"),
+            Arg::with_name("no-summary")
+                .help("Leave out the summary, e.g. when the config file adds it")
+                .long("no-summary")
+                .overrides_with("summary"),
+            Arg::with_name("group-by-show")
+                .help("Output every show once, with its themes under it, in readable and table output")
+                .long_help(
+"Output every show once, with its themes under it, in readable and table output
+Readable output has a line with the show followed by its themes, indented. Tables only fill in the show in the first row of its themes. The shows are in the order they were first picked, or as --sort says."
+                )
+                .long("group-by-show")
+                .conflicts_with_all(&[
+                    "plain", "csv", "tsv", "markdown", "json", "ndjson", "msgpack", "m3u", "sql",
+                    "emit-shell", "format", "format-file",
+                ]),
+            Arg::with_name("no-group-by-show")
+                .help("Output every pick on its own, e.g. when the config file groups them")
+                .long("no-group-by-show")
+                .overrides_with("group-by-show"),
+            Arg::with_name("readable-format")
+                .help("The line of a pick in readable output, e.g. '{song} by {artist} ({type})'")
+                .long_help(
+"The line of a pick in readable output, e.g. '{song} by {artist} ({type})'
+It can use {song}, {artist}, {show}, {id}, {type}, {number}, and {favorite}, which is a star for shows in favorites.json of the data directory. Usually set in the config file."
+                )
+                .long("readable-format")
+                .takes_value(true)
+                .value_name("TEMPLATE")
+                .validator(readable_format_validate),
+            Arg::with_name("type-color")
+                .help("Color readable lines of a type of theme in this color, e.g. 'op=magenta'")
+                .long_help(
+"Color readable lines of a type of theme in this color, e.g. 'op=magenta'
+The types are op, ed and ost, the colors red, green, yellow, blue, magenta and cyan. Openings are green, endings blue and soundtracks yellow otherwise. Lines are only colored as --color says."
+                )
+                .long("type-color")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(type_color_validate),
+            Arg::with_name("plain")
+                .help("Sets output to just the song (or the first of --columns) of every pick, one per line, for piping to other tools")
+                .long("plain"),
             Arg::with_name("csv").help("Sets output to csv").long("csv"),
+            Arg::with_name("csv-delimiter")
+                .help("The character that separates CSV values, ',' by default, e.g. ';' for spreadsheets in many locales")
+                .long("csv-delimiter")
+                .takes_value(true)
+                .value_name("CHAR")
+                .validator(csv_delimiter_validate),
+            Arg::with_name("csv-quote-style")
+                .help("When CSV values are quoted, 'necessary' by default")
+                .long_help(
+"When CSV values are quoted, 'necessary' by default
+'necessary' only quotes values with the delimiter, quotes or line breaks in them, 'always' quotes every value, 'non-numeric' every value that isn't a number, and 'never' none, which can leave values that can't be read back."
+                )
+                .long("csv-quote-style")
+                .takes_value(true)
+                .value_name("STYLE")
+                .possible_values(CSV_QUOTE_STYLES),
+            Arg::with_name("null")
+                .help("End every pick with a NUL instead of a newline, for xargs -0, with --plain and --csv")
+                .short("0")
+                .long("null"),
+            Arg::with_name("ndjson")
+                .help("Sets output to newline-delimited JSON, one object per pick")
+                .long_help(
+"Sets output to newline-delimited JSON, one object per pick
+The objects are the same as the elements of --json."
+                )
+                .long("ndjson"),
+            Arg::with_name("msgpack")
+                .help("Sets output to MessagePack, one map per pick")
+                .long_help(
+"Sets output to MessagePack, one map per pick
+The maps are the same as the objects of --json, one after the other without an array around them, so they can be read as they come."
+                )
+                .long("msgpack"),
+            Arg::with_name("tsv")
+                .help("Sets output to tab-separated values, without any quoting")
+                .long_help(
+"Sets output to tab-separated values, without any quoting
+Tabs and line breaks within values are replaced with spaces, so every line is a pick and every tab separates two columns."
+                )
+                .long("tsv"),
+            Arg::with_name("no-header")
+                .help("Leave out the header row of --csv and --tsv, e.g. to append to earlier output")
+                .long("no-header"),
+            Arg::with_name("markdown")
+                .help("Sets output to a Markdown table, for pasting into GitHub or Discord")
+                .long("markdown"),
+            Arg::with_name("json")
+                .help("Sets output to a JSON array of objects")
+                .long_help(
+"Sets output to a JSON array of objects
+The objects have the id and url of the show and the lowercase column names as keys, e.g. {\"id\":1,\"show\":\"Cowboy Bebop\",\"song\":\"...\",\"type\":\"OP\",\"url\":null}."
+                )
+                .long("json"),
+            Arg::with_name("m3u")
+                .help("Sets output to an extended M3U playlist, for mpv or VLC")
+                .long_help(
+"Sets output to an extended M3U playlist, for mpv or VLC
+Every pick is titled with its song and located at the url of its show, or at a search for the show on animethemes.moe when there is no url."
+                )
+                .long("m3u"),
+            Arg::with_name("sql")
+                .help("Sets output to SQL statements inserting the picks into a table, 'picks' by default")
+                .long_help(
+"Sets output to SQL statements inserting the picks into a table, 'picks' by default, e.g. --sql=weekly_picks
+Every pick is an INSERT of its number, the columns by their lowercase names, and the id and url of its show, for SQLite or PostgreSQL."
+                )
+                .long("sql")
+                .takes_value(true)
+                // So `--sql 5` doesn't take the number of picks as the table
+                .require_equals(true)
+                .min_values(0)
+                .max_values(1)
+                .value_name("TABLE"),
+            Arg::with_name("out")
+                .help("Send the output here instead of stdout, e.g. 'file=picks.md:format=markdown', can be given more than once")
+                .long_help(
+"Send the output here instead of stdout, e.g. 'file=picks.md:format=markdown', can be given more than once
+A sink is stdout, file=PATH, command=COMMAND (which gets the output on stdin) or clipboard, optionally followed by :format= and one of table, readable, plain, csv, tsv, markdown, json, ndjson, msgpack, m3u, sql or emit-shell. Without a format the output is as the other flags choose. Include stdout to keep writing there. Files are replaced as a whole, like with --output."
+                )
+                .long("out")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("SINK")
+                .validator(sink::sink_validate),
+            Arg::with_name("output")
+                .help("Write the output to this file instead of stdout, can be given more than once")
+                .long_help(
+"Write the output to this file instead of stdout, can be given more than once
+Every file gets the same picks, in the format of its extension: .csv, .tsv, .md, .json, .ndjson or .jsonl, .msgpack, .m3u, .sql or .sh for --emit-shell. Files with any other extension get the output the other flags choose. Add --out stdout to keep writing there as well.
+A file is written next to its destination first and then moved into place, so whatever reads it meanwhile sees either the previous or the new output in full, never half of it."
+                )
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("FILE"),
+            Arg::with_name("append")
+                .help("Add the output to the end of the --output files instead of replacing them")
+                .long_help(
+"Add the output to the end of the --output files instead of replacing them, to keep a log of every pick
+Only with plain, CSV, TSV and NDJSON output. The header row of CSV and TSV is only written to a file that is new or empty."
+                )
+                .long("append")
+                .requires("output"),
+            Arg::with_name("sink-failure")
+                .help("What to do with the other --out and --output sinks when one fails")
+                .long_help(
+"What to do with the other --out and --output sinks when one fails
+continue delivers to them anyway, abort skips the ones after it and exits with exit code 1, and retry tries the failing sink twice more, a second and then two seconds later, before continuing. Sinks that got the output keep it either way. Defaults to abort with --hard-fail and continue otherwise. What became of every sink is logged at the end."
+                )
+                .long("sink-failure")
+                .takes_value(true)
+                .value_name("POLICY")
+                .possible_values(sink::SinkFailure::NAMES)
+                .requires("sinks"),
+            Arg::with_name("xlsx")
+                .help("Also write the results to this Excel workbook")
+                .long_help(
+"Also write the results to this Excel workbook
+Numbers, ids and members are written as numbers, everything else as text, below a header row."
+                )
+                .long("xlsx")
+                .takes_value(true)
+                .value_name("FILE"),
+            Arg::with_name("rss")
+                .help("Also write the results to this RSS feed")
+                .long_help(
+"Also write the results to this RSS feed
+Every pick is an item titled with its song, described by its show and type, and linking to the url of the show. The feed only has the picks of this run, readers keep the earlier ones, so a daily cron job makes a theme of the day feed. Dates are in the --timezone."
+                )
+                .long("rss")
+                .takes_value(true)
+                .value_name("FILE"),
+            Arg::with_name("emit-shell")
+                .help("Sets output to shell variable assignments, e.g. PICK_1_SONG='...'")
+                .long_help(
+"Sets output to shell variable assignments, e.g. PICK_1_SONG='...'
+Every column of every pick is assigned, followed by PICK_COUNT. Meant to be used with eval."
+                )
+                .long("emit-shell"),
+            Arg::with_name("format")
+                .help("Sets output to a line per pick in this format, e.g. '{song} [{type}] - {show} ({id})'")
+                .long_help(
+"Sets output to a line per pick in this format, e.g. '{song} [{type}] - {show} ({id})'
+Supports {song}, {artist}, {show}, {id}, {type}, {url}, {franchise}, {rating}, {genres}, {tags}, {members}, {favorites}, {custom.FIELD} and {number}. Lists like {genres} are joined with commas and fields a show doesn't have are empty. Other braces are left alone."
+                )
+                .long("format")
+                .takes_value(true)
+                .value_name("FORMAT"),
+            Arg::with_name("format-file")
+                .help("Sets output to a template read from a file")
+                .long_help(
+"Sets output to a template read from a file, e.g. for HTML, Markdown posts or BBCode
+The part between {#picks} and {/picks} is output for every pick, with {song}, {artist}, {show}, {id}, {type}, {url}, {franchise}, {rating}, {genres}, {tags}, {members}, {favorites}, {custom.FIELD} and {number} filled in. Before and after it {count} is the number of picks, {shows} the number of shows, and {openings}, {endings} and {soundtracks} the number of each type. Without {#picks} the whole file is output for every pick."
+                )
+                .long("format-file")
+                .visible_alias("template")
+                .takes_value(true)
+                .value_name("FILE"),
+            Arg::with_name("song-lang")
+                .help("Show song titles in romaji, in their native script, or both")
+                .long_help(
+"Show song titles in romaji, in their native script, or both
+Themes written like 'Romaji (ネイティブ)' as on MAL have both, others can get a native title from native_titles in the dictionary. Titles with only one variant are always shown as they are."
+                )
+                .long("song-lang")
+                .takes_value(true)
+                .value_name("LANG")
+                .possible_values(&theme::SongLang::NAMES),
+            Arg::with_name("columns")
+                .help("Choose the columns and their order, e.g. 'song,show'")
+                .long_help(
+"Choose the columns and their order, e.g. 'song,show'
+The columns are number, song, show, type, id, url and hash, by default song, show and type. Links, custom columns and notes are added after them. Readable output joins them with dashes unless they start with the default ones."
+                )
+                .long("columns")
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .value_name("COLUMNS")
+                .possible_values(Column::NAMES),
+            Arg::with_name("numbered")
+                .help("Number the results 1 to n in every output mode, e.g. to call out questions of a quiz")
+                .long_help(
+"Number the results 1 to n in every output mode, e.g. to call out questions of a quiz
+Readable and plain output start every line with the number, like '7. ', other modes get a Number column first. The same as adding number to --columns first."
+                )
+                .long("numbered"),
+            Arg::with_name("no-numbered")
+                .help("Don't number the results, e.g. when the config file does")
+                .long("no-numbered")
+                .overrides_with("numbered"),
+            Arg::with_name("sort")
+                .help("Sort the results by song, show or type before they're output")
+                .long_help(
+"Sort the results by song, show or type before they're output
+Songs and shows are sorted by title regardless of case and of fullwidth letters, types as openings, endings, then other soundtrack. Results that are the same stay in the order they were picked in, and numbers from --numbered follow the sorted order."
+                )
+                .long("sort")
+                .takes_value(true)
+                .value_name("KEY")
+                .possible_values(SortKey::NAMES)
+                .conflicts_with_all(&["no-adjacent", "interleave-types"]),
+            Arg::with_name("reverse")
+                .help("Sort the results the other way around")
+                .long("reverse")
+                .requires("sort"),
+            Arg::with_name("no-reverse")
+                .help("Sort the results the usual way around, e.g. when the config file reverses them")
+                .long("no-reverse")
+                .overrides_with("reverse"),
+            Arg::with_name("with-id")
+                .help("Add a column with the id of the show, e.g. to look it up on MAL")
+                .long("with-id"),
+            Arg::with_name("with-url")
+                .help("Add a column with the url of the show, empty for shows without one")
+                .long("with-url"),
+            Arg::with_name("links")
+                .help("Add a column with a link of this kind, e.g. 'youtube' or 'mal'")
+                .long("links")
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true),
+            Arg::with_name("link-template")
+                .help("Define a link kind, e.g. 'anidb=https://anidb.net/perl-bin/animedb.pl?show=animelist&adb.search={show}'")
+                .long_help(
+"Define a link kind, e.g. 'anidb=https://anidb.net/perl-bin/animedb.pl?show=animelist&adb.search={show}'
+Supports the same placeholders as --format except {number}. Replaces the built-in kind of the same name."
+                )
+                .long("link-template")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(links::link_template_validate),
+            Arg::with_name("custom-column")
+                .help("Add a column with a custom field of the dictionary, e.g. 'studio'")
+                .long_help(
+"Add a column with a custom field of the dictionary, e.g. 'studio'
+Custom fields are any fields of a show the dictionary format doesn't define. Templates can also use them as {custom.studio}."
+                )
+                .long("custom-column")
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .value_name("FIELD"),
+            Arg::with_name("notes")
+                .help("Add a column with notes from this JSON file, e.g. trivia for a quiz")
+                .long_help(
+"Add a column with notes from this JSON file, e.g. trivia for a quiz
+The file maps show ids or pick hashes to notes, like {\"1\": \"Directed by Shinichiro Watanabe\"}. A pick hash is the note of a single theme and wins over the note of its show. Pick hashes are output with '--columns song,show,type,hash'."
+                )
+                .long("notes")
+                .takes_value(true)
+                .value_name("FILE"),
+        ])
+        // Hook arguments
+        .args(&[
+            Arg::with_name("exec")
+                .help("Run a command for each pick, e.g. 'notify-send \"{song}\" \"{show}\"'")
+                .long_help(
+"Run a command for each pick, e.g. 'notify-send \"{song}\" \"{show}\"'
+Supports the same placeholders as --format except {custom.FIELD} and {number}, which should be quoted like shell variables. The values are passed as environment variables (PICK_SONG, PICK_SHOW, ...) along with PICK_NUMBER, so they are never run as part of the command. On Windows the command is run without cmd, as a program and its arguments with the placeholders filled in."
+                )
+                .long("exec")
+                .takes_value(true),
+            Arg::with_name("exec-after")
+                .help("Run a command once after all picks were output")
+                .long_help(
+"Run a command once after all picks were output
+The picks are passed in the environment variables --emit-shell outputs, PICK_COUNT and PICK_1_SONG, PICK_1_SHOW, ... On Windows the command is run without cmd, so only the program it runs can read them."
+                )
+                .long("exec-after")
+                .takes_value(true),
         ])
-        .group(ArgGroup::with_name("display").args(&["table", "readable", "csv"]))
+        .group(ArgGroup::with_name("display").args(&["table", "readable", "plain", "csv", "tsv", "markdown", "json", "ndjson", "msgpack", "m3u", "sql", "emit-shell", "format", "format-file"]))
+        .group(ArgGroup::with_name("sinks").args(&["out", "output"]).multiple(true));
+
+    #[cfg(feature = "announce")]
+    let app = app.arg(
+        Arg::with_name("announce")
+            .help("Speak each pick using the text-to-speech engine of the OS")
+            .long("announce"),
+    );
+
+    #[cfg(feature = "encrypted")]
+    let app = app.arg(
+        Arg::with_name("identity")
+            .help("The age identity file to decrypt a .age list with")
+            .long_help(
+                "The age identity file to decrypt a .age list with
+Lists ending in .gpg or .asc are decrypted with gpg, which finds the key on its own.",
+            )
+            .long("identity")
+            .env("RANDOM_SHOW_THEMES_IDENTITY")
+            .takes_value(true),
+    );
+
+    // Known but hidden without their feature, so using one explains what's missing
+    let app = MISSING_FEATURE_FLAGS.iter().fold(app, |app, flag| {
+        app.arg(
+            Arg::with_name(flag.name)
+                .long(flag.name)
+                .takes_value(flag.takes_value)
+                .hidden(true),
+        )
+    });
+
+    #[cfg(feature = "git")]
+    let app = app.arg(
+        Arg::with_name("changed-since")
+            .help("Only pick shows added or changed since this git revision, e.g. 'v2024-spring' or 'HEAD~10'")
+            .long_help(
+"Only pick shows added or changed since this git revision, e.g. 'v2024-spring' or 'HEAD~10'
+Shows count as changed when their entry in the dictionary was added or modified, or when they were added to the list. The data files have to be in a git repository."
+            )
+            .long("changed-since")
+            .takes_value(true),
+    );
+
+    app
+}
+
+/// Inserts the `pick` subcommand when no subcommand is given
+///
+/// Keeps invocations from before there were subcommands, like `random-show-themes -d … -l … 10`,
+/// working. Returns whether the subcommand was inserted.
+pub fn imply_pick_subcommand(args: &mut Vec<OsString>) -> bool {
+    let rest = &args[args.len().min(1)..];
+    if rest.is_empty() {
+        return false;
+    }
+    if rest.len() == 1
+        && ["-h", "--help", "-V", "--version"]
+            .iter()
+            .any(|flag| rest[0] == *flag)
+    {
+        return false;
+    }
+
+    if subcommand_position(args).is_some() {
+        return false;
+    }
+
+    args.insert(1, "pick".into());
+    true
+}
+
+/// The position of the subcommand in the arguments, if one is given
+pub(crate) fn subcommand_position(args: &[OsString]) -> Option<usize> {
+    // Global options that take their value as the next argument, like `--log-level info`
+    let takes_value: Vec<String> = global_args()
+        .iter()
+        .filter(|arg| arg.is_set(ArgSettings::TakesValue))
+        .flat_map(|arg| {
+            let long = arg.s.long.map(|long| format!("--{}", long));
+            let short = arg.s.short.map(|short| format!("-{}", short));
+            long.into_iter().chain(short)
+        })
+        .collect();
+
+    // The first argument that isn't an option or the value of a global option
+    let mut i = 1;
+    while i < args.len() {
+        if takes_value.iter().any(|option| args[i] == **option) {
+            i += 1;
+        } else if !args[i].to_string_lossy().starts_with('-') {
+            break;
+        }
+        i += 1;
+    }
+    args.get(i)
+        .filter(|arg| SUBCOMMANDS.iter().any(|name| *arg == *name))
+        .map(|_| i)
 }
 
 pub fn set_up_logging(matches: &ArgMatches) {
@@ -134,13 +1346,44 @@ pub fn set_up_logging(matches: &ArgMatches) {
         })
         .unwrap_or(stderrlog::Timestamp::Off);
 
-    stderrlog::new()
-        .module(module_path!())
-        .quiet(quiet)
-        .verbosity(verbose + 1) // change verbosity with no -v to warn
-        .timestamp(ts)
-        .init()
-        .unwrap()
+    // --log-level wins over -q, which wins over -v, and errors are always logged
+    let default = match matches.value_of("log-level") {
+        // Validated by clap
+        Some(level) => level.parse().unwrap(),
+        None if quiet => LevelFilter::Error,
+        None => match verbose {
+            0 => LevelFilter::Warn,
+            1 => LevelFilter::Info,
+            2 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        },
+    };
+    // Validated by clap
+    let spec: LogSpec = matches
+        .value_of("log")
+        .map(|v| v.parse().unwrap())
+        .unwrap_or_default();
+
+    // The levels are up to the scoped logger, stderrlog only formats
+    let color = match Coloring::from_matches(matches) {
+        Coloring::Always => stderrlog::ColorChoice::Always,
+        Coloring::Auto if io::stderr().is_terminal() => stderrlog::ColorChoice::Auto,
+        _ => stderrlog::ColorChoice::Never,
+    };
+    let mut formatter = stderrlog::new();
+    formatter.verbosity(4).timestamp(ts).color(color);
+    let logger = ScopedLogger::new(formatter, default, spec);
+    log::set_max_level(logger.max_level());
+    log::set_boxed_logger(Box::new(logger)).unwrap()
+}
+
+/// Gets the path given by `arg`, or else `file_name` in the data directory
+pub fn data_file_path(matches: &ArgMatches, arg: &str, file_name: &str) -> Option<PathBuf> {
+    matches.value_of(arg).map(PathBuf::from).or_else(|| {
+        matches
+            .value_of("data-dir")
+            .map(|dir| Path::new(dir).join(file_name))
+    })
 }
 
 pub fn read_json_file<P, T>(path: P) -> Result<T, Box<dyn Error>>
@@ -159,6 +1402,41 @@ where
     Ok(result)
 }
 
+/// Writes a data file in the canonical format, see `canonical`
+///
+/// The file is written next to its destination first and then moved into place, so an
+/// interrupted write never leaves a truncated data file behind.
+pub fn write_json_file<P, T>(path: P, value: &T) -> Result<(), Box<dyn Error>>
+where
+    P: AsRef<Path>,
+    T: Serialize,
+{
+    let json = canonical::to_string(&serde_json::to_value(value)?);
+    write_file_atomically(path, json.as_bytes())?;
+    Ok(())
+}
+
+/// Writes the file next to its destination first and then moves it into place, so whatever
+/// reads it meanwhile sees either the old or the new contents in full
+///
+/// The name of the temporary file is unique, so runs writing the same file at the same time
+/// don't write into each other's. Updates that read the file first also need a `lock::FileLock`.
+pub fn write_file_atomically<P: AsRef<Path>>(path: P, contents: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(
+        ".{}-{:08x}.tmp",
+        std::process::id(),
+        rand::random::<u32>()
+    ));
+
+    let written = std::fs::write(&temp, contents).and_then(|()| std::fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    written
+}
+
 /// Checks if the value can be parsed as a positive, non-zero integer
 fn pos_int_validate(value: String) -> Result<(), String> {
     let error_msg = "must be a positive, non-zero integer";
@@ -170,70 +1448,582 @@ fn pos_int_validate(value: String) -> Result<(), String> {
     }
 }
 
-pub fn create_table<'a>(matches: &'a ArgMatches) -> Table<'a> {
-    let mut table = Table::new();
+/// The names of the quote styles of `--csv-quote-style`
+pub const CSV_QUOTE_STYLES: &[&str] = &["necessary", "always", "non-numeric", "never"];
+
+/// The quote style called `name`, one of `CSV_QUOTE_STYLES`
+fn csv_quote_style(name: &str) -> Option<csv::QuoteStyle> {
+    match name {
+        "necessary" => Some(csv::QuoteStyle::Necessary),
+        "always" => Some(csv::QuoteStyle::Always),
+        "non-numeric" => Some(csv::QuoteStyle::NonNumeric),
+        "never" => Some(csv::QuoteStyle::Never),
+        _ => None,
+    }
+}
+
+/// A single ASCII character that can separate CSV values, like `;`
+pub fn csv_delimiter(value: &str) -> Result<u8, String> {
+    match value.as_bytes() {
+        [b'"' | b'\n' | b'\r'] => Err(format!(
+            "'{}' can't separate values",
+            value.escape_default()
+        )),
+        [byte] if byte.is_ascii() => Ok(*byte),
+        _ => Err("must be a single ASCII character, like ';'".to_owned()),
+    }
+}
+
+fn csv_delimiter_validate(value: String) -> Result<(), String> {
+    csv_delimiter(&value).map(|_| ())
+}
+
+fn table_width_validate(value: String) -> Result<(), String> {
+    value
+        .parse::<TableWidth>()
+        .map(|_| ())
+        .map_err(|_| "must be a positive integer, or 0 for unbounded".to_owned())
+}
+
+/// The maximum width of table columns
+#[derive(Debug, PartialEq)]
+pub enum TableWidth {
+    Columns(usize),
+    Unbounded,
+}
+
+impl TableWidth {
+    /// Used when neither the terminal nor `COLUMNS` give a width
+    const FALLBACK: TableWidth = TableWidth::Columns(60);
+
+    /// From `--table-width`, the terminal size or the `COLUMNS` environment variable, in that order
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        use terminal_size::{terminal_size, Width};
+        TableWidth::resolve(
+            matches.value_of("table width"),
+            terminal_size().map(|(Width(width), _)| width as usize),
+            std::env::var("COLUMNS").ok().as_deref(),
+        )
+    }
+
+    fn resolve(arg: Option<&str>, terminal: Option<usize>, columns: Option<&str>) -> Self {
+        if let Some(arg) = arg {
+            // Validated by clap
+            return arg.parse().unwrap();
+        }
+        terminal
+            .or_else(|| columns.and_then(|c| c.trim().parse().ok()))
+            .filter(|&width| width > 0)
+            .map(TableWidth::Columns)
+            .unwrap_or(TableWidth::FALLBACK)
+    }
+
+    pub fn max_column_width(&self) -> usize {
+        match self {
+            TableWidth::Columns(width) => *width,
+            TableWidth::Unbounded => usize::MAX,
+        }
+    }
+}
+
+impl FromStr for TableWidth {
+    type Err = std::num::ParseIntError;
 
-    use terminal_size::{terminal_size, Height, Width};
-    let width = matches
-        .value_of("table width")
-        .map(|s| (Width(s.parse().unwrap()), Height(20)))
-        .unwrap_or(terminal_size().unwrap_or((Width(60), Height(20))));
-    let (Width(width), _) = width;
-    table.max_column_width = width as _;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.parse()? {
+            0 => TableWidth::Unbounded,
+            width => TableWidth::Columns(width),
+        })
+    }
+}
 
-    // Set table style (hardcoded)
-    // Note: should this option be exposed to users?
-    table.style = TableStyle::rounded();
+pub fn create_table(matches: &ArgMatches) -> Table {
+    Table::new(
+        TableWidth::from_matches(matches).max_column_width(),
+        width_mode(matches),
+    )
+}
 
-    table
+/// How wide characters are on the terminal, from `--width-mode`
+pub fn width_mode(matches: &ArgMatches) -> WidthMode {
+    // Validated by clap
+    matches
+        .value_of("width-mode")
+        .map(|v| v.parse().unwrap())
+        .unwrap_or_default()
 }
 
+/// Outputs what comes before `count` picks, except for tables which are built as a whole
+pub fn output_header(
+    out: &mut dyn Write,
+    output_mode: &OutputMode,
+    columns: &[Column],
+    totals: &Totals,
+) -> Result<(), Box<dyn Error>> {
+    let headers: Vec<&str> = columns.iter().map(|c| c.header()).collect();
+    match output_mode {
+        OutputMode::Table(_)
+        | OutputMode::Readable(_)
+        | OutputMode::Plain { .. }
+        | OutputMode::Json
+        | OutputMode::NdJson
+        | OutputMode::MsgPack
+        | OutputMode::Sql(_)
+        | OutputMode::Shell => {}
+        OutputMode::Template(template) => write!(out, "{}", template.render_header(totals))?,
+        OutputMode::CSV { header: false, .. } | OutputMode::Tsv { header: false } => {}
+        OutputMode::CSV {
+            null,
+            delimiter,
+            quote_style,
+            header: true,
+        } => {
+            let mut wtr = csv_writer(out, *null, *delimiter, *quote_style);
+            wtr.write_record(&headers)?;
+            wtr.flush()?;
+        }
+        OutputMode::Tsv { header: true } => writeln!(out, "{}", tsv_row(&headers))?,
+        OutputMode::Markdown => writeln!(out, "{}", markdown::header(headers))?,
+        OutputMode::M3u => writeln!(out, "{}", m3u::HEADER)?,
+    }
+    Ok(())
+}
+
+/// Outputs what comes after the picks, including the whole table
+pub fn output_footer(
+    out: &mut dyn Write,
+    output_mode: &OutputMode,
+    totals: &Totals,
+    table: &mut Option<Table>,
+) -> Result<(), Box<dyn Error>> {
+    match output_mode {
+        // The table has to exist if the output mode is set to table
+        OutputMode::Table(_) => writeln!(out, "{}", table.as_mut().unwrap().render())?,
+        // No cleanup required for the modes that only output picks
+        OutputMode::Readable(_)
+        | OutputMode::Plain { .. }
+        | OutputMode::CSV { .. }
+        | OutputMode::Tsv { .. }
+        | OutputMode::Markdown
+        | OutputMode::NdJson
+        | OutputMode::MsgPack
+        | OutputMode::M3u
+        | OutputMode::Sql(_) => {}
+        OutputMode::Json => write!(out, "{}", json_footer(totals.count))?,
+        OutputMode::Shell => writeln!(out, "PICK_COUNT={}", totals.count)?,
+        OutputMode::Template(template) => write!(out, "{}", template.render_footer(totals))?,
+    }
+    Ok(())
+}
+
+/// Where a pick is among the picks of its show, with `--group-by-show`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Grouping {
+    /// The picks aren't grouped
+    Ungrouped,
+    /// The first pick of its show, which comes after the show
+    First,
+    /// A pick of the same show as the one before it
+    Rest,
+}
+
+impl Grouping {
+    /// The grouping of a pick that comes after a pick of the `previous` show, if grouped
+    pub fn of(grouped: bool, previous: Option<&Show>, pick: &Pick) -> Self {
+        match previous {
+            _ if !grouped => Self::Ungrouped,
+            Some(show) if show.id == pick.show.id => Self::Rest,
+            _ => Self::First,
+        }
+    }
+}
+
+/// Outputs the summary of `--summary` after the picks and anything after them
+///
+/// Only outputs that are for people get it, the others would no longer be valid with it.
+pub fn output_summary(
+    out: &mut dyn Write,
+    output_mode: &OutputMode,
+    totals: &Totals,
+) -> io::Result<()> {
+    match output_mode {
+        // Tables already end with an empty line
+        OutputMode::Table(_) => writeln!(out, "{}", totals.summary()),
+        OutputMode::Readable(_) => writeln!(out, "\n{}", totals.summary()),
+        _ => Ok(()),
+    }
+}
+
+/// Outputs the pick at the 1-based position `number`
+///
+/// Only readable and table output are grouped, other modes ignore `grouping`.
 pub fn output_theme(
-    choice: &String,
-    show: &Show,
+    out: &mut dyn Write,
+    number: usize,
+    pick: &Pick,
+    columns: &[Column],
     output_mode: &OutputMode,
+    grouping: Grouping,
     table: &mut Option<Table>,
 ) -> Result<(), Box<dyn Error>> {
-    let song_type = if show.opening_themes.contains(choice) {
-        "OP"
-    } else if show.ending_themes.contains(choice) {
-        "ED"
-    } else {
-        "ST"
+    let mut values: Vec<String> = columns.iter().map(|c| c.value(number, pick)).collect();
+    // A number first is a prefix in the modes that aren't laid out in columns
+    let (prefix, line_columns, line_values) = match columns.first() {
+        Some(Column::Number) => (format!("{}. ", number), &columns[1..], &values[1..]),
+        _ => (String::new(), columns, &values[..]),
     };
 
     match output_mode {
-        OutputMode::Table => {
+        OutputMode::Table(widths) => {
+            for (column, value) in columns.iter().zip(&mut values) {
+                // Every show is only in the first row of its picks
+                if grouping == Grouping::Rest && *column == Column::Show {
+                    value.clear();
+                }
+                if let Some(width) = widths.of(column) {
+                    *value = truncate(value, width, widths.width_mode);
+                }
+            }
             // Unwrap is ok if we know it definetly exists
-            table.as_mut().unwrap().add_row(Row::new(vec![
-                TableCell::new(choice),
-                TableCell::new(&show.title),
-                TableCell::new(song_type),
-            ]));
+            table.as_mut().unwrap().add_row(values);
+        }
+        OutputMode::Readable(style) => {
+            if grouping == Grouping::First {
+                writeln!(out, "{}", pick.show.title)?;
+            }
+            let indent = if grouping == Grouping::Ungrouped {
+                ""
+            } else {
+                "  "
+            };
+
+            // Anything beyond the song, show and type is appended to the usual line
+            let line = if Column::starts_with_default(line_columns) {
+                // An id or a note on its own could be mistaken for part of the title, like a
+                // season
+                let extra: Vec<String> = line_columns
+                    .iter()
+                    .zip(line_values)
+                    .skip(Column::DEFAULT.len())
+                    .map(|(column, value)| match column {
+                        Column::Id => format!("#{}", value),
+                        Column::Notes(_) if !value.is_empty() => format!("- {}", value),
+                        _ => value.clone(),
+                    })
+                    .collect();
+                if grouping == Grouping::Ungrouped {
+                    style.render(number, pick, &extra)
+                } else {
+                    style.render_under_show(number, pick, &extra)
+                }
+            } else if grouping == Grouping::Ungrouped {
+                style.render_columns(number, pick, line_values)
+            } else {
+                let values: Vec<String> = line_columns
+                    .iter()
+                    .zip(line_values)
+                    .filter(|(column, _)| **column != Column::Show)
+                    .map(|(_, value)| value.clone())
+                    .collect();
+                style.render_columns(number, pick, &values)
+            };
+            // A format places the number itself
+            if style.has_format() {
+                writeln!(out, "{}{}", indent, line)?;
+            } else {
+                writeln!(out, "{}{}{}", indent, prefix, line)?;
+            }
         }
-        OutputMode::Readable => {
-            println!("{} [{}] from {}", choice, song_type, show.title);
+        OutputMode::Plain { null } => {
+            let value = line_values.first().map_or("", String::as_str);
+            let end = if *null { '\0' } else { '\n' };
+            write!(out, "{}{}{}", prefix, value, end)?;
         }
-        OutputMode::CSV => {
-            let mut wtr = csv::Writer::from_writer(std::io::stdout());
-            wtr.write_record(&[choice, song_type, &show.title])?;
+        OutputMode::CSV {
+            null,
+            delimiter,
+            quote_style,
+            ..
+        } => {
+            let mut wtr = csv_writer(out, *null, *delimiter, *quote_style);
+            wtr.write_record(&values)?;
             wtr.flush()?;
         }
+        OutputMode::Shell => {
+            for (column, value) in columns.iter().zip(&values) {
+                writeln!(
+                    out,
+                    "{}={}",
+                    shell::variable_name(number, column.header()),
+                    shell::quote(value)
+                )?;
+            }
+        }
+        OutputMode::Tsv { .. } => {
+            writeln!(out, "{}", tsv_row(&values))?;
+        }
+        OutputMode::Markdown => {
+            let cells: Vec<String> = values.iter().map(|v| markdown::escape(v)).collect();
+            writeln!(out, "{}", markdown::row(&cells))?;
+        }
+        OutputMode::Json => {
+            let line = serde_json::to_string(&json_object(number, pick, columns, values)?)?;
+            let separator = if number == 1 { "[" } else { "," };
+            write!(out, "{}\n{}", separator, line)?;
+        }
+        OutputMode::NdJson => {
+            // Serialized first, so a closed stdout is an io::Error like for the other modes
+            let line = serde_json::to_string(&json_object(number, pick, columns, values)?)?;
+            writeln!(out, "{}", line)?;
+        }
+        OutputMode::MsgPack => {
+            let object = json_object(number, pick, columns, values)?;
+            let mut bytes = vec![];
+            msgpack::encode(&serde_json::Value::Object(object), &mut bytes);
+            out.write_all(&bytes)?;
+        }
+        OutputMode::M3u => {
+            let title = prefix + &Column::song(columns).value(number, pick);
+            writeln!(out, "{}", m3u::entry(&title, pick))?;
+        }
+        OutputMode::Sql(table) => {
+            // The number always comes first and the id and url last, so they aren't inserted twice
+            let chosen: Vec<(&Column, &String)> = columns
+                .iter()
+                .zip(&values)
+                .filter(|(column, _)| !matches!(column, Column::Number | Column::Id | Column::Url))
+                .collect();
+            let mut names = vec!["number".to_owned()];
+            names.extend(chosen.iter().map(|(c, _)| sql::column_name(c.header())));
+            names.extend(["id".to_owned(), "url".to_owned()]);
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+
+            let mut row = vec![sql::Value::Integer(number as u64)];
+            row.extend(chosen.iter().map(|(_, value)| sql::Value::Text(value)));
+            let id = pick.show.id.to_string();
+            row.push(match pick.show.id.as_number() {
+                Some(id) => sql::Value::Integer(id),
+                None => sql::Value::Text(&id),
+            });
+            row.push(
+                pick.show
+                    .url
+                    .as_deref()
+                    .map_or(sql::Value::Null, sql::Value::Text),
+            );
+            writeln!(out, "{}", sql::insert(table, &names, &row))?;
+        }
+        OutputMode::Template(template) => {
+            write!(out, "{}", template.render_pick(number, pick))?;
+        }
     }
 
     Ok(())
 }
 
+/// The object of a pick in JSON output: the id and url of the show, and the value of every
+/// column keyed by its lowercase name
+///
+/// The id and url are always there, as a number or string and as a string or null, even when they
+/// are chosen as columns. The number is a number too, when it's chosen.
+fn json_object(
+    number: usize,
+    pick: &Pick,
+    columns: &[Column],
+    values: Vec<String>,
+) -> Result<serde_json::Map<String, serde_json::Value>, serde_json::Error> {
+    let mut object: serde_json::Map<String, serde_json::Value> = columns
+        .iter()
+        .zip(values)
+        .map(|(column, value)| (column.header().to_lowercase(), value.into()))
+        .collect();
+    if columns.contains(&Column::Number) {
+        object.insert("number".to_owned(), number.into());
+    }
+    object.insert("id".to_owned(), serde_json::to_value(&pick.show.id)?);
+    object.insert("url".to_owned(), serde_json::to_value(&pick.show.url)?);
+    if !pick.show.custom.is_empty() {
+        object.insert(
+            "custom".to_owned(),
+            serde_json::to_value(&pick.show.custom)?,
+        );
+    }
+    Ok(object)
+}
+
+/// A CSV writer that ends records with a newline, or a NUL with `null`
+fn csv_writer(
+    out: &mut dyn Write,
+    null: bool,
+    delimiter: u8,
+    quote_style: csv::QuoteStyle,
+) -> csv::Writer<&mut dyn Write> {
+    let mut builder = csv::WriterBuilder::new();
+    builder.delimiter(delimiter).quote_style(quote_style);
+    if null {
+        builder.terminator(csv::Terminator::Any(b'\0'));
+    }
+    builder.from_writer(out)
+}
+
+/// A line of tab-separated values, with tabs and line breaks within them replaced by spaces
+fn tsv_row<S: AsRef<str>>(values: &[S]) -> String {
+    let values: Vec<String> = values
+        .iter()
+        .map(|value| value.as_ref().replace(['\t', '\n', '\r'], " "))
+        .collect();
+    values.join("\t")
+}
+
+/// The end of a JSON array of `count` picks
+fn json_footer(count: usize) -> &'static str {
+    if count == 0 {
+        "[]\n"
+    } else {
+        "\n]\n"
+    }
+}
+
+/// Checks if an output error means stdout was closed early, e.g. by `| head -1`
+pub fn is_broken_pipe(error: &(dyn Error + 'static)) -> bool {
+    let io_error = match error.downcast_ref::<csv::Error>() {
+        Some(error) => match error.kind() {
+            csv::ErrorKind::Io(error) => Some(error),
+            _ => None,
+        },
+        None => error.downcast_ref::<io::Error>(),
+    };
+    io_error.is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
 /// Appends `other` to `first` if `other` is not empty
-pub fn smart_append<T: Clone>(first: &mut Vec<T>, other: &Vec<T>) {
+pub fn smart_append<T: Clone>(first: &mut Vec<T>, other: &[T]) {
     if !other.is_empty() {
-        first.append(&mut other.clone());
+        first.extend_from_slice(other);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[test]
+    fn family_friendly() {
+        let show = |rating: Option<&str>, nsfw| Show {
+            rating: rating.map(|r| r.to_owned()),
+            nsfw,
+            ..Default::default()
+        };
+        assert!(show(None, false).is_family_friendly());
+        assert!(show(Some("PG-13 - Teens 13 or older"), false).is_family_friendly());
+        assert!(!show(Some("PG-13 - Teens 13 or older"), true).is_family_friendly());
+        assert!(!show(Some("R - 17+ (violence & profanity)"), false).is_family_friendly());
+        assert!(!show(Some("R+ - Mild Nudity"), false).is_family_friendly());
+        assert!(!show(Some("Rx - Hentai"), false).is_family_friendly());
+    }
+
+    #[test]
+    fn custom_fields() {
+        let show: Show = serde_json::from_str(
+            r#"{"mal_id": 1, "title": "Cowboy Bebop", "studio": "Sunrise", "score": 8.75, "source": null}"#,
+        )
+        .unwrap();
+        assert_eq!(show.id, 1usize.into());
+        assert_eq!(show.custom.len(), 3);
+        assert_eq!(show.custom_field("studio").as_deref(), Some("Sunrise"));
+        assert_eq!(show.custom_field("score").as_deref(), Some("8.75"));
+        assert_eq!(show.custom_field("source").as_deref(), Some(""));
+        assert_eq!(show.custom_field("director"), None);
+    }
+
+    #[test]
+    fn implied_pick_subcommand() {
+        fn imply(args: &[&str]) -> (Vec<OsString>, bool) {
+            let mut args: Vec<OsString> = args.iter().map(OsString::from).collect();
+            let implied = imply_pick_subcommand(&mut args);
+            (args, implied)
+        }
+
+        let (args, implied) = imply(&["prog", "-d", "dict.json", "-l", "list.json", "5"]);
+        assert!(implied);
+        assert_eq!(args[..2], ["prog", "pick"]);
+        assert!(!imply(&["prog", "pick", "5"]).1);
+        assert!(!imply(&["prog", "-q", "--timestamp", "sec", "selftest"]).1);
+        assert!(!imply(&["prog", "--log", "selection=debug", "pick", "5"]).1);
+        assert!(!imply(&["prog", "--log-level", "info", "pick", "5"]).1);
+        assert!(!imply(&["prog", "--color", "never", "pick", "5"]).1);
+        assert!(!imply(&["prog", "--progress", "json", "selftest"]).1);
+        let (args, implied) = imply(&["prog", "--log-level", "info", "-d", "dict.json", "5"]);
+        assert!(implied);
+        assert_eq!(args[..2], ["prog", "pick"]);
+        assert!(!imply(&["prog", "--help"]).1);
+        assert!(!imply(&["prog"]).1);
+    }
+
+    #[test]
+    fn table_width() {
+        assert_eq!(
+            TableWidth::resolve(Some("0"), Some(80), None),
+            TableWidth::Unbounded
+        );
+        assert_eq!(
+            TableWidth::resolve(Some("100"), Some(80), None),
+            TableWidth::Columns(100)
+        );
+        assert_eq!(
+            TableWidth::resolve(None, Some(80), Some("120")),
+            TableWidth::Columns(80)
+        );
+        assert_eq!(
+            TableWidth::resolve(None, None, Some("120")),
+            TableWidth::Columns(120)
+        );
+        assert_eq!(
+            TableWidth::resolve(None, None, Some("wide")),
+            TableWidth::FALLBACK
+        );
+        assert_eq!(TableWidth::resolve(None, None, None), TableWidth::FALLBACK);
+    }
+
+    #[test]
+    fn csv_delimiters() {
+        assert_eq!(csv_delimiter(";"), Ok(b';'));
+        assert_eq!(csv_delimiter("\t"), Ok(b'\t'));
+        assert!(csv_delimiter("\"").is_err());
+        assert!(csv_delimiter(";;").is_err());
+        assert!(csv_delimiter("§").is_err());
+        assert!(csv_delimiter("").is_err());
+    }
+
+    #[test]
+    fn broken_pipes() {
+        let broken_pipe = || io::Error::from(io::ErrorKind::BrokenPipe);
+        assert!(is_broken_pipe(&broken_pipe()));
+        assert!(is_broken_pipe(&csv::Error::from(broken_pipe())));
+        assert!(!is_broken_pipe(&io::Error::from(io::ErrorKind::NotFound)));
+        assert!(!is_broken_pipe(&*Box::<dyn Error>::from("broken pipe")));
+    }
+
+    #[test]
+    fn atomic_writes() {
+        let dir = std::env::temp_dir().join(format!("atomic-writes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("favorites.json");
+        let writers: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || write_file_atomically(&path, &[b'0' + i; 64]))
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap().unwrap();
+        }
+        // One of the writes in full, and no temporary files left behind
+        let contents = std::fs::read(&path).unwrap();
+        assert!(contents.len() == 64 && contents.iter().all(|&b| b == contents[0]));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn pos_int_validation() {
         assert!(pos_int_validate("1".to_owned()).is_ok());
@@ -242,6 +2032,7 @@ mod tests {
         assert!(pos_int_validate("0".to_owned()).is_err());
     }
 
+    #[allow(clippy::type_complexity)]
     fn smart_appending_template<T: Clone>(
         a: T,
         b: T,
@@ -274,4 +2065,16 @@ mod tests {
         assert_eq!(first.0, first.1);
         assert_eq!(second.0, second.1);
     }
+
+    #[test]
+    #[cfg(not(feature = "announce"))]
+    fn missing_features() {
+        let args = ["prog", "pick", "1", "-d", "d.json", "-l", "l.json"];
+        let matches = create_clap_app().get_matches_from(args);
+        assert!(check_features(matches.subcommand().1.unwrap()).is_ok());
+
+        let matches = create_clap_app().get_matches_from(args.iter().chain(&["--announce"]));
+        let error = check_features(matches.subcommand().1.unwrap()).unwrap_err();
+        assert!(error.contains("--features announce"), "{}", error);
+    }
 }
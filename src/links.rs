@@ -0,0 +1,239 @@
+use std::str::FromStr;
+
+use crate::selection::Pick;
+use crate::theme::{SongLang, Theme};
+
+/// Link kinds that are available without defining a template
+const BUILT_IN: &[(&str, &str)] = &[
+    (
+        "youtube",
+        "https://www.youtube.com/results?search_query={song}+{show}",
+    ),
+    ("mal", "https://myanimelist.net/anime/{id}"),
+];
+
+/// The placeholders templates can use, shared with `--exec`
+pub(crate) const PLACEHOLDERS: &[&str] = &[
+    "song",
+    "artist",
+    "show",
+    "id",
+    "type",
+    "url",
+    "franchise",
+    "rating",
+    "genres",
+    "tags",
+    "members",
+    "favorites",
+];
+
+/// The prefix of placeholders of custom fields, e.g. `{custom.studio}`
+const CUSTOM: &str = "custom.";
+
+/// A named URL template, e.g. `mal=https://myanimelist.net/anime/{id}`
+///
+/// Supports the `{song}`, `{artist}`, `{show}`, `{id}`, `{type}` and `{custom.FIELD}`
+/// placeholders, as well as the other fields of the show in `PLACEHOLDERS`, which are all
+/// percent-encoded when substituted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkTemplate {
+    pub kind: String,
+    pub template: String,
+    /// The script of `{song}`
+    pub song_lang: SongLang,
+}
+
+impl LinkTemplate {
+    pub fn built_in() -> Vec<LinkTemplate> {
+        BUILT_IN
+            .iter()
+            .map(|(kind, template)| LinkTemplate {
+                kind: kind.to_string(),
+                template: template.to_string(),
+                song_lang: SongLang::Both,
+            })
+            .collect()
+    }
+
+    pub fn render(&self, pick: &Pick) -> String {
+        substitute(&self.template, |placeholder| {
+            encode(&placeholder_value(placeholder, pick, self.song_lang))
+        })
+    }
+}
+
+impl FromStr for LinkTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, template) = s
+            .split_once('=')
+            .filter(|(kind, template)| !kind.is_empty() && !template.is_empty())
+            .ok_or_else(|| format!("invalid link template '{}', expected 'kind=template'", s))?;
+
+        validate_placeholders(template).map_err(|e| format!("{} in link template '{}'", e, s))?;
+
+        Ok(LinkTemplate {
+            kind: kind.to_owned(),
+            template: template.to_owned(),
+            song_lang: SongLang::Both,
+        })
+    }
+}
+
+/// Checks that every `{placeholder}` in the template is closed and known
+fn validate_placeholders(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| "unclosed placeholder".to_owned())?
+            + start;
+        let placeholder = &rest[start + 1..end];
+        if !is_placeholder(placeholder) {
+            return Err(format!(
+                "unknown placeholder '{{{}}}', expected one of {{{}}} or {{{}FIELD}}",
+                placeholder,
+                PLACEHOLDERS.join("}, {"),
+                CUSTOM
+            ));
+        }
+        rest = &rest[end + 1..];
+    }
+    Ok(())
+}
+
+/// Replaces every placeholder of a validated template with the value for it
+fn substitute(template: &str, value: impl Fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        // Templates are validated when parsed, so every placeholder is closed and known
+        let end = rest[start..].find('}').unwrap() + start;
+        out.push_str(&value(&rest[start + 1..end]));
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Checks if a placeholder is one of `PLACEHOLDERS` or a custom field
+pub(crate) fn is_placeholder(placeholder: &str) -> bool {
+    PLACEHOLDERS.contains(&placeholder)
+        || placeholder
+            .strip_prefix(CUSTOM)
+            .is_some_and(|field| !field.is_empty())
+}
+
+/// The value of a placeholder for a pick, unencoded
+///
+/// Fields the show doesn't have are empty, lists are joined with commas. `{song}` is in the
+/// script of `song_lang`.
+pub(crate) fn placeholder_value(placeholder: &str, pick: &Pick, song_lang: SongLang) -> String {
+    if let Some(field) = placeholder.strip_prefix(CUSTOM) {
+        return pick.show.custom_field(field).unwrap_or_default();
+    }
+    let song = song_lang.apply(pick.song, &pick.show.native_titles);
+    let theme = Theme::parse(&song);
+    match placeholder {
+        "song" => theme.title.to_owned(),
+        "artist" => theme.artist.unwrap_or_default().to_owned(),
+        "show" => pick.show.title.clone(),
+        "id" => pick.show.id.to_string(),
+        "url" => pick.show.url.clone().unwrap_or_default(),
+        "franchise" => pick.show.franchise.clone().unwrap_or_default(),
+        "rating" => pick.show.rating.clone().unwrap_or_default(),
+        "genres" => pick.show.genres.join(", "),
+        "tags" => pick.show.tags.join(", "),
+        "members" => pick.show.members.map(|n| n.to_string()).unwrap_or_default(),
+        "favorites" => pick
+            .show
+            .favorites
+            .map(|n| n.to_string())
+            .unwrap_or_default(),
+        _ => pick.show.song_type(pick.song).to_owned(),
+    }
+}
+
+/// Validates a link template argument for clap
+pub fn link_template_validate(value: String) -> Result<(), String> {
+    value.parse::<LinkTemplate>().map(|_| ())
+}
+
+/// Percent-encodes everything except unreserved characters
+pub(crate) fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::fixtures;
+    use crate::Show;
+
+    #[test]
+    fn link_templates() {
+        assert!("yt".parse::<LinkTemplate>().is_err());
+        assert!("yt=".parse::<LinkTemplate>().is_err());
+        assert!("yt=https://example.com/{song"
+            .parse::<LinkTemplate>()
+            .is_err());
+        assert!("yt=https://example.com/{title}"
+            .parse::<LinkTemplate>()
+            .is_err());
+
+        let show = Show {
+            id: 22319usize.into(),
+            ..fixtures::show("Tokyo Ghoul", "#1: \"unravel\" by TK (eps 1-11)")
+        };
+        let pick = fixtures::opening(&show);
+        let built_in = LinkTemplate::built_in();
+        assert_eq!(
+            built_in[0].render(&pick),
+            "https://www.youtube.com/results?search_query=unravel+Tokyo%20Ghoul"
+        );
+        assert_eq!(
+            built_in[1].render(&pick),
+            "https://myanimelist.net/anime/22319"
+        );
+        let custom: LinkTemplate = "search=https://example.com/?q={artist}&t={type}"
+            .parse()
+            .unwrap();
+        assert_eq!(custom.render(&pick), "https://example.com/?q=TK&t=OP");
+        let mut show = fixtures::show("Tokyo Ghoul", "#1: \"unravel\" by TK");
+        show.native_titles
+            .insert("unravel".to_owned(), "アンラヴェル".to_owned());
+        let native = LinkTemplate {
+            song_lang: SongLang::Native,
+            ..built_in[0].clone()
+        };
+        assert_eq!(
+            native.render(&fixtures::opening(&show)),
+            "https://www.youtube.com/results?search_query=%E3%82%A2%E3%83%B3%E3%83%A9%E3%83%B4%E3%82%A7%E3%83%AB+Tokyo%20Ghoul"
+        );
+        assert!("studio=https://example.com/{custom.}"
+            .parse::<LinkTemplate>()
+            .is_err());
+        let studio: LinkTemplate = "studio=https://example.com/{custom.studio}"
+            .parse()
+            .unwrap();
+        assert_eq!(studio.render(&pick), "https://example.com/");
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(encode("Ah Yeah!!"), "Ah%20Yeah%21%21");
+        assert_eq!(encode("聖"), "%E8%81%96");
+    }
+}
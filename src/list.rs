@@ -0,0 +1,226 @@
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::ShowId;
+
+/// Where a show is on the user's watch list, as kept by MAL and AniList
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum WatchStatus {
+    Watching,
+    Completed,
+    OnHold,
+    Dropped,
+    PlanToWatch,
+}
+
+impl WatchStatus {
+    pub const NAMES: &'static [&'static str] = &[
+        "watching",
+        "completed",
+        "on-hold",
+        "dropped",
+        "plan-to-watch",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Watching => "watching",
+            Self::Completed => "completed",
+            Self::OnHold => "on-hold",
+            Self::Dropped => "dropped",
+            Self::PlanToWatch => "plan-to-watch",
+        }
+    }
+}
+
+impl FromStr for WatchStatus {
+    type Err = String;
+
+    /// Accepts the names used by MAL (`on_hold`) and AniList (`PAUSED`) as well as our own
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "watching" | "current" | "repeating" => Ok(Self::Watching),
+            "completed" => Ok(Self::Completed),
+            "on-hold" | "paused" => Ok(Self::OnHold),
+            "dropped" => Ok(Self::Dropped),
+            "plan-to-watch" | "planning" => Ok(Self::PlanToWatch),
+            _ => Err(format!(
+                "invalid watch status '{}', expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for WatchStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// A show of the list along with what the user keeps about it
+///
+/// In the list file an entry is either just the id of the show, or an object like
+/// `{"id": 24833, "status": "completed"}`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "RawEntry")]
+pub struct ListEntry {
+    pub id: ShowId,
+    pub status: Option<WatchStatus>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawEntry {
+    Id(ShowId),
+    Entry {
+        #[serde(alias = "mal_id")]
+        id: ShowId,
+        #[serde(default)]
+        status: Option<WatchStatus>,
+    },
+}
+
+impl From<RawEntry> for ListEntry {
+    fn from(raw: RawEntry) -> Self {
+        match raw {
+            RawEntry::Id(id) => id.into(),
+            RawEntry::Entry { id, status } => ListEntry { id, status },
+        }
+    }
+}
+
+impl Serialize for ListEntry {
+    /// Entries without metadata are written as just the id, like they are usually read
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.status {
+            None => self.id.serialize(serializer),
+            Some(status) => {
+                let mut entry = serializer.serialize_struct("ListEntry", 2)?;
+                entry.serialize_field("id", &self.id)?;
+                entry.serialize_field("status", status.name())?;
+                entry.end()
+            }
+        }
+    }
+}
+
+impl From<ShowId> for ListEntry {
+    fn from(id: ShowId) -> Self {
+        ListEntry { id, status: None }
+    }
+}
+
+/// What to do with shows that are on the list more than once, set with `--list-duplicates`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ListDuplicates {
+    /// Only the first entry of a show counts
+    #[default]
+    Dedupe,
+    /// A show is as much more likely to be picked as it is on the list more often
+    Weight,
+    /// Duplicates are an error
+    Error,
+}
+
+impl ListDuplicates {
+    pub const NAMES: &'static [&'static str] = &["dedupe", "weight", "error"];
+
+    /// Applies the policy to a list, failing with a message naming the duplicates for `Error`
+    pub fn apply(self, list: Vec<ListEntry>) -> Result<Vec<ListEntry>, String> {
+        match self {
+            Self::Weight => Ok(list),
+            Self::Dedupe => {
+                let mut seen = HashSet::with_capacity(list.len());
+                Ok(list
+                    .into_iter()
+                    .filter(|entry| seen.insert(entry.id.clone()))
+                    .collect())
+            }
+            Self::Error => {
+                let mut seen = HashSet::with_capacity(list.len());
+                let mut duplicates: Vec<&ShowId> = vec![];
+                for entry in &list {
+                    if !seen.insert(&entry.id) && !duplicates.contains(&&entry.id) {
+                        duplicates.push(&entry.id);
+                    }
+                }
+                if duplicates.is_empty() {
+                    return Ok(list);
+                }
+                let duplicates: Vec<String> = duplicates.iter().map(|id| id.to_string()).collect();
+                Err(format!(
+                    "shows on the list more than once: {}",
+                    duplicates.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+impl FromStr for ListDuplicates {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dedupe" => Ok(Self::Dedupe),
+            "weight" => Ok(Self::Weight),
+            "error" => Ok(Self::Error),
+            _ => Err(format!(
+                "invalid list duplicates policy '{}', expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_entries() {
+        let list: Vec<ListEntry> = serde_json::from_str(
+            r#"[24833, "my-show", {"id": 1, "status": "COMPLETED"}, {"mal_id": 2, "status": "on_hold"}, {"id": 3}]"#,
+        )
+        .unwrap();
+        assert_eq!(list[0], ShowId::from(24833usize).into());
+        assert_eq!(list[1], ShowId::from("my-show").into());
+        assert_eq!(list[2].status, Some(WatchStatus::Completed));
+        assert_eq!(list[3].id, ShowId::from(2usize));
+        assert_eq!(list[3].status, Some(WatchStatus::OnHold));
+        assert_eq!(list[4].status, None);
+
+        assert!(
+            serde_json::from_str::<Vec<ListEntry>>(r#"[{"id": 1, "status": "binged"}]"#).is_err()
+        );
+        assert_eq!("PAUSED".parse(), Ok(WatchStatus::OnHold));
+    }
+
+    #[test]
+    fn list_duplicates() {
+        let list: Vec<ListEntry> = serde_json::from_str("[1, 2, 1, 3, 1, 2]").unwrap();
+        let ids = |list: Vec<ListEntry>| -> Vec<String> {
+            list.iter().map(|entry| entry.id.to_string()).collect()
+        };
+        assert_eq!(
+            ids(ListDuplicates::Dedupe.apply(list.clone()).unwrap()),
+            ["1", "2", "3"]
+        );
+        assert_eq!(ListDuplicates::Weight.apply(list.clone()).unwrap(), list);
+        assert_eq!(
+            ListDuplicates::Error.apply(list.clone()),
+            Err("shows on the list more than once: 1, 2".to_owned())
+        );
+        assert!(ListDuplicates::Error.apply(list[..2].to_vec()).is_ok());
+    }
+}
@@ -0,0 +1,91 @@
+//! Locks around updates of the favorites and the session, so runs at the same time don't lose
+//! each other's changes
+//!
+//! A lock is a file next to the locked one, created only if it doesn't exist yet and removed when
+//! the lock is dropped. A run that was killed while holding it leaves it behind, so waiting for it
+//! gives up after a while and names the file to remove.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for another run to finish its update
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// How often to check if the other run is done
+const RETRY: Duration = Duration::from_millis(20);
+
+/// A lock on a file, released when dropped
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Waits until no other run holds the lock on `path` and takes it, the file itself doesn't
+    /// have to exist
+    pub fn acquire<P: AsRef<Path>>(path: P) -> io::Result<FileLock> {
+        let mut lock = path.as_ref().as_os_str().to_owned();
+        lock.push(".lock");
+        let lock = PathBuf::from(lock);
+
+        let start = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&lock) {
+                Ok(mut file) => {
+                    // Only for people wondering who holds it
+                    let _ = write!(file, "{}", std::process::id());
+                    return Ok(FileLock { path: lock });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    if start.elapsed() >= TIMEOUT {
+                        return Err(io::Error::new(
+                            e.kind(),
+                            format!(
+                                "{} is held by another run, remove it if none is running",
+                                lock.display()
+                            ),
+                        ));
+                    }
+                    thread::sleep(RETRY);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locking() {
+        let path = std::env::temp_dir().join(format!("lock-test-{}.json", std::process::id()));
+        let lock_path = path.with_extension("json.lock");
+
+        let lock = FileLock::acquire(&path).unwrap();
+        assert!(lock_path.exists());
+        // Waits for the first lock instead of failing
+        let waiting = {
+            let path = path.clone();
+            thread::spawn(move || {
+                let start = Instant::now();
+                FileLock::acquire(&path).map(|lock| (lock, start.elapsed()))
+            })
+        };
+        thread::sleep(Duration::from_millis(100));
+        drop(lock);
+        let (lock, waited) = waiting.join().unwrap().unwrap();
+        assert!(waited >= Duration::from_millis(50));
+        drop(lock);
+        assert!(!lock_path.exists());
+    }
+}
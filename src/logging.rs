@@ -0,0 +1,180 @@
+//! Log levels that can be set per module with `--log`
+
+use std::str::FromStr;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// The module all log messages of this crate come from
+const CRATE: &str = "random_show_themes";
+
+/// The levels `--log-level` takes, errors can't be turned off with it
+pub const LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+/// Log levels by module, e.g. `selection=debug,quota=trace`
+///
+/// Modules are named relative to the crate. An entry without a module sets the level of every
+/// module that isn't named.
+#[derive(Debug, Default, PartialEq)]
+pub struct LogSpec {
+    pub default: Option<LevelFilter>,
+    pub modules: Vec<(String, LevelFilter)>,
+}
+
+impl FromStr for LogSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut spec = LogSpec::default();
+        for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let (module, level) = match directive.split_once('=') {
+                Some((module, level)) => (Some(module.trim()), level.trim()),
+                None => (None, directive),
+            };
+            let level = level.parse::<LevelFilter>().map_err(|_| {
+                format!(
+                    "invalid log level '{}', expected one of: off, error, warn, info, debug, trace",
+                    level
+                )
+            })?;
+            match module {
+                Some("") => return Err(format!("missing module in '{}'", directive)),
+                Some(module) => {
+                    let module = module
+                        .strip_prefix(CRATE)
+                        .and_then(|m| m.strip_prefix("::"))
+                        .unwrap_or(module);
+                    spec.modules.push((module.to_owned(), level));
+                }
+                None => spec.default = Some(level),
+            }
+        }
+        Ok(spec)
+    }
+}
+
+pub fn log_spec_validate(v: String) -> Result<(), String> {
+    v.parse::<LogSpec>().map(|_| ())
+}
+
+/// Filters log messages by the level of their module before handing them to `inner`
+pub struct ScopedLogger<L> {
+    inner: L,
+    default: LevelFilter,
+    /// Full module paths, the most specific ones first
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl<L: Log> ScopedLogger<L> {
+    /// `inner` is expected to let every message through
+    pub fn new(inner: L, default: LevelFilter, spec: LogSpec) -> Self {
+        let mut modules: Vec<(String, LevelFilter)> = spec
+            .modules
+            .into_iter()
+            .map(|(module, level)| (format!("{}::{}", CRATE, module), level))
+            .collect();
+        // Later entries for the same module win
+        modules.reverse();
+        modules.sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+        ScopedLogger {
+            inner,
+            default: spec.default.unwrap_or(default),
+            modules,
+        }
+    }
+
+    /// The level of the module a message is logged from, messages from other crates are dropped
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        if !is_within(CRATE, target) {
+            return LevelFilter::Off;
+        }
+        self.modules
+            .iter()
+            .find(|(module, _)| is_within(module, target))
+            .map_or(self.default, |&(_, level)| level)
+    }
+
+    /// The most verbose level of any module
+    pub fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|&(_, level)| level)
+            .fold(self.default, std::cmp::max)
+    }
+}
+
+impl<L: Log> Log for ScopedLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Checks if `target` is `module` or one of its submodules
+fn is_within(module: &str, target: &str) -> bool {
+    target
+        .strip_prefix(module)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Discard;
+
+    impl Log for Discard {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, _: &Record) {}
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn scoped_log_levels() {
+        let spec: LogSpec = "selection=debug, quota=off,selection::inner=trace"
+            .parse()
+            .unwrap();
+        assert_eq!(spec.default, None);
+        assert_eq!(spec.modules.len(), 3);
+        assert!("selection=loud".parse::<LogSpec>().is_err());
+        assert!("=debug".parse::<LogSpec>().is_err());
+        assert_eq!(
+            "info".parse::<LogSpec>().unwrap().default,
+            Some(LevelFilter::Info)
+        );
+
+        let logger = ScopedLogger::new(Discard, LevelFilter::Warn, spec);
+        assert_eq!(
+            logger.level_for("random_show_themes::selection"),
+            LevelFilter::Debug
+        );
+        assert_eq!(
+            logger.level_for("random_show_themes::selection::inner"),
+            LevelFilter::Trace
+        );
+        assert_eq!(
+            logger.level_for("random_show_themes::quota"),
+            LevelFilter::Off
+        );
+        // Only whole module names match
+        assert_eq!(
+            logger.level_for("random_show_themes::selections"),
+            LevelFilter::Warn
+        );
+        assert_eq!(logger.level_for("random_show_themes"), LevelFilter::Warn);
+        assert_eq!(logger.level_for("serde_json"), LevelFilter::Off);
+        assert_eq!(logger.max_level(), LevelFilter::Trace);
+    }
+}
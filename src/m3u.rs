@@ -0,0 +1,57 @@
+//! Extended M3U playlists for `--m3u`, which players like mpv and VLC open directly
+
+use crate::links::encode;
+use crate::selection::Pick;
+
+/// The first line of every extended M3U playlist
+pub const HEADER: &str = "#EXTM3U";
+
+/// Where shows without a url are searched for
+const SEARCH: &str = "https://animethemes.moe/search?q=";
+
+/// The lines of a pick: its title, without a known duration, and its location
+pub fn entry(title: &str, pick: &Pick) -> String {
+    format!(
+        "#EXTINF:-1,{}\n{}",
+        title.replace(['\n', '\r'], " "),
+        location(pick)
+    )
+}
+
+/// The url of the show, or a search for it
+fn location(pick: &Pick) -> String {
+    match &pick.show.url {
+        Some(url) => url.clone(),
+        None => format!("{}{}", SEARCH, encode(&pick.show.title)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::fixtures;
+    use crate::Show;
+
+    #[test]
+    fn entries() {
+        let show = fixtures::bebop();
+        let pick = fixtures::opening(&show);
+        assert_eq!(
+            entry(pick.song, &pick),
+            "#EXTINF:-1,\"Tank!\" by The Seatbelts\nhttps://animethemes.moe/search?q=Cowboy%20Bebop"
+        );
+
+        let show_with_url = Show {
+            url: Some("https://example.com/tank.webm".to_owned()),
+            ..Default::default()
+        };
+        let pick = Pick {
+            show: &show_with_url,
+            ..pick
+        };
+        assert_eq!(
+            entry("Tank!\nTV size", &pick),
+            "#EXTINF:-1,Tank! TV size\nhttps://example.com/tank.webm"
+        );
+    }
+}
@@ -1,176 +1,962 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
 use clap::ArgMatches;
-use log::{error, info};
-use rand::seq::SliceRandom;
-use term_table::{row::Row, table_cell::TableCell, Table};
+use log::{error, info, warn};
 
+use random_show_themes::argfile::expand_arg_files;
+use random_show_themes::canonical;
+use random_show_themes::columns::Column;
+use random_show_themes::config::{self, Config};
+use random_show_themes::demo;
+use random_show_themes::favorites;
+use random_show_themes::list::{ListDuplicates, ListEntry};
+use random_show_themes::lock::FileLock;
+use random_show_themes::paths;
+use random_show_themes::progress::Progress;
+use random_show_themes::receipt::{DataFile, Receipt};
+use random_show_themes::rng::CountingRng;
+use random_show_themes::selection::{
+    every_theme, select, Pick, SelectionOptions, SelectionStrategy,
+};
+use random_show_themes::session::Session;
+use random_show_themes::sink::{self, Delivery, Sink, SinkFailure};
+use random_show_themes::sort::{self, SortKey};
+use random_show_themes::template::Totals;
+use random_show_themes::theme::SongLang;
+use random_show_themes::timezone::Timezone;
+use random_show_themes::truncate::truncate;
 use random_show_themes::{
-    create_clap_app, create_table, output_theme, read_json_file, set_up_logging, smart_append,
-    OutputMode, Show,
+    check_features, create_clap_app, create_table, data_file_path, imply_pick_subcommand,
+    is_broken_pipe, output_footer, output_header, output_summary, output_theme, read_json_file,
+    set_up_logging, write_json_file, Grouping, OutputMode, Show, ShowId,
 };
 
+/// Reads the list, which may be encrypted with the `encrypted` feature
+fn read_list(path: &Path, matches: &ArgMatches) -> Result<Vec<ListEntry>, i32> {
+    #[cfg(feature = "encrypted")]
+    let list = random_show_themes::encrypted::read_json_file(path, matches.value_of("identity"));
+    #[cfg(not(feature = "encrypted"))]
+    let list = {
+        let _ = matches;
+        read_json_file(path)
+    };
+    list.map_err(|e| {
+        error!("couldn't parse list {}: {}", path.display(), e);
+        EXIT_FAILURE
+    })
+}
+
+/// Exit code for any error
+const EXIT_FAILURE: i32 = 1;
+/// Exit code for when fewer results than requested could be output with --expect-exact
+const EXIT_SHORT: i32 = 3;
+
 fn main() {
-    let matches = create_clap_app().get_matches();
+    let mut args = expand_arg_files(std::env::args_os()).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(EXIT_FAILURE);
+    });
+    let implied_pick = imply_pick_subcommand(&mut args);
+    match Config::load() {
+        Ok(Some(config)) => config.apply(&mut args),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(EXIT_FAILURE);
+        }
+    }
+    let matches = create_clap_app().get_matches_from(args);
+    // A subcommand is required by clap
+    let (subcommand, matches) = matches.subcommand();
+    let matches = matches.unwrap();
 
     // Set up all logging stuff
-    set_up_logging(&matches);
+    // Global arguments are propagated to the subcommand
+    set_up_logging(matches);
+
+    if implied_pick {
+        warn!("running without a subcommand is deprecated, use `random-show-themes pick` instead");
+    }
 
-    match run(&matches) {
-        Err(()) => std::process::exit(1),
-        Ok(()) => {}
+    let result = match subcommand {
+        "selftest" => selftest(matches),
+        "verify" => verify(matches),
+        "fav" => fav(matches),
+        "disable" => set_disabled(matches, true),
+        "enable" => set_disabled(matches, false),
+        "fmt" => fmt(matches),
+        "paths" => print_paths(matches),
+        _ => pick(matches),
+    };
+    if let Err(code) = result {
+        std::process::exit(code);
     }
 }
 
-fn run(matches: &ArgMatches) -> Result<(), ()> {
+fn pick(matches: &ArgMatches) -> Result<(), i32> {
+    check_features(matches).map_err(|e| {
+        error!("{}", e);
+        EXIT_FAILURE
+    })?;
+
+    if let Some(number) = matches.value_of("explain") {
+        return explain(
+            matches.value_of("receipt").unwrap(),
+            number.parse().unwrap(),
+        )
+        .map_err(|()| EXIT_FAILURE);
+    }
+
     // Get inital argument values
-    let dictionary: PathBuf = matches.value_of("dictionary").unwrap().into();
-    let list: PathBuf = matches.value_of("list").unwrap().into();
-    let number_of_results: usize = matches.value_of("number").unwrap().parse().unwrap();
+    // Either the arguments, the data directory or the demo are required by clap
+    let demo = matches.is_present("demo");
+    let (dictionary, list) = if demo {
+        (
+            PathBuf::from(demo::DICTIONARY_NAME),
+            PathBuf::from(demo::LIST_NAME),
+        )
+    } else {
+        let list_file_name = match matches.value_of("from") {
+            Some("favorites") => favorites::FILE_NAME,
+            _ => "list.json",
+        };
+        (
+            data_file_path(matches, "dictionary", "dictionary.json").unwrap(),
+            data_file_path(matches, "list", list_file_name).unwrap(),
+        )
+    };
+    // Required by clap, unless every theme is output
+    let number_of_results: usize = matches
+        .value_of("number")
+        .map_or(0, |number| number.parse().unwrap());
 
-    let output_mode: OutputMode = OutputMode::from_matches(&matches);
+    let output_mode: OutputMode = OutputMode::from_matches(matches).map_err(|e| {
+        error!("{}", e);
+        EXIT_FAILURE
+    })?;
+    let output_sinks = sink::output_sinks(matches).map_err(|e| {
+        error!("{}", e);
+        EXIT_FAILURE
+    })?;
+    let columns = Column::from_matches(matches).map_err(|e| {
+        error!("{}", e);
+        EXIT_FAILURE
+    })?;
+    let mut selection_options = SelectionOptions::from_matches(matches);
     let hard_fail = matches.is_present("hard-fail");
+    let expect_exact = matches.is_present("expect-exact");
+    let data_files = [("dictionary", dictionary.clone()), ("list", list.clone())];
+
+    #[cfg(feature = "git")]
+    let changed = match matches.value_of("changed-since") {
+        Some(since) => Some(
+            random_show_themes::changes::changed_since(&dictionary, &list, since).map_err(|e| {
+                error!("couldn't find the changes since {}: {}", since, e);
+                EXIT_FAILURE
+            })?,
+        ),
+        None => None,
+    };
 
     // Re-assign variables to parsed data
-    let dictionary: HashMap<usize, Show> =
-        read_json_file(dictionary).expect("couldn't parse dictionary into HashMap<usize, Show>");
+    let dictionary: HashMap<ShowId, Show> = if demo {
+        demo::dictionary()
+    } else {
+        read_json_file(&dictionary).map_err(|e| {
+            error!("couldn't parse dictionary {}: {}", dictionary.display(), e);
+            EXIT_FAILURE
+        })?
+    };
+    let list: Vec<ListEntry> = if demo {
+        demo::list()
+    } else {
+        read_list(&list, matches)?
+    };
+
+    #[cfg(feature = "git")]
+    let list: Vec<ListEntry> = match changed {
+        Some(changed) => {
+            let list: Vec<ListEntry> = list
+                .into_iter()
+                .filter(|entry| changed.contains(&entry.id))
+                .collect();
+            info!("{} shows of the list were added or changed", list.len());
+            if list.is_empty() {
+                error!("no shows of the list were added or changed since the given revision");
+                return Err(EXIT_FAILURE);
+            }
+            list
+        }
+        None => list,
+    };
 
-    let list: Vec<usize> = read_json_file(list).expect("couldn't parse list into Vec<usize>");
+    // Validated by clap
+    let list_duplicates: ListDuplicates = matches
+        .value_of("list-duplicates")
+        .map_or_else(Default::default, |v| v.parse().unwrap());
+    let list = list_duplicates.apply(list).map_err(|e| {
+        error!("{}", e);
+        EXIT_FAILURE
+    })?;
 
     if dictionary.is_empty() {
         error!("dictionary cannot be empty");
-        return Err(());
+        return Err(EXIT_FAILURE);
     } else if list.is_empty() {
         error!("list cannot be empty");
-        return Err(());
+        return Err(EXIT_FAILURE);
     }
 
-    let list_len = list.len();
-    let number_of_results = if list_len < number_of_results {
-        error!(
-            "{} results were requested, however the list only contained {} entries",
-            number_of_results, list_len
+    if matches.is_present("check") {
+        return check(
+            &data_files,
+            &selection_options,
+            number_of_results,
+            &list,
+            &dictionary,
         );
-        if hard_fail {
-            return Err(());
-        }
-        info!("requesting {} results instead", { list_len });
-        list_len
+    }
+
+    if matches.is_present("all") {
+        return output_all(
+            &selection_options,
+            &list,
+            &dictionary,
+            &columns,
+            &output_mode,
+            matches,
+            &mut Progress::from_matches(matches, "export"),
+        );
+    }
+
+    let mut rng = CountingRng::new(rand::thread_rng());
+
+    // The session is saved once the picks were output, with its lock held until then
+    let mut session = None;
+    let mut picks = if let SelectionStrategy::ExhaustThenReshuffle = selection_options.strategy {
+        let path = paths::session_file().ok_or_else(|| {
+            error!("couldn't find a directory for the session, HOME isn't set");
+            EXIT_FAILURE
+        })?;
+        let lock = Session::lock(&path).map_err(|e| {
+            error!("couldn't lock the session: {}", e);
+            EXIT_FAILURE
+        })?;
+        let mut unplayed = Session::load(&path).map_err(|e| {
+            error!("couldn't read the session: {}", e);
+            EXIT_FAILURE
+        })?;
+        let picks = unplayed.select_unplayed(
+            &mut selection_options,
+            number_of_results,
+            &list,
+            &dictionary,
+            &mut rng,
+        );
+        session = Some((unplayed, path, lock));
+        picks
     } else {
-        number_of_results
+        select(
+            &selection_options,
+            number_of_results,
+            &list,
+            &dictionary,
+            &mut rng,
+        )
     };
 
-    let mut rng = &mut rand::thread_rng();
+    // Whether the list is too short or filters and constraints leave too few themes, and whatever
+    // the strategy, everything that could be picked is output, unless --expect-exact says not to
+    let short = picks.len() < number_of_results;
+    if short {
+        let message = format!(
+            "{} results were requested, however only {} could be selected",
+            number_of_results,
+            picks.len()
+        );
+        if expect_exact {
+            error!("{}", message);
+            return Err(EXIT_SHORT);
+        } else if hard_fail {
+            error!("{}", message);
+        } else {
+            warn!("{}", message);
+        }
+    }
 
-    // Before result loop output
-    let mut table = match output_mode {
-        OutputMode::Table => {
-            let mut table = create_table(&matches);
+    // Validated by clap
+    if let Some(key) = matches
+        .value_of("sort")
+        .map(|v| v.parse::<SortKey>().unwrap())
+    {
+        key.sort(&mut picks, matches.is_present("reverse"));
+    }
+    if matches.is_present("group-by-show") {
+        sort::group_by_show(&mut picks);
+    }
 
-            table.add_row(Row::new(vec![
-                TableCell::new("Song"),
-                TableCell::new("Show"),
-                TableCell::new("Type"),
-            ]));
+    // Validated by clap
+    let timezone: Timezone = matches
+        .value_of("timezone")
+        .map(|v| v.parse().unwrap())
+        .unwrap_or_default();
+    if let Some(path) = matches.value_of("receipt") {
+        let mut receipt = Receipt::new(&selection_options, number_of_results, &picks);
+        receipt.created = Some(timezone.now());
+        receipt.rng_draws = Some(rng.draws());
+        // The demo has no files to fingerprint
+        for (role, data_path) in data_files.iter().filter(|_| !demo) {
+            match DataFile::new(role, data_path) {
+                Ok(data_file) => receipt.data_files.push(data_file),
+                Err(e) => error!("couldn't fingerprint {}: {}", role, e),
+            }
+        }
+        if let Err(e) = receipt.write(path) {
+            error!("couldn't write receipt: {}", e);
+            if hard_fail {
+                return Err(EXIT_FAILURE);
+            }
+        }
+    }
 
-            Some(table)
+    let mut sinks: Vec<Sink> = matches
+        .values_of("out")
+        // Validated by clap
+        .map(|values| values.map(|v| v.parse().unwrap()).collect())
+        .unwrap_or_default();
+    sinks.extend(output_sinks);
+    if sinks.is_empty() {
+        write_picks(
+            &mut io::stdout(),
+            matches,
+            &picks,
+            &columns,
+            &output_mode,
+            hard_fail,
+        )?;
+    }
+    let sink_failure = match matches.value_of("sink-failure") {
+        // Validated by clap
+        Some(policy) => policy.parse().unwrap(),
+        None if hard_fail => SinkFailure::Abort,
+        None => SinkFailure::Continue,
+    };
+    // Every format is checked before anything is delivered
+    let sink_modes = sinks
+        .iter()
+        .map(|sink| match &sink.format {
+            Some(name) => OutputMode::from_name(name, matches),
+            None => OutputMode::from_matches(matches),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| {
+            error!("{}", e);
+            EXIT_FAILURE
+        })?;
+    let mut deliveries = Vec::with_capacity(sinks.len());
+    for (sink, output_mode) in sinks.iter().zip(sink_modes) {
+        if sink_failure == SinkFailure::Abort
+            && deliveries.iter().any(|(_, d)| *d == Delivery::Failed)
+        {
+            deliveries.push((sink, Delivery::Skipped));
+            continue;
         }
-        OutputMode::Readable => None,
-        OutputMode::CSV => {
-            let mut wtr = csv::Writer::from_writer(std::io::stdout());
-            if let Err(e) = wtr.write_record(&["Song", "Show", "Type"]) {
+        let output_mode = if sink.is_stdout() {
+            output_mode
+        } else {
+            output_mode.without_colors()
+        };
+        let output_mode = if sink.continues_file() {
+            output_mode.without_header()
+        } else {
+            output_mode
+        };
+        let mut output = vec![];
+        write_picks(
+            &mut output,
+            matches,
+            &picks,
+            &columns,
+            &output_mode,
+            hard_fail,
+        )?;
+        // Stdout closed early, e.g. by `| head -1`, fails like any other sink
+        match sink.deliver_with(&output, sink_failure) {
+            Ok(()) => deliveries.push((sink, Delivery::Delivered)),
+            Err(e) => {
                 error!("{}", e);
-                return Err(());
+                deliveries.push((sink, Delivery::Failed));
+            }
+        }
+    }
+    if deliveries.iter().all(|(_, d)| *d == Delivery::Delivered) {
+        if !deliveries.is_empty() {
+            info!("{}", sink::summary(&deliveries));
+        }
+    } else {
+        warn!("{}", sink::summary(&deliveries));
+        if hard_fail || sink_failure == SinkFailure::Abort {
+            return Err(EXIT_FAILURE);
+        }
+    }
+
+    // Picks only count as played once they were output somewhere
+    let delivered = sinks.is_empty() || deliveries.iter().any(|(_, d)| *d == Delivery::Delivered);
+    if let Some((session, path, lock)) = session.filter(|_| delivered) {
+        if let Err(e) = session.save(&path) {
+            error!("couldn't save the session: {}", e);
+            return Err(EXIT_FAILURE);
+        }
+        drop(lock);
+        let (played, total) = session.progress(&selection_options, &list, &dictionary);
+        info!("{}/{} played", played, total);
+    }
+
+    if let Some(path) = matches.value_of("xlsx") {
+        if let Err(e) = random_show_themes::xlsx::write(Path::new(path), &columns, &picks) {
+            error!("{}", e);
+            if hard_fail {
+                return Err(EXIT_FAILURE);
+            }
+        }
+    }
+    if let Some(path) = matches.value_of("rss") {
+        let song = Column::song(&columns);
+        let written = random_show_themes::rss::write(Path::new(path), &song, &picks, timezone);
+        if let Err(e) = written {
+            error!("{}", e);
+            if hard_fail {
+                return Err(EXIT_FAILURE);
             }
-            if let Err(e) = wtr.flush() {
+        }
+    }
+
+    // Once, however many sinks there are
+    if matches.is_present("summary") && !output_mode.is_for_people() {
+        eprintln!("{}", Totals::of(&picks).summary());
+    }
+
+    // Only after the output, which has everything that could be picked
+    if short && hard_fail {
+        return Err(EXIT_FAILURE);
+    }
+
+    let song_lang = SongLang::from_matches(matches);
+    if let Some(template) = matches.value_of("exec") {
+        for (i, pick) in picks.iter().enumerate() {
+            if let Err(e) = random_show_themes::exec::exec(template, i + 1, pick, song_lang) {
                 error!("{}", e);
-                return Err(());
+                if hard_fail {
+                    return Err(EXIT_FAILURE);
+                }
+            }
+        }
+    }
+    if let Some(command) = matches.value_of("exec-after") {
+        if let Err(e) = random_show_themes::exec::exec_after(command, &picks, &columns) {
+            error!("{}", e);
+            if hard_fail {
+                return Err(EXIT_FAILURE);
+            }
+        }
+    }
+
+    #[cfg(feature = "announce")]
+    {
+        if matches.is_present("announce") {
+            for pick in &picks {
+                if let Err(e) = random_show_themes::announce::announce(pick, song_lang) {
+                    error!("{}", e);
+                    if hard_fail {
+                        return Err(EXIT_FAILURE);
+                    }
+                }
             }
-            None
         }
+    }
+
+    Ok(())
+}
+
+fn selftest(matches: &ArgMatches) -> Result<(), i32> {
+    // All validated by clap
+    let iterations = matches.value_of("iterations").unwrap().parse().unwrap();
+    let shows = matches.value_of("shows").unwrap().parse().unwrap();
+    let picks = matches.value_of("picks").unwrap().parse().unwrap();
+
+    let report = random_show_themes::selftest::run(
+        SelectionStrategy::from_matches(matches),
+        iterations,
+        shows,
+        picks,
+        &mut rand::thread_rng(),
+        &mut Progress::from_matches(matches, "selftest"),
+    );
+    print_out(format_args!("{}\n", report))?;
+
+    if report.passed() {
+        Ok(())
+    } else {
+        Err(EXIT_FAILURE)
+    }
+}
+
+fn fav(matches: &ArgMatches) -> Result<(), i32> {
+    // Required by clap
+    let data_dir = Path::new(matches.value_of("data-dir").unwrap());
+    let path = data_dir.join(favorites::FILE_NAME);
+    // Held until the favorites are written, so runs at the same time don't undo each other
+    let _lock = match matches.subcommand_name() {
+        Some("list") => None,
+        _ => Some(FileLock::acquire(&path).map_err(|e| {
+            error!("couldn't lock {}: {}", path.display(), e);
+            EXIT_FAILURE
+        })?),
     };
+    let mut favorites = favorites::load(&path).map_err(|e| {
+        error!("couldn't read {}: {}", path.display(), e);
+        EXIT_FAILURE
+    })?;
+    // The dictionary is only used to show titles and catch typos, so it's fine if it's missing
+    let dictionary: HashMap<ShowId, Show> =
+        read_json_file(data_dir.join("dictionary.json")).unwrap_or_default();
 
-    match result_loop(
-        number_of_results,
-        &list,
-        &dictionary,
-        &mut rng,
-        &output_mode,
-        &mut table,
-    ) {
-        Err(x) => {
-            if hard_fail {
-                return Err(x);
+    match matches.subcommand() {
+        ("list", _) => {
+            for entry in &favorites {
+                match dictionary.get(&entry.id) {
+                    Some(show) => print_out(format_args!("{} {}\n", entry.id, show.title))?,
+                    None => print_out(format_args!("{}\n", entry.id))?,
+                }
+            }
+            return Ok(());
+        }
+        ("add", Some(matches)) => {
+            for id in matches.values_of("id").unwrap().map(ShowId::from) {
+                if !dictionary.is_empty() && !dictionary.contains_key(&id) {
+                    warn!("{} isn't in the dictionary", id);
+                }
+                if !favorites::add(&mut favorites, id.clone()) {
+                    info!("{} already is a favorite", id);
+                }
+            }
+        }
+        ("remove", Some(matches)) => {
+            for id in matches.values_of("id").unwrap().map(ShowId::from) {
+                if !favorites::remove(&mut favorites, &id) {
+                    warn!("{} isn't a favorite", id);
+                }
+            }
+        }
+        // A subcommand is required by clap
+        _ => unreachable!(),
+    }
+
+    write_json_file(&path, &favorites).map_err(|e| {
+        error!("couldn't write {}: {}", path.display(), e);
+        EXIT_FAILURE
+    })
+}
+
+/// Disables or enables shows in the dictionary, leaving everything else about them as it is
+fn set_disabled(matches: &ArgMatches, disabled: bool) -> Result<(), i32> {
+    // Either the dictionary or the data directory are required by clap
+    let path = data_file_path(matches, "dictionary", "dictionary.json").unwrap();
+    let mut dictionary: serde_json::Map<String, serde_json::Value> = read_json_file(&path)
+        .map_err(|e| {
+            error!("couldn't parse dictionary {}: {}", path.display(), e);
+            EXIT_FAILURE
+        })?;
+
+    let mut changed = false;
+    let mut missing = false;
+    // Required by clap
+    for id in matches.values_of("id").unwrap() {
+        let show = match dictionary.get_mut(id).and_then(|show| show.as_object_mut()) {
+            Some(show) => show,
+            None => {
+                error!("{} isn't in the dictionary", id);
+                missing = true;
+                continue;
             }
+        };
+        let was_disabled = show.get("disabled") == Some(&serde_json::Value::Bool(true));
+        if was_disabled == disabled {
+            info!(
+                "{} already is {}",
+                id,
+                if disabled { "disabled" } else { "enabled" }
+            );
+            continue;
+        }
+        if disabled {
+            show.insert("disabled".to_owned(), serde_json::Value::Bool(true));
+        } else {
+            show.remove("disabled");
         }
-        Ok(_) => {}
+        changed = true;
     }
 
-    // After result loop output
-    match output_mode {
-        OutputMode::Table => {
-            // The table has to exist if the output mode is set to table
-            println!("{}", table.as_mut().unwrap().render());
+    if changed {
+        write_json_file(&path, &dictionary).map_err(|e| {
+            error!("couldn't write {}: {}", path.display(), e);
+            EXIT_FAILURE
+        })?;
+    }
+    if missing {
+        Err(EXIT_FAILURE)
+    } else {
+        Ok(())
+    }
+}
+
+fn fmt(matches: &ArgMatches) -> Result<(), i32> {
+    let check = matches.is_present("check");
+    let mut formatted = true;
+    // Required by clap
+    for path in matches.values_of("file").unwrap() {
+        let value: serde_json::Value = match read_json_file(path) {
+            Ok(value) => value,
+            Err(e) => {
+                error!("couldn't read {}: {}", path, e);
+                formatted = false;
+                continue;
+            }
+        };
+        if std::fs::read_to_string(path).ok() == Some(canonical::to_string(&value)) {
+            continue;
+        }
+
+        if check {
+            print_out(format_args!("{} isn't formatted\n", path))?;
+            formatted = false;
+        } else if let Err(e) = write_json_file(path, &value) {
+            error!("couldn't write {}: {}", path, e);
+            formatted = false;
+        } else {
+            info!("formatted {}", path);
         }
-        // No cleanup required for readable
-        OutputMode::Readable => {}
-        // We don't own and pass around the writer, we create a new one and flush it each time, so we don't flush it here
-        OutputMode::CSV => {}
     }
 
+    if formatted {
+        Ok(())
+    } else {
+        Err(EXIT_FAILURE)
+    }
+}
+
+fn print_paths(matches: &ArgMatches) -> Result<(), i32> {
+    let unknown = || "unknown, HOME isn't set".to_owned();
+    let describe = |path: &Path| {
+        if path.exists() {
+            path.display().to_string()
+        } else {
+            format!("{} (doesn't exist yet)", path.display())
+        }
+    };
+
+    let config = match std::env::var_os(config::ENV) {
+        Some(path) => format!("{} (from {})", describe(Path::new(&path)), config::ENV),
+        None => paths::config_file().map_or_else(unknown, |path| describe(&path)),
+    };
+    print_out(format_args!("config      {}\n", config))?;
+    let session = paths::session_file().map_or_else(unknown, |path| describe(&path));
+    print_out(format_args!("session     {}\n", session))?;
+
+    match matches.value_of("data-dir") {
+        Some(dir) => {
+            for file_name in ["dictionary.json", "list.json", favorites::FILE_NAME] {
+                let name = file_name.trim_end_matches(".json");
+                let path = Path::new(dir).join(file_name);
+                print_out(format_args!("{:<11} {}\n", name, describe(&path)))?;
+            }
+        }
+        None => print_out(format_args!(
+            "data files  given with --data-dir or -d/-l, none set\n"
+        ))?,
+    }
     Ok(())
 }
 
-fn result_loop(
+/// Reports what picks would be made from, without making any, for CI of the data files
+///
+/// Fails when shows of the list aren't in the dictionary or fewer shows than requested can be
+/// picked.
+fn check(
+    data_files: &[(&str, PathBuf)],
+    options: &SelectionOptions,
     number_of_results: usize,
-    list: &Vec<usize>,
-    dictionary: &HashMap<usize, Show>,
-    rng: &mut impl rand::Rng,
+    list: &[ListEntry],
+    dictionary: &HashMap<ShowId, Show>,
+) -> Result<(), i32> {
+    // Already loaded and validated on startup, or that would have failed
+    let config = std::env::var_os(config::ENV)
+        .map(PathBuf::from)
+        .or_else(|| paths::config_file().filter(|path| path.exists()));
+    match config {
+        Some(path) => print_out(format_args!("config {}: ok\n", path.display()))?,
+        None => print_out(format_args!("config: none\n"))?,
+    }
+    let (dictionary_path, list_path) = (&data_files[0].1, &data_files[1].1);
+    print_out(format_args!(
+        "dictionary {}: {} shows\n",
+        dictionary_path.display(),
+        dictionary.len()
+    ))?;
+
+    let mut ok = true;
+    let mut seen = HashSet::with_capacity(list.len());
+    let ids: Vec<&ShowId> = list
+        .iter()
+        .map(|entry| &entry.id)
+        .filter(|id| seen.insert(*id))
+        .collect();
+    print_out(format_args!(
+        "list {}: {} shows\n",
+        list_path.display(),
+        ids.len()
+    ))?;
+    let missing: Vec<String> = ids
+        .iter()
+        .filter(|id| !dictionary.contains_key(id))
+        .map(|id| id.to_string())
+        .collect();
+    if !missing.is_empty() {
+        ok = false;
+        print_out(format_args!(
+            "list {}: not in the dictionary: {}\n",
+            list_path.display(),
+            missing.join(", ")
+        ))?;
+    }
+
+    let filters = options.filter_names();
+    let filters = if filters.is_empty() {
+        "none".to_owned()
+    } else {
+        filters.join(", ")
+    };
+    print_out(format_args!("filters: {}\n", filters))?;
+    let themes: Vec<Pick> = every_theme(options, list, dictionary).collect();
+    // Shows without eligible themes can't be picked
+    let shows = themes
+        .iter()
+        .map(|pick| &pick.show.id)
+        .collect::<HashSet<_>>()
+        .len();
+    print_out(format_args!(
+        "eligible: {} shows, {} themes\n",
+        shows,
+        themes.len()
+    ))?;
+    if shows == 0 {
+        ok = false;
+        print_out(format_args!("eligible: nothing can be picked\n"))?;
+    } else if shows < number_of_results && !options.strategy.allows_repeats() {
+        ok = false;
+        print_out(format_args!(
+            "eligible: {} results were requested, however only {} shows can be picked\n",
+            number_of_results, shows
+        ))?;
+    }
+
+    if ok {
+        Ok(())
+    } else {
+        Err(EXIT_FAILURE)
+    }
+}
+
+fn verify(matches: &ArgMatches) -> Result<(), i32> {
+    // Required by clap
+    let receipt: Receipt = read_json_file(matches.value_of("receipt").unwrap()).map_err(|e| {
+        error!("couldn't read receipt: {}", e);
+        EXIT_FAILURE
+    })?;
+    if receipt.data_files.is_empty() {
+        error!("the receipt doesn't record any data files, it was written by an older version");
+        return Err(EXIT_FAILURE);
+    }
+
+    let mut verified = true;
+    for data_file in &receipt.data_files {
+        let path = matches
+            .value_of(&data_file.role)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| data_file.path.clone());
+        match data_file.changed(&path) {
+            Ok(None) => print_out(format_args!("{} {}: ok\n", data_file.role, path.display()))?,
+            Ok(Some(sha256)) => {
+                verified = false;
+                print_out(format_args!(
+                    "{} {}: changed (expected sha256 {}, found {})\n",
+                    data_file.role,
+                    path.display(),
+                    data_file.sha256,
+                    sha256
+                ))?;
+            }
+            Err(e) => {
+                verified = false;
+                print_out(format_args!(
+                    "{} {}: couldn't be read ({})\n",
+                    data_file.role,
+                    path.display(),
+                    e
+                ))?;
+            }
+        }
+    }
+
+    if verified {
+        Ok(())
+    } else {
+        Err(EXIT_FAILURE)
+    }
+}
+
+fn explain(receipt: &str, number: usize) -> Result<(), ()> {
+    let receipt: Receipt = read_json_file(receipt).map_err(|e| {
+        error!("couldn't read receipt: {}", e);
+    })?;
+    let explanation = receipt.explain(number).map_err(|e| {
+        error!("{}", e);
+    })?;
+    print_out(format_args!("{}", explanation)).map_err(drop)
+}
+
+/// Outputs every eligible theme as soon as it's picked, so memory use doesn't grow with the
+/// number of themes and a slow reader holds the picking back
+fn output_all(
+    options: &SelectionOptions,
+    list: &[ListEntry],
+    dictionary: &HashMap<ShowId, Show>,
+    columns: &[Column],
     output_mode: &OutputMode,
-    table: &mut Option<Table>,
-) -> Result<(), ()> {
-    let mut prev_res = Vec::with_capacity(number_of_results as _);
-    let mut loop_res = Vec::with_capacity(number_of_results as _);
-    for _ in 0..number_of_results {
-        let a = loop {
-            let res = list.choose(rng);
-
-            // if the list is not empty `res` will be Some
-            if let Some(res) = res {
-                if !prev_res.contains(res) {
-                    prev_res.push(res.clone());
-
-                    if let Some(show) = dictionary.get(res) {
-                        let mut themes = show.opening_themes.clone();
-                        // Avoid clone + appending if we know there's nothing there
-                        smart_append(&mut themes, &show.ending_themes);
-                        smart_append(&mut themes, &show.other_soundtrack);
-
-                        // if the list [of all themes] is not empty `choice` will be Some
-                        if let Some(choice) = themes.choose(rng) {
-                            if let Err(e) = output_theme(choice, show, output_mode, table) {
-                                error!("{}", e);
-                                // We don't have access to hard_fail, so we leave it up to the caller's error handling
-                                break Err(());
-                            };
-                            break Ok(());
-                        } else {
-                            // try again for a different show
-                            continue;
-                        }
-                    }
-                } else if prev_res.len() == list.len() {
-                    // If we've gone through everything we can and still don't have enough, there's nothing we can do
-                    error!("not enough results were found");
-                    break Err(());
-                }
+    matches: &ArgMatches,
+    progress: &mut Progress,
+) -> Result<(), i32> {
+    let hard_fail = matches.is_present("hard-fail");
+    let grouped = matches.is_present("group-by-show");
+    // Counting takes a pass of its own, which is only worth it for a progress bar
+    let total = if progress.is_enabled() {
+        Some(every_theme(options, list, dictionary).count())
+    } else {
+        None
+    };
+    progress.start(total);
+
+    // Only the modes that can be output pick by pick are allowed by clap, none of which need
+    // the count up front
+    let mut stdout = io::stdout();
+    output_header(&mut stdout, output_mode, columns, &Totals::default())
+        .map_err(|e| output_error(&*e))?;
+    let mut totals = Totals::default();
+    // The themes of a show come one after the other, so they're grouped as they are
+    let mut previous: Option<&Show> = None;
+    for (i, pick) in every_theme(options, list, dictionary).enumerate() {
+        totals.add(&pick);
+        let grouping = Grouping::of(grouped, previous, &pick);
+        previous = Some(pick.show);
+        if let Err(e) = output_theme(
+            &mut stdout,
+            i + 1,
+            &pick,
+            columns,
+            output_mode,
+            grouping,
+            &mut None,
+        ) {
+            output_error(&*e);
+            if hard_fail {
+                return Err(EXIT_FAILURE);
             }
-        };
-        loop_res.push(a);
+        }
+        progress.advance(1);
     }
-    match loop_res.contains(&Err(())) {
-        true => Err(()),
-        false => Ok(()),
+    output_footer(&mut stdout, output_mode, &totals, &mut None).map_err(|e| output_error(&*e))?;
+    progress.finish();
+    if matches.is_present("summary") {
+        output_summary(&mut stdout, output_mode, &totals).map_err(|e| output_error(&e))?;
+        if !output_mode.is_for_people() {
+            eprintln!("{}", totals.summary());
+        }
     }
+    Ok(())
+}
+
+/// Outputs the picks along with anything before and after them
+///
+/// When a pick can't be output the others still are, unless `hard_fail` is set.
+fn write_picks(
+    out: &mut dyn Write,
+    matches: &ArgMatches,
+    picks: &[Pick],
+    columns: &[Column],
+    output_mode: &OutputMode,
+    hard_fail: bool,
+) -> Result<(), i32> {
+    let mut table = match output_mode {
+        OutputMode::Table(widths) => {
+            let mut table = create_table(matches);
+            table.add_row(columns.iter().map(|c| match widths.of(c) {
+                Some(width) => truncate(c.header(), width, widths.width_mode),
+                None => c.header().to_owned(),
+            }));
+            Some(table)
+        }
+        _ => None,
+    };
+
+    let totals = Totals::of(picks);
+    output_header(out, output_mode, columns, &totals).map_err(|e| output_error(&*e))?;
+    let mut failed = false;
+    let grouped = matches.is_present("group-by-show");
+    for (i, pick) in picks.iter().enumerate() {
+        let grouping = Grouping::of(grouped, i.checked_sub(1).map(|i| picks[i].show), pick);
+        if let Err(e) = output_theme(out, i + 1, pick, columns, output_mode, grouping, &mut table) {
+            output_error(&*e);
+            failed = true;
+        }
+    }
+    if failed && hard_fail {
+        return Err(EXIT_FAILURE);
+    }
+    output_footer(out, output_mode, &totals, &mut table).map_err(|e| output_error(&*e))?;
+    if matches.is_present("summary") {
+        output_summary(out, output_mode, &totals).map_err(|e| output_error(&e))?;
+    }
+    Ok(())
+}
+
+/// Logs an error writing output and returns the exit code for it
+fn output_error(e: &(dyn Error + 'static)) -> i32 {
+    exit_if_broken_pipe(e);
+    error!("{}", e);
+    EXIT_FAILURE
+}
+
+/// Ends the program quietly with success if stdout was closed early, e.g. by `| head -1`,
+/// since whoever closed it already has all the output they wanted
+fn exit_if_broken_pipe(e: &(dyn Error + 'static)) {
+    if is_broken_pipe(e) {
+        std::process::exit(0);
+    }
+}
+
+/// Writes to stdout, see `exit_if_broken_pipe` for when it's closed early
+fn print_out(text: fmt::Arguments) -> Result<(), i32> {
+    let mut stdout = io::stdout();
+    stdout
+        .write_fmt(text)
+        .and_then(|()| stdout.flush())
+        .map_err(|e| {
+            exit_if_broken_pipe(&e);
+            error!("{}", e);
+            EXIT_FAILURE
+        })
 }
@@ -0,0 +1,56 @@
+//! GitHub-flavored Markdown tables for `--markdown`
+
+/// The header row and the delimiter row below it
+pub fn header<'a>(headers: impl IntoIterator<Item = &'a str>) -> String {
+    let headers: Vec<String> = headers.into_iter().map(escape).collect();
+    let delimiters = vec!["---"; headers.len()];
+    format!("{}\n{}", row(&headers), row(&delimiters))
+}
+
+/// A row of already escaped cells
+pub fn row<S: AsRef<str>>(cells: &[S]) -> String {
+    let cells: Vec<&str> = cells.iter().map(AsRef::as_ref).collect();
+    format!("| {} |", cells.join(" | "))
+}
+
+/// Escapes a value so it stays within its cell and isn't formatted
+///
+/// Links are only kept within their cell, so they're still turned into links.
+pub fn escape(value: &str) -> String {
+    let is_link = value.starts_with("https://") || value.starts_with("http://");
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\n' | '\r' => escaped.push(' '),
+            '|' => escaped.push_str("\\|"),
+            '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' if !is_link => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_tables() {
+        assert_eq!(
+            header(vec!["Song", "Show", "Type"]),
+            "| Song | Show | Type |\n| --- | --- | --- |"
+        );
+        assert_eq!(
+            row(&[escape("A|B"), escape("*Fate/Zero*"), escape("OP")]),
+            "| A\\|B | \\*Fate/Zero\\* | OP |"
+        );
+        assert_eq!(escape("two\nlines"), "two lines");
+        assert_eq!(
+            escape("https://example.com/a_b|c"),
+            "https://example.com/a_b\\|c"
+        );
+    }
+}
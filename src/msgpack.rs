@@ -0,0 +1,131 @@
+//! MessagePack for `--msgpack`, as specified at https://github.com/msgpack/msgpack
+//!
+//! Only what JSON values need is encoded, always in the smallest form that fits.
+
+use serde_json::Value;
+
+/// Appends the encoding of `value` to `out`
+pub fn encode(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.push(0xc0),
+        Value::Bool(false) => out.push(0xc2),
+        Value::Bool(true) => out.push(0xc3),
+        Value::Number(number) => {
+            if let Some(n) = number.as_u64() {
+                encode_unsigned(n, out);
+            } else if let Some(n) = number.as_i64() {
+                encode_signed(n, out);
+            } else {
+                out.push(0xcb);
+                out.extend_from_slice(&number.as_f64().unwrap_or_default().to_be_bytes());
+            }
+        }
+        Value::String(text) => encode_str(text, out),
+        Value::Array(values) => {
+            encode_length(values.len(), 0x90, 0xdc, out);
+            for value in values {
+                encode(value, out);
+            }
+        }
+        Value::Object(map) => {
+            encode_length(map.len(), 0x80, 0xde, out);
+            for (key, value) in map {
+                encode_str(key, out);
+                encode(value, out);
+            }
+        }
+    }
+}
+
+fn encode_unsigned(n: u64, out: &mut Vec<u8>) {
+    if n < 0x80 {
+        out.push(n as u8);
+    } else if n <= u64::from(u8::MAX) {
+        out.extend_from_slice(&[0xcc, n as u8]);
+    } else if n <= u64::from(u16::MAX) {
+        out.push(0xcd);
+        out.extend_from_slice(&(n as u16).to_be_bytes());
+    } else if n <= u64::from(u32::MAX) {
+        out.push(0xce);
+        out.extend_from_slice(&(n as u32).to_be_bytes());
+    } else {
+        out.push(0xcf);
+        out.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+/// Encodes a negative number, non-negative ones are always unsigned
+fn encode_signed(n: i64, out: &mut Vec<u8>) {
+    if n >= -32 {
+        out.push(n as u8);
+    } else if n >= i64::from(i8::MIN) {
+        out.extend_from_slice(&[0xd0, n as u8]);
+    } else if n >= i64::from(i16::MIN) {
+        out.push(0xd1);
+        out.extend_from_slice(&(n as i16).to_be_bytes());
+    } else if n >= i64::from(i32::MIN) {
+        out.push(0xd2);
+        out.extend_from_slice(&(n as i32).to_be_bytes());
+    } else {
+        out.push(0xd3);
+        out.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+fn encode_str(text: &str, out: &mut Vec<u8>) {
+    let len = text.len();
+    if len < 32 {
+        out.push(0xa0 | len as u8);
+    } else if len <= usize::from(u8::MAX) {
+        out.extend_from_slice(&[0xd9, len as u8]);
+    } else if len <= usize::from(u16::MAX) {
+        out.push(0xda);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(0xdb);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+    out.extend_from_slice(text.as_bytes());
+}
+
+/// Encodes the length of an array or map, `fix` being the type of up to 15 elements and `wide`
+/// the type with a 16-bit length, which is followed by the one with a 32-bit length
+fn encode_length(len: usize, fix: u8, wide: u8, out: &mut Vec<u8>) {
+    if len < 16 {
+        out.push(fix | len as u8);
+    } else if len <= usize::from(u16::MAX) {
+        out.push(wide);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(wide + 1);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn encoded(value: Value) -> Vec<u8> {
+        let mut out = vec![];
+        encode(&value, &mut out);
+        out
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(
+            encoded(json!({"id": 1, "url": null})),
+            b"\x82\xa2id\x01\xa3url\xc0"
+        );
+        assert_eq!(encoded(json!(200)), [0xcc, 200]);
+        assert_eq!(encoded(json!(65536)), [0xce, 0, 1, 0, 0]);
+        assert_eq!(encoded(json!(-1)), [0xff]);
+        assert_eq!(encoded(json!(-200)), [0xd1, 0xff, 0x38]);
+        assert_eq!(encoded(json!(0.5)), [0xcb, 0x3f, 0xe0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(encoded(json!([true, false])), [0x92, 0xc3, 0xc2]);
+        assert_eq!(&encoded(json!("a".repeat(40)))[..2], [0xd9, 40]);
+        assert_eq!(&encoded(json!(vec![0; 16]))[..3], [0xdc, 0, 16]);
+    }
+}
@@ -0,0 +1,108 @@
+//! Normalizing titles and names before they are compared, so every feature that matches or
+//! orders them agrees on when two are the same
+//!
+//! Case folding is Unicode's default, which is the same in every locale except for the dotted
+//! and dotless i of Turkish and Azeri, where `I` still folds to `i`.
+
+/// Which differences between two strings don't count
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Normalization {
+    /// `Tank!` and `TANK!`, also `ß` and `ss`
+    pub fold_case: bool,
+    /// `ＯＰ` and `OP`, fullwidth ASCII and the ideographic space
+    pub fold_width: bool,
+    /// `S.I.D.` and `SID`, anything that isn't a letter, digit or space
+    pub strip_punctuation: bool,
+}
+
+impl Normalization {
+    /// How artists and franchises are matched, by `--unique-artist` and `--no-adjacent`
+    pub const MATCHING: Self = Normalization {
+        fold_case: true,
+        fold_width: true,
+        strip_punctuation: true,
+    };
+    /// How titles are ordered by `--sort`
+    pub const SORTING: Self = Normalization {
+        fold_case: true,
+        fold_width: true,
+        strip_punctuation: false,
+    };
+
+    /// The normalized string, with runs of whitespace made a single space and none around it
+    ///
+    /// A string of nothing but punctuation keeps it, so it doesn't become the same as every
+    /// other one.
+    pub fn apply(self, s: &str) -> String {
+        let mut chars: Vec<char> = s.chars().collect();
+        if self.fold_width {
+            chars = chars.into_iter().map(fold_width).collect();
+        }
+        if self.fold_case {
+            chars = chars.into_iter().flat_map(fold_case).collect();
+        }
+        if self.strip_punctuation && chars.iter().any(|c| c.is_alphanumeric()) {
+            chars.retain(|c| c.is_alphanumeric() || c.is_whitespace());
+        }
+        let s: String = chars.into_iter().collect();
+        s.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// The halfwidth form of fullwidth ASCII and of the ideographic space
+fn fold_width(c: char) -> char {
+    match c {
+        '\u{3000}' => ' ',
+        '\u{FF01}'..='\u{FF5E}' => std::char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// Lowercases, and folds the few letters whose lowercase still differs between spellings
+fn fold_case(c: char) -> Vec<char> {
+    match c {
+        'ß' | 'ẞ' => vec!['s', 's'],
+        'ς' => vec!['σ'],
+        _ => c.to_lowercase().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizing() {
+        let matching = |s| Normalization::MATCHING.apply(s);
+        let sorting = |s| Normalization::SORTING.apply(s);
+
+        assert_eq!(matching("  Maximum  the\tHormone "), "maximum the hormone");
+        assert_eq!(matching("S.I.D."), matching("SID"));
+        assert_eq!(matching("Ｌｉｎｋｅｄ　Ｈｏｒｉｚｏｎ"), "linked horizon");
+        assert_eq!(matching("Straße"), matching("STRASSE"));
+        assert_eq!(matching("ΣΟΦΟΣ"), matching("σοφος"));
+        assert_eq!(matching("Ⅻ"), "ⅻ");
+        // The combining dot above its lowercase isn't a letter
+        assert_eq!(matching("İ"), "i");
+        assert_eq!(sorting("İ"), "i\u{307}");
+        // Letters of every script count, not only ASCII
+        assert_eq!(
+            matching("Ling tosite sigure (凛として時雨)"),
+            "ling tosite sigure 凛として時雨"
+        );
+        assert_eq!(matching("ÉLÉGANT・ヨルシカ"), "élégantヨルシカ");
+        assert_ne!(matching("!!!"), matching("???"));
+
+        assert_eq!(sorting("ＴＡＮＫ!"), "tank!");
+        assert_eq!(sorting("S.I.D."), "s.i.d.");
+        assert_eq!(
+            Normalization {
+                fold_case: false,
+                fold_width: false,
+                strip_punctuation: false,
+            }
+            .apply(" Ｔank! "),
+            "Ｔank!"
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Notes for the host, like trivia for a quiz, shown along with picks with `--notes`
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::iter::FromIterator;
+use std::path::Path;
+
+use crate::selection::Pick;
+use crate::sha256::hex_digest;
+use crate::{read_json_file, ShowId};
+
+/// The number of hex digits of a pick hash
+const HASH_LEN: usize = 12;
+
+/// Notes keyed by show id, or by pick hash for a single theme, e.g.
+/// `{"1": "Directed by Shinichiro Watanabe", "3f9a2c41b7e0": "Sung in English"}`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Notes {
+    by_pick: HashMap<String, String>,
+    by_show: HashMap<ShowId, String>,
+}
+
+impl Notes {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let notes: BTreeMap<String, String> = read_json_file(path)?;
+        Ok(notes.into_iter().collect())
+    }
+
+    /// The note of the theme, or else of the show
+    pub fn get(&self, pick: &Pick) -> Option<&str> {
+        self.by_pick
+            .get(&pick_hash(pick))
+            .or_else(|| self.by_show.get(&pick.show.id))
+            .map(String::as_str)
+    }
+}
+
+impl FromIterator<(String, String)> for Notes {
+    /// Any key could be a show id, since ids can be any string
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        let mut notes = Notes::default();
+        for (key, note) in iter {
+            notes
+                .by_show
+                .insert(ShowId::from(key.as_str()), note.clone());
+            notes.by_pick.insert(key, note);
+        }
+        notes
+    }
+}
+
+/// A short hash of the show id and the song, which stays the same across lists and runs
+pub fn pick_hash(pick: &Pick) -> String {
+    let mut hash = hex_digest(format!("{}\n{}", pick.show.id, pick.song).as_bytes());
+    hash.truncate(HASH_LEN);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::fixtures;
+    use crate::Show;
+
+    #[test]
+    fn notes() {
+        let show = Show {
+            id: 1usize.into(),
+            opening_themes: vec!["Tank!".to_owned(), "Tank! (TV size)".to_owned()],
+            ..Default::default()
+        };
+        let pick = Pick {
+            theme_count: 2,
+            ..fixtures::opening(&show)
+        };
+        let other = Pick {
+            song: &show.opening_themes[1],
+            ..pick
+        };
+        let hash = pick_hash(&pick);
+        assert_eq!(hash.len(), HASH_LEN);
+        assert_ne!(hash, pick_hash(&other));
+
+        let notes: Notes = vec![
+            ("01".to_owned(), "Show note".to_owned()),
+            (hash, "Theme note".to_owned()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(notes.get(&pick), Some("Theme note"));
+        assert_eq!(notes.get(&other), Some("Show note"));
+
+        let unknown = Show::default();
+        assert_eq!(
+            notes.get(&Pick {
+                show: &unknown,
+                ..pick
+            }),
+            None
+        );
+    }
+}
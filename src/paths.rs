@@ -0,0 +1,79 @@
+//! Where state is kept on disk, following the conventions of each platform
+//!
+//! | Platform | Config                                  | Data (sessions)                       |
+//! |----------|-----------------------------------------|---------------------------------------|
+//! | Linux    | `$XDG_CONFIG_HOME` or `~/.config`       | `$XDG_DATA_HOME` or `~/.local/share`  |
+//! | macOS    | `~/Library/Application Support`         | `~/Library/Application Support`       |
+//! | Windows  | `%APPDATA%\random-show-themes\config`   | `%APPDATA%\random-show-themes\data`   |
+//!
+//! On Linux and macOS the directory is `random-show-themes` within the one in the table.
+
+use std::path::{Path, PathBuf};
+
+const APP: &str = "random-show-themes";
+
+fn home() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// An XDG base directory, or its default within the home directory
+fn xdg(var: &str, default: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        // Relative paths are invalid according to the spec and are ignored
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home().map(|home| home.join(default)))
+}
+
+fn app_dir(linux: (&str, &str), windows: &str) -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("APPDATA").map(|dir| Path::new(&dir).join(APP).join(windows))
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library/Application Support").join(APP))
+    } else {
+        xdg(linux.0, linux.1).map(|dir| dir.join(APP))
+    }
+}
+
+/// The directory of the config file
+pub fn config_dir() -> Option<PathBuf> {
+    app_dir(("XDG_CONFIG_HOME", ".config"), "config")
+}
+
+/// The directory of state kept between runs, like sessions
+pub fn data_dir() -> Option<PathBuf> {
+    app_dir(("XDG_DATA_HOME", ".local/share"), "data")
+}
+
+/// The config file read unless `RANDOM_SHOW_THEMES_CONFIG` names another one
+pub fn config_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.json"))
+}
+
+/// The session of `--pick-strategy exhaust-then-reshuffle`
+pub fn session_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(crate::session::FILE_NAME))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xdg_directories() {
+        std::env::set_var("XDG_DATA_HOME", "/xdg/data");
+        assert_eq!(
+            session_file(),
+            Some(PathBuf::from("/xdg/data/random-show-themes/session.json"))
+        );
+        // Relative directories fall back to the default
+        std::env::set_var("XDG_DATA_HOME", "relative");
+        assert_eq!(
+            data_dir(),
+            home().map(|home| home.join(".local/share/random-show-themes"))
+        );
+        std::env::remove_var("XDG_DATA_HOME");
+    }
+}
@@ -0,0 +1,112 @@
+//! Machine-readable progress of long operations, set with `--progress json`
+//!
+//! Every event is a JSON object on a line of its own on stderr:
+//!
+//! ```text
+//! {"event":"start","task":"export","total":310}
+//! {"done":120,"event":"progress","task":"export","total":310}
+//! {"done":310,"event":"finish","task":"export","total":310}
+//! ```
+//!
+//! `total` is left out when it isn't known in advance. Progress events are sent at most every
+//! 100 ms, start and finish always.
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use clap::ArgMatches;
+use serde_json::json;
+
+/// The least time between two progress events
+const INTERVAL: Duration = Duration::from_millis(100);
+
+/// Reports the progress of a task, or nothing when progress wasn't asked for
+#[derive(Debug, Default)]
+pub struct Progress {
+    enabled: bool,
+    task: &'static str,
+    total: Option<usize>,
+    done: usize,
+    last_event: Option<Instant>,
+}
+
+impl Progress {
+    /// A report for `task`, enabled by `--progress`
+    pub fn from_matches(matches: &ArgMatches, task: &'static str) -> Self {
+        Progress {
+            enabled: matches.value_of("progress") == Some("json"),
+            task,
+            ..Default::default()
+        }
+    }
+
+    /// Whether events are sent, so callers can skip working out a total otherwise
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn start(&mut self, total: Option<usize>) {
+        self.total = total;
+        self.done = 0;
+        self.last_event = Some(Instant::now());
+        self.send("start", false);
+    }
+
+    /// Marks `n` more steps as done
+    pub fn advance(&mut self, n: usize) {
+        self.done += n;
+        let due = self
+            .last_event
+            .is_none_or(|last| last.elapsed() >= INTERVAL);
+        if self.enabled && due {
+            self.last_event = Some(Instant::now());
+            self.send("progress", true);
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.send("finish", true);
+    }
+
+    fn send(&self, event: &str, with_done: bool) {
+        if self.enabled {
+            // Progress is only informational, a closed stderr shouldn't stop the task
+            let _ = writeln!(io::stderr().lock(), "{}", self.event(event, with_done));
+        }
+    }
+
+    fn event(&self, event: &str, with_done: bool) -> serde_json::Value {
+        let mut object = json!({ "event": event, "task": self.task });
+        if with_done {
+            object["done"] = self.done.into();
+        }
+        if let Some(total) = self.total {
+            object["total"] = total.into();
+        }
+        object
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_events() {
+        let mut progress = Progress {
+            task: "export",
+            ..Default::default()
+        };
+        progress.start(Some(3));
+        assert_eq!(
+            progress.event("start", false).to_string(),
+            r#"{"event":"start","task":"export","total":3}"#
+        );
+        progress.advance(2);
+        progress.total = None;
+        assert_eq!(
+            progress.event("progress", true).to_string(),
+            r#"{"done":2,"event":"progress","task":"export"}"#
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use std::cmp::Reverse;
+use std::fmt;
+use std::str::FromStr;
+
+use log::warn;
+
+use crate::Show;
+
+/// The show attribute a quota counts
+#[derive(Debug, PartialEq)]
+pub enum QuotaField {
+    Genre,
+    Tag,
+}
+
+/// A soft constraint on how many picks share a genre or tag, e.g. `genre:Music=2` or `tag:favorite>=1`
+#[derive(Debug, PartialEq)]
+pub struct Quota {
+    pub field: QuotaField,
+    pub value: String,
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Quota {
+    /// Checks if the show has the genre or tag this quota counts (case insensitive)
+    pub fn matches(&self, show: &Show) -> bool {
+        let values = match self.field {
+            QuotaField::Genre => &show.genres,
+            QuotaField::Tag => &show.tags,
+        };
+        values.iter().any(|v| v.eq_ignore_ascii_case(&self.value))
+    }
+}
+
+impl FromStr for Quota {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error_msg = || {
+            format!(
+                "invalid quota '{}', expected e.g. 'genre:Music=2' or 'tag:favorite>=1'",
+                s
+            )
+        };
+
+        let (field, rest) = s.split_once(':').ok_or_else(error_msg)?;
+        let field = match field {
+            "genre" => QuotaField::Genre,
+            "tag" => QuotaField::Tag,
+            _ => return Err(error_msg()),
+        };
+
+        let (value, count, op) = if let Some((value, count)) = rest.split_once(">=") {
+            (value, count, ">=")
+        } else if let Some((value, count)) = rest.split_once("<=") {
+            (value, count, "<=")
+        } else if let Some((value, count)) = rest.split_once('=') {
+            (value, count, "=")
+        } else {
+            return Err(error_msg());
+        };
+        if value.is_empty() {
+            return Err(error_msg());
+        }
+        let count: usize = count.trim().parse().map_err(|_| error_msg())?;
+
+        let (min, max) = match op {
+            ">=" => (count, None),
+            "<=" => (0, Some(count)),
+            _ => (count, Some(count)),
+        };
+
+        Ok(Quota {
+            field,
+            value: value.to_owned(),
+            min,
+            max,
+        })
+    }
+}
+
+impl fmt::Display for Quota {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let field = match self.field {
+            QuotaField::Genre => "genre",
+            QuotaField::Tag => "tag",
+        };
+        match (self.min, self.max) {
+            (min, Some(max)) if min == max => write!(f, "{}:{}={}", field, self.value, min),
+            (_, Some(max)) => write!(f, "{}:{}<={}", field, self.value, max),
+            (min, None) => write!(f, "{}:{}>={}", field, self.value, min),
+        }
+    }
+}
+
+/// Validates a quota argument for clap
+pub fn quota_validate(value: String) -> Result<(), String> {
+    value.parse::<Quota>().map(|_| ())
+}
+
+/// Chooses up to `number_of_results` candidates while trying to satisfy every quota
+///
+/// Candidates are considered in the order given. Minimums are filled first, preferring
+/// candidates with a higher priority, then the remaining slots are filled with any candidate that
+/// does not push a quota over its maximum.
+/// Unmet minimums are reported as warnings. Returns the chosen indices in the order they were drawn.
+pub fn fill_quotas(quotas: &[Quota], candidates: &[&Show], number_of_results: usize) -> Vec<usize> {
+    let mut chosen = Vec::with_capacity(number_of_results);
+    let mut counts = vec![0; quotas.len()];
+
+    let fits = |show: &Show, counts: &[usize]| {
+        quotas
+            .iter()
+            .zip(counts)
+            .all(|(q, &count)| !q.matches(show) || q.max.is_none_or(|max| count < max))
+    };
+
+    let take = |i: usize, counts: &mut [usize], chosen: &mut Vec<usize>| {
+        chosen.push(i);
+        for (q, count) in quotas.iter().zip(counts.iter_mut()) {
+            if q.matches(candidates[i]) {
+                *count += 1;
+            }
+        }
+    };
+
+    // Meet the minimums first, with the shows of the highest priority
+    let mut by_priority: Vec<usize> = (0..candidates.len()).collect();
+    by_priority.sort_by_key(|&i| Reverse(candidates[i].priority));
+    for (qi, quota) in quotas.iter().enumerate() {
+        for &i in &by_priority {
+            let show = candidates[i];
+            if chosen.len() == number_of_results || counts[qi] >= quota.min {
+                break;
+            }
+            if !chosen.contains(&i) && quota.matches(show) && fits(show, &counts) {
+                take(i, &mut counts, &mut chosen);
+            }
+        }
+    }
+
+    // Then fill up the rest
+    for (i, show) in candidates.iter().enumerate() {
+        if chosen.len() == number_of_results {
+            break;
+        }
+        if !chosen.contains(&i) && fits(show, &counts) {
+            take(i, &mut counts, &mut chosen);
+        }
+    }
+
+    for (quota, count) in quotas.iter().zip(&counts) {
+        if *count < quota.min {
+            warn!(
+                "quota {} was not met, only {} matching results were found",
+                quota, count
+            );
+        }
+    }
+
+    chosen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn show(id: usize, genres: &[&str], tags: &[&str]) -> Show {
+        Show {
+            id: id.into(),
+            title: format!("Show {}", id),
+            opening_themes: vec!["OP".to_owned()],
+            genres: genres.iter().map(|s| s.to_string()).collect(),
+            tags: tags.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn quota_parsing() {
+        assert_eq!(
+            "genre:Music=2".parse(),
+            Ok(Quota {
+                field: QuotaField::Genre,
+                value: "Music".to_owned(),
+                min: 2,
+                max: Some(2)
+            })
+        );
+        assert_eq!(
+            "tag:favorite>=1".parse(),
+            Ok(Quota {
+                field: QuotaField::Tag,
+                value: "favorite".to_owned(),
+                min: 1,
+                max: None
+            })
+        );
+        let quota: Quota = "tag:skip<=1".parse().unwrap();
+        assert_eq!((quota.min, quota.max), (0, Some(1)));
+        assert_eq!(quota.to_string(), "tag:skip<=1");
+        assert!("studio:Bones=1".parse::<Quota>().is_err());
+        assert!("genre:Music".parse::<Quota>().is_err());
+        assert!("genre:=1".parse::<Quota>().is_err());
+        assert!("genre:Music=-1".parse::<Quota>().is_err());
+    }
+
+    #[test]
+    fn quotas_are_filled_first() {
+        let shows = [
+            show(1, &["Action"], &[]),
+            show(2, &["Action"], &[]),
+            show(3, &["Music"], &[]),
+            show(4, &["Music"], &["favorite"]),
+            show(5, &["Music"], &[]),
+        ];
+        let candidates: Vec<&Show> = shows.iter().collect();
+        let quotas = vec!["genre:music=2".parse().unwrap()];
+        assert_eq!(fill_quotas(&quotas, &candidates, 3), vec![2, 3, 0]);
+
+        let quotas = vec![
+            "genre:Music<=1".parse().unwrap(),
+            "tag:favorite>=1".parse().unwrap(),
+        ];
+        assert_eq!(fill_quotas(&quotas, &candidates, 4), vec![3, 0, 1]);
+
+        // Priority decides which shows meet a minimum
+        let mut shows = shows;
+        shows[4].priority = 2;
+        shows[0].priority = 5;
+        let candidates: Vec<&Show> = shows.iter().collect();
+        let quotas = vec!["genre:music=2".parse().unwrap()];
+        assert_eq!(fill_quotas(&quotas, &candidates, 3), vec![4, 2, 0]);
+    }
+}
@@ -0,0 +1,279 @@
+use std::error::Error;
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::selection::{Pick, SelectionOptions, SelectionStrategy};
+use crate::sha256::hex_digest;
+use crate::ShowId;
+
+/// A record of how a selection was made, written with `--receipt`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Receipt {
+    /// The version of random-show-themes that made the selection
+    pub version: String,
+    /// When the selection was made, as an RFC 3339 timestamp, missing from receipts written
+    /// before it was recorded
+    #[serde(default)]
+    pub created: Option<String>,
+    pub requested: usize,
+    pub strategy: String,
+    pub filters: Vec<String>,
+    pub quotas: Vec<String>,
+    pub no_adjacent: Option<String>,
+    /// Missing from receipts written before artists could be made unique
+    #[serde(default)]
+    pub unique_artist: bool,
+    /// Missing from receipts written before themes could be ordered
+    #[serde(default)]
+    pub order_themes: Option<String>,
+    /// Missing from receipts written before themes could be weighted by type
+    #[serde(default)]
+    pub type_weights: Option<String>,
+    /// Missing from receipts written before types could be interleaved
+    #[serde(default)]
+    pub interleave_types: Option<String>,
+    /// How often the random number generator was drawn from for the whole selection, missing
+    /// from receipts written before draws were counted
+    #[serde(default)]
+    pub rng_draws: Option<u64>,
+    /// The files the selection was made from, missing from receipts written before they were recorded
+    #[serde(default)]
+    pub data_files: Vec<DataFile>,
+    pub picks: Vec<ReceiptPick>,
+}
+
+/// A fingerprint of a file the selection was made from, to check it hasn't changed since
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct DataFile {
+    /// What the file was used as, "dictionary" or "list"
+    pub role: String,
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+impl DataFile {
+    pub fn new<P: AsRef<Path>>(role: &str, path: P) -> io::Result<Self> {
+        Ok(DataFile {
+            role: role.to_owned(),
+            path: path.as_ref().to_owned(),
+            sha256: hex_digest(&std::fs::read(&path)?),
+        })
+    }
+
+    /// The SHA-256 of the file at `path` if it differs from the recorded one
+    pub fn changed<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<String>> {
+        let sha256 = hex_digest(&std::fs::read(path)?);
+        Ok(Some(sha256).filter(|sha256| *sha256 != self.sha256))
+    }
+}
+
+/// A single pick of a receipt, in output order
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReceiptPick {
+    pub id: ShowId,
+    pub title: String,
+    pub song: String,
+    pub song_type: String,
+    pub draw: usize,
+    pub pool_size: usize,
+    pub theme_count: usize,
+    /// The quotas this pick counted towards
+    pub quotas: Vec<String>,
+}
+
+impl Receipt {
+    pub fn new(options: &SelectionOptions, requested: usize, picks: &[Pick]) -> Self {
+        Receipt {
+            version: clap::crate_version!().to_owned(),
+            created: None,
+            requested,
+            strategy: options.strategy.name().to_owned(),
+            filters: options.filter_names(),
+            quotas: options.quotas.iter().map(|q| q.to_string()).collect(),
+            no_adjacent: options.no_adjacent.as_ref().map(|a| a.name().to_owned()),
+            unique_artist: options.unique_artist,
+            order_themes: options.theme_order.as_ref().map(|o| o.name().to_owned()),
+            type_weights: options.type_weights.as_ref().map(|w| w.to_string()),
+            interleave_types: Some(&options.type_pattern)
+                .filter(|pattern| !pattern.is_empty())
+                .map(|pattern| pattern.join(",").to_lowercase()),
+            rng_draws: None,
+            data_files: vec![],
+            picks: picks
+                .iter()
+                .map(|pick| ReceiptPick {
+                    id: pick.show.id.clone(),
+                    title: pick.show.title.clone(),
+                    song: pick.song.clone(),
+                    song_type: pick.show.song_type(pick.song).to_owned(),
+                    draw: pick.draw,
+                    pool_size: pick.pool_size,
+                    theme_count: pick.theme_count,
+                    quotas: options
+                        .quotas
+                        .iter()
+                        .filter(|q| q.matches(pick.show))
+                        .map(|q| q.to_string())
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Describes how the pick at the 1-based position `number` came to be
+    pub fn explain(&self, number: usize) -> Result<String, String> {
+        let pick = number
+            .checked_sub(1)
+            .and_then(|i| self.picks.get(i))
+            .ok_or_else(|| {
+                format!(
+                    "there is no pick {}, the receipt contains {} picks",
+                    number,
+                    self.picks.len()
+                )
+            })?;
+
+        let mut out = String::new();
+        self.describe(number, pick, &mut out)
+            .expect("writing to a String can't fail");
+        Ok(out)
+    }
+
+    fn describe(&self, number: usize, pick: &ReceiptPick, out: &mut String) -> fmt::Result {
+        let random = self.strategy != SelectionStrategy::TakeFirst.name();
+
+        writeln!(
+            out,
+            "Pick {}: {} [{}] from {} (id {})",
+            number, pick.song, pick.song_type, pick.title, pick.id
+        )?;
+        if let Some(created) = &self.created {
+            writeln!(out, "  made at: {}", created)?;
+        }
+        writeln!(out, "  strategy: {}", self.strategy)?;
+        if let Some(draws) = self.rng_draws {
+            writeln!(out, "  random draws: {} for the whole selection", draws)?;
+        }
+        // Not the odds of the draw, which quotas, weights of list duplicates and left out
+        // themes change
+        writeln!(
+            out,
+            "  show: draw {} of {}, remaining candidates: {}",
+            pick.draw + 1,
+            self.picks.len(),
+            pick.pool_size
+        )?;
+        write!(out, "  theme: chosen from {} themes", pick.theme_count)?;
+        match &self.order_themes {
+            Some(order) => write!(out, " ({} by numbering)", order)?,
+            None if random => match &self.type_weights {
+                Some(weights) => write!(out, " (weighted by type: {})", weights)?,
+                None => write!(out, " (1 in {})", pick.theme_count)?,
+            },
+            None => {}
+        }
+        writeln!(out)?;
+        if let Some(pattern) = &self.interleave_types {
+            writeln!(
+                out,
+                "  type: {} in the pattern {}",
+                pick.song_type.to_lowercase(),
+                pattern
+            )?;
+        }
+        writeln!(out, "  filters passed: {}", list_or_none(&self.filters))?;
+        writeln!(
+            out,
+            "  quotas counted towards: {}",
+            list_or_none(&pick.quotas)
+        )?;
+        if self.unique_artist {
+            writeln!(out, "  kept since no earlier pick is by the same artist")?;
+        }
+        if let Some(no_adjacent) = &self.no_adjacent {
+            writeln!(
+                out,
+                "  reordered so no neighbours share the same {}",
+                no_adjacent
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn list_or_none(values: &[String]) -> String {
+    if values.is_empty() {
+        "none".to_owned()
+    } else {
+        values.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::fixtures;
+    use crate::Show;
+
+    #[test]
+    fn explaining() {
+        let show = Show {
+            id: 7usize.into(),
+            title: "Show".to_owned(),
+            genres: vec!["Music".to_owned()],
+            ..Default::default()
+        };
+        let song = "\"Song\" by Artist".to_owned();
+        let options = SelectionOptions {
+            quotas: vec!["genre:Music>=1".parse().unwrap()],
+            family_friendly: true,
+            ..Default::default()
+        };
+        let picks = [Pick {
+            pool_size: 12,
+            theme_count: 3,
+            ..fixtures::pick(&song, &show)
+        }];
+        let mut receipt = Receipt::new(&options, 2, &picks);
+        receipt.created = Some("2020-02-14T20:30:05Z".to_owned());
+        receipt.rng_draws = Some(5);
+
+        let explanation = receipt.explain(1).unwrap();
+        assert!(explanation.starts_with("Pick 1: \"Song\" by Artist [ST] from Show (id 7)\n"));
+        assert!(explanation.contains("made at: 2020-02-14T20:30:05Z\n"));
+        assert!(explanation.contains("random draws: 5 for the whole selection\n"));
+        assert!(explanation.contains("show: draw 1 of 1, remaining candidates: 12\n"));
+        assert!(explanation.contains("chosen from 3 themes (1 in 3)"));
+        assert!(explanation.contains("filters passed: family-friendly\n"));
+        assert!(explanation.contains("quotas counted towards: genre:Music>=1\n"));
+        assert!(receipt.explain(0).is_err());
+        assert!(receipt.explain(2).is_err());
+    }
+
+    #[test]
+    fn data_files() {
+        let path = std::env::temp_dir().join("random-show-themes-data-file.json");
+        std::fs::write(&path, "[1, 2, 3]").unwrap();
+        let data_file = DataFile::new("list", &path).unwrap();
+        assert_eq!(
+            data_file.sha256,
+            "a36b1f2c3f84522dd1005145646617d7054c0851e97c72a039c0bdfac9fa07f3"
+        );
+        assert_eq!(data_file.changed(&path).unwrap(), None);
+
+        std::fs::write(&path, "[1, 2]").unwrap();
+        assert!(data_file.changed(&path).unwrap().is_some());
+        std::fs::remove_file(&path).unwrap();
+        assert!(data_file.changed(&path).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+//! Counting the random draws of a selection, so receipts can record them
+
+use rand::RngCore;
+
+/// Wraps a random number generator and counts how often it is drawn from, a draw being any
+/// request for random bits, however many
+#[derive(Debug)]
+pub struct CountingRng<R> {
+    rng: R,
+    draws: u64,
+}
+
+impl<R: RngCore> CountingRng<R> {
+    pub fn new(rng: R) -> Self {
+        CountingRng { rng, draws: 0 }
+    }
+
+    /// How often the generator was drawn from so far
+    pub fn draws(&self) -> u64 {
+        self.draws
+    }
+}
+
+impl<R: RngCore> RngCore for CountingRng<R> {
+    fn next_u32(&mut self) -> u32 {
+        self.draws += 1;
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.draws += 1;
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.draws += 1;
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.draws += 1;
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn counting() {
+        let mut rng = CountingRng::new(StdRng::seed_from_u64(275));
+        assert_eq!(rng.draws(), 0);
+        let first: u64 = rng.gen();
+        rng.fill_bytes(&mut [0; 16]);
+        assert_eq!(rng.draws(), 2);
+
+        // The numbers are those of the wrapped generator
+        assert_eq!(first, StdRng::seed_from_u64(275).gen::<u64>());
+    }
+}
@@ -0,0 +1,97 @@
+//! RSS 2.0 feeds for `--rss`, e.g. for a theme of the day picked by cron
+//!
+//! Every run writes a new feed with only its own picks. Their guids are unique to the run, so
+//! feed readers show them as new items and keep the earlier ones.
+
+use std::error::Error;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use crate::columns::Column;
+use crate::selection::Pick;
+use crate::timezone::Timezone;
+use crate::xml::escape;
+
+/// Writes a feed of the picks to `path`, replacing any file there
+pub fn write(
+    path: &Path,
+    song: &Column,
+    picks: &[Pick],
+    timezone: Timezone,
+) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, feed(song, picks, Utc::now(), timezone))
+        .map_err(|e| format!("couldn't write {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+/// The feed of picks made at `time`, titled with the `song` column
+fn feed(song: &Column, picks: &[Pick], time: DateTime<Utc>, timezone: Timezone) -> String {
+    let date = timezone.format_rfc2822(time);
+    let mut xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+<channel>
+<title>Random show themes</title>
+<link>{}</link>
+<description>Themes picked by random-show-themes</description>
+<lastBuildDate>{}</lastBuildDate>
+"#,
+        env!("CARGO_PKG_REPOSITORY"),
+        date
+    );
+    for (i, pick) in picks.iter().enumerate() {
+        xml.push_str("<item>\n");
+        xml.push_str(&format!(
+            "<title>{}</title>\n",
+            escape(&song.value(i + 1, pick))
+        ));
+        xml.push_str(&format!(
+            "<description>{} [{}]</description>\n",
+            escape(&pick.show.title),
+            pick.show.song_type(pick.song)
+        ));
+        if let Some(url) = &pick.show.url {
+            xml.push_str(&format!("<link>{}</link>\n", escape(url)));
+        }
+        xml.push_str(&format!(
+            "<guid isPermaLink=\"false\">{}-{}</guid>\n",
+            time.timestamp(),
+            i + 1
+        ));
+        xml.push_str(&format!("<pubDate>{}</pubDate>\n", date));
+        xml.push_str("</item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::fixtures;
+    use crate::theme::SongLang;
+    use crate::Show;
+    use chrono::TimeZone;
+
+    #[test]
+    fn feeds() {
+        let show = Show {
+            url: Some("https://example.com/?a=1&b=2".to_owned()),
+            ..fixtures::bebop()
+        };
+        let pick = fixtures::opening(&show);
+        let time = Utc.ymd(2020, 2, 14).and_hms(20, 30, 5);
+        let feed = feed(&Column::Song(SongLang::Both), &[pick], time, Timezone::Utc);
+        assert!(feed.contains(
+            "<item>
+<title>&quot;Tank!&quot; by The Seatbelts</title>
+<description>Cowboy Bebop [OP]</description>
+<link>https://example.com/?a=1&amp;b=2</link>
+<guid isPermaLink=\"false\">1581712205-1</guid>
+<pubDate>Fri, 14 Feb 2020 20:30:05 +0000</pubDate>
+</item>"
+        ));
+        assert!(feed.ends_with("</channel>\n</rss>\n"));
+    }
+}
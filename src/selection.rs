@@ -0,0 +1,1004 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use clap::ArgMatches;
+use log::warn;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::list::{ListEntry, WatchStatus};
+use crate::normalize::Normalization;
+use crate::quota::{fill_quotas, Quota};
+use crate::spacing::{spread_out, Adjacency};
+use crate::theme::Theme;
+use crate::weights::{parse_song_type, TypeWeights};
+use crate::{Show, ShowId};
+
+/// How shows and themes are chosen from the list
+#[derive(Default)]
+pub enum SelectionStrategy {
+    /// Pick shows at random without repeats, then a random theme from each
+    #[default]
+    Random,
+    /// Take the first eligible shows in list order, and the first theme of each
+    TakeFirst,
+    /// Pick shows and themes at random, the same show and theme may be picked more than once
+    WithReplacement,
+    /// Pick at random like `Random`, but never a theme that was already played since every
+    /// eligible theme was last played, which is kept track of in the session
+    ExhaustThenReshuffle,
+}
+
+impl SelectionStrategy {
+    pub const NAMES: [&'static str; 4] = [
+        "random",
+        "take-first",
+        "with-replacement",
+        "exhaust-then-reshuffle",
+    ];
+
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        if let Some(name) = matches.value_of("pick-strategy") {
+            // Validated by clap
+            name.parse().unwrap()
+        } else if matches.is_present("take-first") {
+            Self::TakeFirst
+        } else if matches.is_present("sample-with-replacement") {
+            Self::WithReplacement
+        } else {
+            Self::Random
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Random => "random",
+            Self::TakeFirst => "take-first",
+            Self::WithReplacement => "with-replacement",
+            Self::ExhaustThenReshuffle => "exhaust-then-reshuffle",
+        }
+    }
+
+    /// Checks if the same show can be picked more than once
+    pub fn allows_repeats(&self) -> bool {
+        matches!(self, Self::WithReplacement)
+    }
+}
+
+impl FromStr for SelectionStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(Self::Random),
+            "take-first" => Ok(Self::TakeFirst),
+            "with-replacement" => Ok(Self::WithReplacement),
+            "exhaust-then-reshuffle" => Ok(Self::ExhaustThenReshuffle),
+            _ => Err(format!(
+                "invalid strategy '{}', expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Which theme of a show to prefer, by the `#N` numbering of the theme
+///
+/// Themes without a number count as the first one. Openings are preferred over endings with the
+/// same number, and endings over other soundtrack.
+#[derive(Debug, PartialEq)]
+pub enum ThemeOrder {
+    Earliest,
+    Latest,
+}
+
+impl ThemeOrder {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Earliest => "earliest",
+            Self::Latest => "latest",
+        }
+    }
+
+    /// Picks the preferred theme, `themes` are expected in the order of `all_themes`
+    fn choose<'a>(&self, themes: &[&'a String]) -> Option<&'a String> {
+        let indexed = themes
+            .iter()
+            .enumerate()
+            .map(|(position, theme)| (Theme::parse(theme).index.unwrap_or(1), position, *theme));
+        match self {
+            Self::Earliest => indexed.min_by_key(|&(index, position, _)| (index, position)),
+            Self::Latest => indexed.min_by_key(|&(index, position, _)| (Reverse(index), position)),
+        }
+        .map(|(_, _, theme)| theme)
+    }
+}
+
+impl FromStr for ThemeOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "earliest" => Ok(Self::Earliest),
+            "latest" => Ok(Self::Latest),
+            _ => Err(format!(
+                "invalid theme order '{}', expected 'earliest' or 'latest'",
+                s
+            )),
+        }
+    }
+}
+
+/// Everything that influences which themes get selected
+#[derive(Default)]
+pub struct SelectionOptions {
+    pub strategy: SelectionStrategy,
+    pub quotas: Vec<Quota>,
+    pub family_friendly: bool,
+    /// Also pick shows that are disabled in the dictionary
+    pub include_disabled: bool,
+    pub no_adjacent: Option<Adjacency>,
+    /// Never pick two themes by the same artist
+    pub unique_artist: bool,
+    /// Choose themes by their numbering instead of as the strategy would
+    pub theme_order: Option<ThemeOrder>,
+    /// Only pick shows with one of these statuses on the list, if any are given
+    pub statuses: Vec<WatchStatus>,
+    /// Only pick shows with at least this many members, or with fewer for `hipster`
+    pub min_members: Option<u64>,
+    pub hipster: bool,
+    /// How likely each type of theme is to be chosen within a show
+    pub type_weights: Option<TypeWeights>,
+    /// The types the picks cycle through, in order, as returned by `Show::song_type`
+    pub type_pattern: Vec<&'static str>,
+    /// Themes that are left out because they were already played, by show
+    pub played: HashSet<(ShowId, String)>,
+}
+
+impl SelectionOptions {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let quotas = matches
+            .values_of("quota")
+            .map(|values| {
+                values
+                    // Validated by clap
+                    .map(|v| v.parse().unwrap())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        SelectionOptions {
+            strategy: SelectionStrategy::from_matches(matches),
+            quotas,
+            family_friendly: matches.is_present("family-friendly"),
+            include_disabled: matches.is_present("include-disabled"),
+            // Validated by clap
+            no_adjacent: matches.value_of("no-adjacent").map(|v| v.parse().unwrap()),
+            unique_artist: matches.is_present("unique-artist"),
+            // Validated by clap
+            theme_order: matches.value_of("order-themes").map(|v| v.parse().unwrap()),
+            // Validated by clap
+            type_weights: matches
+                .values_of("type-weight")
+                .map(|values| TypeWeights::from_values(values).unwrap()),
+            type_pattern: matches
+                .values_of("interleave-types")
+                // Validated by clap
+                .map(|values| values.map(|v| parse_song_type(v).unwrap()).collect())
+                .unwrap_or_default(),
+            statuses: matches
+                .values_of("status")
+                // Validated by clap
+                .map(|values| values.map(|v| v.parse().unwrap()).collect())
+                .unwrap_or_default(),
+            // Validated by clap
+            min_members: matches
+                .value_of("min-popularity")
+                .map(|v| v.parse().unwrap()),
+            hipster: matches.is_present("hipster"),
+            played: HashSet::new(),
+        }
+    }
+
+    /// The names of the filters that are turned on
+    pub fn filter_names(&self) -> Vec<String> {
+        let mut names = vec![];
+        if self.family_friendly {
+            names.push("family-friendly".to_owned());
+        }
+        if !self.statuses.is_empty() {
+            let statuses: Vec<&str> = self.statuses.iter().map(|s| s.name()).collect();
+            names.push(format!("status {}", statuses.join("/")));
+        }
+        match (self.min_members, self.hipster) {
+            (Some(members), false) => names.push(format!("members >= {}", members)),
+            (Some(members), true) => names.push(format!("members < {}", members)),
+            (None, _) => {}
+        }
+        names
+    }
+
+    /// Checks if picks have to be checked against each other, which takes drawing more of them
+    /// than requested
+    fn has_constraints(&self) -> bool {
+        self.unique_artist || self.no_adjacent.is_some()
+    }
+
+    /// Checks if the show passes every filter and isn't disabled, shows without a number of
+    /// members pass the popularity filter
+    pub fn is_eligible(&self, show: &Show) -> bool {
+        let popular_enough = match (self.min_members, show.members) {
+            (Some(min), Some(members)) => (members >= min) != self.hipster,
+            _ => true,
+        };
+        (self.include_disabled || !show.disabled)
+            && (!self.family_friendly || show.is_family_friendly())
+            && popular_enough
+    }
+
+    /// Checks if the list entry passes every filter, entries without a status never pass a status filter
+    pub fn is_listed(&self, entry: &ListEntry) -> bool {
+        self.statuses.is_empty()
+            || entry
+                .status
+                .is_some_and(|status| self.statuses.contains(&status))
+    }
+}
+
+/// A single selected theme
+pub struct Pick<'a> {
+    pub song: &'a String,
+    pub show: &'a Show,
+    /// The number of shows drawn before this one
+    pub draw: usize,
+    /// The number of eligible shows left to draw from, including this one, which doesn't make
+    /// the odds of the draw when quotas or weights are involved
+    pub pool_size: usize,
+    /// The number of themes the song was chosen from
+    pub theme_count: usize,
+}
+
+/// Every eligible theme of every eligible show, in list order
+///
+/// Picks are made as they are needed instead of collected, for exporting everything on huge lists.
+pub fn every_theme<'a>(
+    options: &'a SelectionOptions,
+    list: &'a [ListEntry],
+    dictionary: &'a HashMap<ShowId, Show>,
+) -> impl Iterator<Item = Pick<'a>> + 'a {
+    let mut seen = HashSet::with_capacity(list.len());
+    list.iter()
+        .filter(move |entry| seen.insert(&entry.id))
+        .filter(move |entry| options.is_listed(entry))
+        .filter_map(move |entry| dictionary.get(&entry.id))
+        .filter(move |show| options.is_eligible(show))
+        .enumerate()
+        .flat_map(move |(draw, show)| {
+            let themes = eligible_themes(options, show);
+            let theme_count = themes.len();
+            themes.into_iter().map(move |song| Pick {
+                song,
+                show,
+                draw,
+                pool_size: 1,
+                theme_count,
+            })
+        })
+}
+
+/// How many times the requested number of picks are drawn when picks have constraints between
+/// them, so picks that break one can be replaced
+const OVERSAMPLING: usize = 3;
+
+/// Selects up to `number_of_results` themes from the shows in `list`
+///
+/// Fewer results are returned if the list does not contain enough eligible shows, unless the
+/// strategy allows repeats. With constraints between picks, like `unique_artist` or
+/// `no_adjacent`, more picks are drawn than requested and trimmed afterwards, so fewer results
+/// may also be returned when not enough of them are compatible.
+///
+/// A show that is on the list more than once is as much more likely to be picked, see
+/// `ListDuplicates` to remove duplicates first.
+///
+/// With a `type_pattern` the picks follow it instead, see `select_pattern`.
+pub fn select<'a>(
+    options: &SelectionOptions,
+    number_of_results: usize,
+    list: &[ListEntry],
+    dictionary: &'a HashMap<ShowId, Show>,
+    rng: &mut impl Rng,
+) -> Vec<Pick<'a>> {
+    let mut order: Vec<&ListEntry> = list.iter().collect();
+    if let SelectionStrategy::Random | SelectionStrategy::ExhaustThenReshuffle = options.strategy {
+        order.shuffle(rng);
+    }
+
+    // Filtered shows and shows without any themes are skipped in favour of the next one
+    let mut seen = HashSet::with_capacity(list.len());
+    let candidates: Vec<&Show> = order
+        .into_iter()
+        .filter(|entry| seen.insert(&entry.id))
+        .filter(|entry| options.is_listed(entry))
+        .filter_map(|entry| dictionary.get(&entry.id))
+        .filter(|show| options.is_eligible(show) && !unplayed_themes(options, show).is_empty())
+        .collect();
+
+    if !options.type_pattern.is_empty() {
+        return select_pattern(options, number_of_results, &candidates, rng);
+    }
+
+    let oversample = options.has_constraints() && !options.strategy.allows_repeats();
+    let draws = if oversample {
+        number_of_results.saturating_mul(OVERSAMPLING)
+    } else {
+        number_of_results
+    };
+
+    // Pairs of the draw number and the index of the drawn candidate
+    let drawn: Vec<(usize, usize)> = if options.strategy.allows_repeats() {
+        let mut entries: HashMap<&ShowId, usize> = HashMap::with_capacity(list.len());
+        for entry in list {
+            *entries.entry(&entry.id).or_default() += 1;
+        }
+        // Fails only without candidates
+        match WeightedIndex::new(
+            candidates
+                .iter()
+                .map(|show| entries.get(&show.id).unwrap_or(&1)),
+        ) {
+            Ok(index) => (0..number_of_results)
+                .map(|draw| (draw, index.sample(rng)))
+                .collect(),
+            Err(_) => vec![],
+        }
+    } else {
+        let mut drawn: Vec<(usize, usize)> = fill_quotas(&options.quotas, &candidates, draws)
+            .into_iter()
+            .enumerate()
+            .collect();
+        // Keep the candidate order, which is already random when it needs to be
+        drawn.sort_unstable_by_key(|&(_, i)| i);
+        drawn
+    };
+
+    let picks: Vec<Pick> = drawn
+        .into_iter()
+        .map(|(draw, i)| {
+            let show = candidates[i];
+            let themes = unplayed_themes(options, show);
+            let song = choose_theme(options, show, &themes, rng);
+            let pool_size = if options.strategy.allows_repeats() {
+                candidates.len()
+            } else {
+                candidates.len() - draw
+            };
+            // Every candidate has at least one theme
+            Pick {
+                song: song.unwrap(),
+                show,
+                draw,
+                pool_size,
+                theme_count: themes.len(),
+            }
+        })
+        .collect();
+
+    let picks = if oversample {
+        trim(options, picks, number_of_results)
+    } else {
+        picks
+    };
+
+    match &options.no_adjacent {
+        Some(by) => spread_out(picks, by),
+        None => picks,
+    }
+}
+
+/// Selects up to `number_of_results` themes whose types cycle through `options.type_pattern`,
+/// e.g. OP, ED, OP, ED
+///
+/// Every slot takes the next candidate with a theme of its type, or a random one when the
+/// strategy allows repeats. Once no candidate has a theme of a type left its slots are skipped,
+/// with a warning, so fewer results are returned.
+fn select_pattern<'a>(
+    options: &SelectionOptions,
+    number_of_results: usize,
+    candidates: &[&'a Show],
+    rng: &mut impl Rng,
+) -> Vec<Pick<'a>> {
+    let themes_of_type = |show: &'a Show, song_type: &str| -> Vec<&'a String> {
+        unplayed_themes(options, show)
+            .into_iter()
+            .filter(|theme| show.song_type(theme) == song_type)
+            .collect()
+    };
+
+    let mut types = options.type_pattern.clone();
+    types.sort_unstable();
+    types.dedup();
+    let mut dry: Vec<&str> = vec![];
+    let mut taken = vec![false; candidates.len()];
+    let mut picks = Vec::with_capacity(number_of_results);
+
+    for &song_type in options.type_pattern.iter().cycle() {
+        if picks.len() == number_of_results || dry.len() == types.len() {
+            break;
+        }
+        if dry.contains(&song_type) {
+            continue;
+        }
+
+        let pool: Vec<usize> = (0..candidates.len())
+            .filter(|&i| options.strategy.allows_repeats() || !taken[i])
+            .filter(|&i| !themes_of_type(candidates[i], song_type).is_empty())
+            .collect();
+        // The candidate order is already random when it needs to be
+        let i = if options.strategy.allows_repeats() {
+            pool.choose(rng).copied()
+        } else {
+            pool.first().copied()
+        };
+        let i = match i {
+            Some(i) => i,
+            None => {
+                warn!(
+                    "no more {} themes can be selected, the pattern continues without them",
+                    song_type
+                );
+                dry.push(song_type);
+                continue;
+            }
+        };
+
+        taken[i] = true;
+        let show = candidates[i];
+        let themes = themes_of_type(show, song_type);
+        // Only candidates with a theme of the type are in the pool
+        picks.push(Pick {
+            song: choose_theme(options, show, &themes, rng).unwrap(),
+            show,
+            draw: picks.len(),
+            pool_size: pool.len(),
+            theme_count: themes.len(),
+        });
+    }
+    picks
+}
+
+/// Chooses one of `themes` of `show` as the strategy and `theme_order` say, `None` only without
+/// themes
+fn choose_theme<'a>(
+    options: &SelectionOptions,
+    show: &Show,
+    themes: &[&'a String],
+    rng: &mut impl Rng,
+) -> Option<&'a String> {
+    match (&options.theme_order, &options.strategy) {
+        (Some(order), _) => order.choose(themes),
+        (
+            None,
+            SelectionStrategy::Random
+            | SelectionStrategy::WithReplacement
+            | SelectionStrategy::ExhaustThenReshuffle,
+        ) => match &options.type_weights {
+            Some(weights) => themes
+                .choose_weighted(rng, |theme| weights.weight(show.song_type(theme)))
+                .ok()
+                .copied(),
+            None => themes.choose(rng).copied(),
+        },
+        (None, SelectionStrategy::TakeFirst) => themes.first().copied(),
+    }
+}
+
+/// Keeps up to `number_of_results` picks that satisfy the constraints between them, preferring
+/// the ones drawn first
+///
+/// For `no_adjacent` a pick is only kept while no more than half of the picks share its
+/// attribute, which leaves an order without neighbours in common. When a pick conflicts with one
+/// that was kept before, the one of the show with the higher priority is kept.
+fn trim<'a>(
+    options: &SelectionOptions,
+    picks: Vec<Pick<'a>>,
+    number_of_results: usize,
+) -> Vec<Pick<'a>> {
+    let drawn = picks.len();
+    let mut by_draw: Vec<usize> = (0..picks.len()).collect();
+    by_draw.sort_unstable_by_key(|&i| picks[i].draw);
+
+    let artists: Vec<Option<String>> = picks
+        .iter()
+        .map(|pick| {
+            Theme::parse(pick.song)
+                .artist
+                .map(|a| Normalization::MATCHING.apply(a))
+        })
+        .collect();
+    let keys: Vec<Option<String>> = picks
+        .iter()
+        .map(|pick| options.no_adjacent.as_ref().and_then(|by| by.key(pick)))
+        .collect();
+    let most_adjacent = number_of_results.div_ceil(2);
+    let conflicts = |i: usize, j: usize| {
+        (options.unique_artist && artists[i].is_some() && artists[i] == artists[j])
+            || (keys[i].is_some() && keys[i] == keys[j])
+    };
+    let fits = |i: usize, kept: &[usize]| {
+        let artist_taken = options.unique_artist
+            && artists[i].is_some()
+            && kept.iter().any(|&k| artists[k] == artists[i]);
+        let sharing_key = kept
+            .iter()
+            .filter(|&&k| keys[i].is_some() && keys[k] == keys[i])
+            .count();
+        !artist_taken && sharing_key < most_adjacent
+    };
+
+    let mut kept: Vec<usize> = Vec::with_capacity(number_of_results);
+    for i in by_draw {
+        if kept.len() == number_of_results {
+            break;
+        }
+        if fits(i, &kept) {
+            kept.push(i);
+            continue;
+        }
+
+        // Replace the conflicting pick of the lowest priority, if this one has a higher priority
+        // and fits in its place
+        let priority = |i: usize| picks[i].show.priority;
+        let replaced = (0..kept.len())
+            .filter(|&k| conflicts(i, kept[k]))
+            .min_by_key(|&k| (priority(kept[k]), Reverse(k)))
+            .filter(|&k| priority(kept[k]) < priority(i));
+        if let Some(k) = replaced {
+            let mut without = kept.clone();
+            without.remove(k);
+            if fits(i, &without) {
+                kept[k] = i;
+            }
+        }
+    }
+
+    if kept.len() < number_of_results.min(drawn) {
+        warn!(
+            "only {} of {} results could be selected without breaking the constraints between them",
+            kept.len(),
+            number_of_results
+        );
+    }
+
+    // Keep the output order
+    picks
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| kept.contains(i))
+        .map(|(_, pick)| pick)
+        .collect()
+}
+
+/// The themes of a show that can be chosen, which leaves out types with a weight of 0
+pub(crate) fn eligible_themes<'a>(options: &SelectionOptions, show: &'a Show) -> Vec<&'a String> {
+    let themes = all_themes(show);
+    match &options.type_weights {
+        Some(weights) => themes
+            .into_iter()
+            .filter(|theme| weights.weight(show.song_type(theme)) > 0.0)
+            .collect(),
+        None => themes,
+    }
+}
+
+/// The eligible themes of a show that weren't played yet
+fn unplayed_themes<'a>(options: &SelectionOptions, show: &'a Show) -> Vec<&'a String> {
+    let mut themes = eligible_themes(options, show);
+    if !options.played.is_empty() {
+        themes.retain(|theme| {
+            !options
+                .played
+                .contains(&(show.id.clone(), theme.to_string()))
+        });
+    }
+    themes
+}
+
+/// Collects every theme of a show: openings, then endings, then other soundtrack
+fn all_themes(show: &Show) -> Vec<&String> {
+    show.opening_themes
+        .iter()
+        .chain(&show.ending_themes)
+        .chain(&show.other_soundtrack)
+        .collect()
+}
+
+/// Shows and picks for the tests of everything that outputs picks
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::Pick;
+    use crate::Show;
+
+    /// Show 1 called `title`, with `opening` as its only theme
+    pub(crate) fn show(title: &str, opening: &str) -> Show {
+        Show {
+            id: 1usize.into(),
+            title: title.to_owned(),
+            opening_themes: vec![opening.to_owned()],
+            ..Default::default()
+        }
+    }
+
+    /// Cowboy Bebop with "Tank!", the show most output is tested with
+    pub(crate) fn bebop() -> Show {
+        show("Cowboy Bebop", "\"Tank!\" by The Seatbelts")
+    }
+
+    /// A pick of `song` from `show`, as the only theme of the only eligible show
+    pub(crate) fn pick<'a>(song: &'a String, show: &'a Show) -> Pick<'a> {
+        Pick {
+            song,
+            show,
+            draw: 0,
+            pool_size: 1,
+            theme_count: 1,
+        }
+    }
+
+    /// A pick of the first opening of `show`
+    pub(crate) fn opening(show: &Show) -> Pick<'_> {
+        pick(&show.opening_themes[0], show)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn show(id: usize, themes: &[&str]) -> Show {
+        Show {
+            id: id.into(),
+            title: format!("Show {}", id),
+            opening_themes: themes.iter().map(|s| s.to_string()).collect(),
+            other_soundtrack: vec!["OST".to_owned()],
+            ..Default::default()
+        }
+    }
+
+    fn dictionary() -> HashMap<ShowId, Show> {
+        let mut dictionary = HashMap::new();
+        for id in 1..=5 {
+            dictionary.insert(id.into(), show(id, &["OP 1", "OP 2"]));
+        }
+        let mut empty = show(6, &[]);
+        empty.other_soundtrack.clear();
+        dictionary.insert(6usize.into(), empty);
+        dictionary
+    }
+
+    fn ids(ids: &[usize]) -> Vec<ListEntry> {
+        ids.iter().map(|&id| ShowId::from(id).into()).collect()
+    }
+
+    fn options(strategy: SelectionStrategy) -> SelectionOptions {
+        SelectionOptions {
+            strategy,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn take_first_respects_list_order() {
+        let dictionary = dictionary();
+        let list = ids(&[3, 6, 9, 3, 1, 5, 2]);
+        let picks = select(
+            &options(SelectionStrategy::TakeFirst),
+            3,
+            &list,
+            &dictionary,
+            &mut rand::thread_rng(),
+        );
+        let picked: Vec<ShowId> = picks.iter().map(|p| p.show.id.clone()).collect();
+        assert_eq!(picked, [3usize, 1, 5].map(ShowId::from));
+        assert!(picks.iter().all(|p| p.song == "OP 1"));
+    }
+
+    #[test]
+    fn random_has_no_repeats() {
+        let dictionary = dictionary();
+        let list = ids(&[1, 1, 2, 2, 3, 6, 7]);
+        let picks = select(
+            &options(SelectionStrategy::Random),
+            10,
+            &list,
+            &dictionary,
+            &mut rand::thread_rng(),
+        );
+        let mut picked: Vec<ShowId> = picks.iter().map(|p| p.show.id.clone()).collect();
+        picked.sort();
+        assert_eq!(picked, [1usize, 2, 3].map(ShowId::from));
+    }
+
+    #[test]
+    fn small_pools() {
+        let dictionary = dictionary();
+        // Show 6 has no themes and 7 isn't in the dictionary, so only three shows are eligible
+        let list = ids(&[1, 2, 3, 6, 7]);
+        let eligible = [1usize, 2, 3].map(ShowId::from);
+        for name in ["random", "take-first", "exhaust-then-reshuffle"] {
+            for unique_artist in [false, true] {
+                let options = SelectionOptions {
+                    unique_artist,
+                    ..options(name.parse().unwrap())
+                };
+                let picks = select(&options, 5, &list, &dictionary, &mut rand::thread_rng());
+                let mut picked: Vec<ShowId> = picks.iter().map(|p| p.show.id.clone()).collect();
+                picked.sort();
+                assert_eq!(picked, eligible, "{}", name);
+            }
+        }
+
+        // Repeats make any pool big enough, unless it's empty
+        let options = options(SelectionStrategy::WithReplacement);
+        let picks = select(&options, 5, &list, &dictionary, &mut rand::thread_rng());
+        assert_eq!(picks.len(), 5);
+        let picks = select(
+            &options,
+            5,
+            &ids(&[6, 7]),
+            &dictionary,
+            &mut rand::thread_rng(),
+        );
+        assert!(picks.is_empty());
+    }
+
+    #[test]
+    fn type_pattern() {
+        let mut dictionary = dictionary();
+        dictionary.get_mut(&2usize.into()).unwrap().ending_themes = vec!["ED 1".to_owned()];
+        let list = ids(&[1, 2, 3, 4]);
+        let pattern = SelectionOptions {
+            type_pattern: vec!["OP", "ED", "ST"],
+            ..options(SelectionStrategy::TakeFirst)
+        };
+        // Show 2 has the only ending, after that the pattern goes on without them until every
+        // show is taken
+        let picks = select(&pattern, 6, &list, &dictionary, &mut rand::thread_rng());
+        let picked: Vec<(ShowId, &str)> = picks
+            .iter()
+            .map(|p| (p.show.id.clone(), p.show.song_type(p.song)))
+            .collect();
+        let expected = [(1usize, "OP"), (2, "ED"), (3, "ST"), (4, "OP")];
+        assert_eq!(
+            picked,
+            expected.map(|(id, song_type)| (ShowId::from(id), song_type))
+        );
+
+        // Repeats keep the pattern going as long as any show has the type
+        let pattern = SelectionOptions {
+            type_pattern: vec!["ED", "OP"],
+            ..options(SelectionStrategy::WithReplacement)
+        };
+        let picks = select(&pattern, 4, &list, &dictionary, &mut rand::thread_rng());
+        let types: Vec<&str> = picks.iter().map(|p| p.show.song_type(p.song)).collect();
+        assert_eq!(types, ["ED", "OP", "ED", "OP"]);
+    }
+
+    #[test]
+    fn status_filter() {
+        let dictionary = dictionary();
+        let mut list = ids(&[1, 2, 3, 4]);
+        list[0].status = Some(WatchStatus::Completed);
+        list[1].status = Some(WatchStatus::Dropped);
+        list[2].status = Some(WatchStatus::Completed);
+        let options = SelectionOptions {
+            statuses: vec![WatchStatus::Completed, WatchStatus::Watching],
+            ..options(SelectionStrategy::TakeFirst)
+        };
+        let picks = select(&options, 5, &list, &dictionary, &mut rand::thread_rng());
+        let picked: Vec<ShowId> = picks.iter().map(|p| p.show.id.clone()).collect();
+        assert_eq!(picked, [1usize, 3].map(ShowId::from));
+        assert_eq!(options.filter_names(), ["status completed/watching"]);
+    }
+
+    #[test]
+    fn popularity_filter() {
+        let mut dictionary = dictionary();
+        for (id, members) in [(1usize, 500), (2, 90_000), (3, 10_000)] {
+            dictionary.get_mut(&id.into()).unwrap().members = Some(members);
+        }
+        let list = ids(&[1, 2, 3, 4]);
+        let mut options = SelectionOptions {
+            min_members: Some(10_000),
+            ..options(SelectionStrategy::TakeFirst)
+        };
+        let picked = |options: &SelectionOptions| -> Vec<ShowId> {
+            select(options, 4, &list, &dictionary, &mut rand::thread_rng())
+                .iter()
+                .map(|p| p.show.id.clone())
+                .collect()
+        };
+        // Shows without members are kept either way
+        assert_eq!(picked(&options), [2usize, 3, 4].map(ShowId::from));
+        options.hipster = true;
+        assert_eq!(picked(&options), [1usize, 4].map(ShowId::from));
+        assert_eq!(options.filter_names(), ["members < 10000"]);
+    }
+
+    #[test]
+    fn disabled_shows() {
+        let mut dictionary = dictionary();
+        dictionary.get_mut(&2usize.into()).unwrap().disabled = true;
+        let list = ids(&[1, 2, 3]);
+        let mut options = options(SelectionStrategy::TakeFirst);
+        let picked = |options: &SelectionOptions| -> Vec<ShowId> {
+            select(options, 3, &list, &dictionary, &mut rand::thread_rng())
+                .iter()
+                .map(|p| p.show.id.clone())
+                .collect()
+        };
+        assert_eq!(picked(&options), [1usize, 3].map(ShowId::from));
+        options.include_disabled = true;
+        assert_eq!(picked(&options), [1usize, 2, 3].map(ShowId::from));
+    }
+
+    #[test]
+    fn type_weights() {
+        use rand::SeedableRng;
+
+        let dictionary = dictionary();
+        let list = ids(&[1, 2, 3, 4, 5]);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(228);
+        let options = SelectionOptions {
+            type_weights: Some(TypeWeights::from_values(vec!["op=3", "ost=2"]).unwrap()),
+            ..options(SelectionStrategy::WithReplacement)
+        };
+        let draws = 30_000;
+        let picks = select(&options, draws, &list, &dictionary, &mut rng);
+        // Both openings have weight 3, the OST has weight 2
+        let ost = picks.iter().filter(|p| p.song == "OST").count() as f64;
+        let expected = draws as f64 * 2.0 / 8.0;
+        assert!((ost - expected).abs() < expected * 0.05);
+
+        // Shows whose themes are all weighted 0 can't be picked
+        let options = SelectionOptions {
+            type_weights: Some(TypeWeights::from_values(vec!["op=0", "ost=0"]).unwrap()),
+            ..Default::default()
+        };
+        assert!(select(&options, 3, &list, &dictionary, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn oversampling_constraints() {
+        let mut dictionary = HashMap::new();
+        for (id, artist, franchise) in [
+            (1usize, "Same", "A"),
+            (2, "same", "A"),
+            (3, "Same", "A"),
+            (4, "Other", "B"),
+            (5, "Another", "A"),
+        ] {
+            let mut show = show(id, &[&format!("\"Song {}\" by {}", id, artist)]);
+            show.other_soundtrack.clear();
+            show.franchise = Some(franchise.to_owned());
+            dictionary.insert(show.id.clone(), show);
+        }
+        let list = ids(&[1, 2, 3, 4, 5]);
+        let mut rng = rand::thread_rng();
+        let shows = |picks: Vec<Pick>| -> Vec<String> {
+            picks.iter().map(|p| p.show.id.to_string()).collect()
+        };
+
+        // Later shows replace the ones by an artist that was already picked
+        let constrained = SelectionOptions {
+            unique_artist: true,
+            ..options(SelectionStrategy::TakeFirst)
+        };
+        let picks = select(&constrained, 3, &list, &dictionary, &mut rng);
+        assert_eq!(shows(picks), ["1", "4", "5"]);
+        assert_eq!(
+            select(&constrained, 4, &list, &dictionary, &mut rng).len(),
+            3
+        );
+
+        // At most two of three picks can share a franchise without being neighbours
+        let constrained = SelectionOptions {
+            no_adjacent: Some(Adjacency::Franchise),
+            ..options(SelectionStrategy::TakeFirst)
+        };
+        let picks = select(&constrained, 3, &list, &dictionary, &mut rng);
+        assert_eq!(shows(picks), ["1", "4", "2"]);
+
+        // Conflicts are settled in favour of the show with the higher priority
+        dictionary.get_mut(&ShowId::from(3usize)).unwrap().priority = 1;
+        let constrained = SelectionOptions {
+            unique_artist: true,
+            ..options(SelectionStrategy::TakeFirst)
+        };
+        let picks = select(&constrained, 3, &list, &dictionary, &mut rng);
+        assert_eq!(shows(picks), ["3", "4", "5"]);
+    }
+
+    #[test]
+    fn every_theme_in_list_order() {
+        let dictionary = dictionary();
+        let list = ids(&[2, 6, 1, 2]);
+        let options = SelectionOptions {
+            type_weights: Some(TypeWeights::from_values(vec!["ost=0"]).unwrap()),
+            ..Default::default()
+        };
+        let picks: Vec<(String, String)> = every_theme(&options, &list, &dictionary)
+            .map(|pick| (pick.show.id.to_string(), pick.song.clone()))
+            .collect();
+        assert_eq!(
+            picks,
+            [("2", "OP 1"), ("2", "OP 2"), ("1", "OP 1"), ("1", "OP 2")]
+                .map(|(id, song)| (id.to_owned(), song.to_owned()))
+        );
+    }
+
+    #[test]
+    fn with_replacement_is_uniform() {
+        use rand::SeedableRng;
+
+        let dictionary = dictionary();
+        let list = ids(&[1, 2, 3, 6]);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(207);
+        let draws = 30_000;
+        let picks = select(
+            &options(SelectionStrategy::WithReplacement),
+            draws,
+            &list,
+            &dictionary,
+            &mut rng,
+        );
+        assert_eq!(picks.len(), draws);
+
+        let mut shows: HashMap<&ShowId, usize> = HashMap::new();
+        let mut songs: HashMap<&String, usize> = HashMap::new();
+        for pick in &picks {
+            assert_eq!(pick.pool_size, 3);
+            *shows.entry(&pick.show.id).or_insert(0) += 1;
+            *songs.entry(pick.song).or_insert(0) += 1;
+        }
+        // Show 6 has no themes
+        assert_eq!(shows.len(), 3);
+        for &count in shows.values() {
+            let expected = draws / 3;
+            assert!((count as f64 - expected as f64).abs() < expected as f64 * 0.05);
+        }
+        // Every show has the same three themes
+        assert_eq!(songs.len(), 3);
+        for &count in songs.values() {
+            let expected = draws / 3;
+            assert!((count as f64 - expected as f64).abs() < expected as f64 * 0.05);
+        }
+
+        let picks = select(
+            &options(SelectionStrategy::WithReplacement),
+            5,
+            &ids(&[6, 7]),
+            &dictionary,
+            &mut rng,
+        );
+        assert!(picks.is_empty());
+    }
+
+    #[test]
+    fn theme_order() {
+        let themes = [
+            "#2: \"Ah Yeah!!\" by Sukima Switch".to_owned(),
+            "#1: \"Imagination\" by SPYAIR".to_owned(),
+            "#1: \"Tsuki no Mienai Yoru ni\" by Nico Touches the Walls".to_owned(),
+            "#3: \"Climber\" by SPYAIR".to_owned(),
+            "\"Hikari Are\" by BURNOUT SYNDROMES".to_owned(),
+        ];
+        let themes: Vec<&String> = themes.iter().collect();
+        assert_eq!(ThemeOrder::Earliest.choose(&themes), Some(themes[1]));
+        assert_eq!(ThemeOrder::Latest.choose(&themes), Some(themes[3]));
+        assert_eq!(ThemeOrder::Latest.choose(&[]), None);
+        assert!("first".parse::<ThemeOrder>().is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::list::ListEntry;
+use crate::progress::Progress;
+use crate::selection::{select, SelectionOptions, SelectionStrategy};
+use crate::{Show, ShowId};
+
+/// Deviations further than this many standard deviations from the expected value are flagged
+const THRESHOLD: f64 = 4.0;
+
+/// The observed frequency of a show or theme compared to the expected one
+pub struct Frequency {
+    pub expected: f64,
+    pub observed: usize,
+    /// The deviation from the expected value in standard deviations
+    pub deviation: f64,
+}
+
+impl Frequency {
+    /// Compares to a binomial distribution of `trials` trials with probability `p`
+    fn new(observed: usize, trials: usize, p: f64) -> Self {
+        let expected = trials as f64 * p;
+        let sd = (expected * (1.0 - p)).sqrt();
+        let deviation = if sd > 0.0 {
+            (observed as f64 - expected) / sd
+        } else {
+            0.0
+        };
+        Frequency {
+            expected,
+            observed,
+            deviation,
+        }
+    }
+
+    pub fn is_flagged(&self) -> bool {
+        self.deviation.abs() > THRESHOLD
+    }
+}
+
+/// The results of running the sampler repeatedly on a synthetic dictionary
+pub struct Report {
+    pub iterations: usize,
+    pub picks: usize,
+    pub strategy: &'static str,
+    /// The number of themes of each show and how often the show was picked
+    pub shows: Vec<(usize, Frequency)>,
+    /// How often each theme was picked
+    pub themes: Vec<Frequency>,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        !self
+            .shows
+            .iter()
+            .map(|(_, f)| f)
+            .chain(&self.themes)
+            .any(Frequency::is_flagged)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Self-test: {} iterations of {} picks from {} synthetic shows ({})",
+            self.iterations,
+            self.picks,
+            self.shows.len(),
+            self.strategy
+        )?;
+        writeln!(
+            f,
+            "{:>6} {:>7} {:>12} {:>12} {:>10}",
+            "show", "themes", "expected", "observed", "deviation"
+        )?;
+        for (i, (themes, frequency)) in self.shows.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>6} {:>7} {:>12.1} {:>12} {:>9.2}σ{}",
+                i + 1,
+                themes,
+                frequency.expected,
+                frequency.observed,
+                frequency.deviation,
+                if frequency.is_flagged() { "  <-" } else { "" }
+            )?;
+        }
+
+        let flagged_themes = self.themes.iter().filter(|f| f.is_flagged()).count();
+        let max_theme_deviation = self
+            .themes
+            .iter()
+            .map(|f| f.deviation.abs())
+            .fold(0.0, f64::max);
+        writeln!(
+            f,
+            "{} of {} themes deviate more than {}σ (largest deviation {:.2}σ)",
+            flagged_themes,
+            self.themes.len(),
+            THRESHOLD,
+            max_theme_deviation
+        )?;
+
+        if self.passed() {
+            write!(
+                f,
+                "All frequencies are within {}σ of the expected value",
+                THRESHOLD
+            )
+        } else {
+            write!(f, "Significant deviations were found")
+        }
+    }
+}
+
+/// A dictionary of `count` shows with one to three themes each
+fn synthetic_dictionary(count: usize) -> HashMap<ShowId, Show> {
+    (1..=count)
+        .map(|i| {
+            let themes = (i - 1) % 3 + 1;
+            let show = Show {
+                id: i.into(),
+                title: format!("Show {}", i),
+                opening_themes: (1..=themes).map(|t| format!("Theme {}-{}", i, t)).collect(),
+                ..Default::default()
+            };
+            (show.id.clone(), show)
+        })
+        .collect()
+}
+
+/// Runs the sampler `iterations` times and compares the frequencies to a uniform distribution
+pub fn run(
+    strategy: SelectionStrategy,
+    iterations: usize,
+    shows: usize,
+    picks: usize,
+    rng: &mut impl rand::Rng,
+    progress: &mut Progress,
+) -> Report {
+    let dictionary = synthetic_dictionary(shows);
+    let list: Vec<ListEntry> = (1..=shows).map(|id| ShowId::from(id).into()).collect();
+    let options = SelectionOptions {
+        strategy,
+        ..Default::default()
+    };
+
+    let mut show_counts: HashMap<&ShowId, usize> = HashMap::new();
+    let mut theme_counts: HashMap<&String, usize> = HashMap::new();
+    progress.start(Some(iterations));
+    for _ in 0..iterations {
+        for pick in select(&options, picks, &list, &dictionary, rng) {
+            *show_counts.entry(&pick.show.id).or_insert(0) += 1;
+            *theme_counts.entry(pick.song).or_insert(0) += 1;
+        }
+        progress.advance(1);
+    }
+    progress.finish();
+
+    // Without replacement every iteration is a single trial of whether a show is picked at all,
+    // with replacement every pick is a trial of its own
+    let (trials, show_p) = if options.strategy.allows_repeats() {
+        (iterations * picks, 1.0 / shows as f64)
+    } else {
+        (iterations, picks.min(shows) as f64 / shows as f64)
+    };
+
+    let mut show_frequencies = Vec::with_capacity(shows);
+    let mut theme_frequencies = Vec::new();
+    for ListEntry { id, .. } in &list {
+        let show = &dictionary[id];
+        let themes = &show.opening_themes;
+        let observed = show_counts.get(id).copied().unwrap_or(0);
+        show_frequencies.push((themes.len(), Frequency::new(observed, trials, show_p)));
+        for theme in themes {
+            let observed = theme_counts.get(theme).copied().unwrap_or(0);
+            theme_frequencies.push(Frequency::new(
+                observed,
+                trials,
+                show_p / themes.len() as f64,
+            ));
+        }
+    }
+
+    Report {
+        iterations,
+        picks,
+        strategy: options.strategy.name(),
+        shows: show_frequencies,
+        themes: theme_frequencies,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn sampler_passes_self_test() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(222);
+        let report = run(
+            SelectionStrategy::Random,
+            5_000,
+            6,
+            2,
+            &mut rng,
+            &mut Progress::default(),
+        );
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.shows.len(), 6);
+        assert_eq!(report.themes.len(), 12);
+
+        let report = run(
+            SelectionStrategy::WithReplacement,
+            5_000,
+            6,
+            2,
+            &mut rng,
+            &mut Progress::default(),
+        );
+        assert!(report.passed(), "{}", report);
+    }
+
+    #[test]
+    fn take_first_fails_self_test() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(222);
+        let report = run(
+            SelectionStrategy::TakeFirst,
+            1_000,
+            6,
+            2,
+            &mut rng,
+            &mut Progress::default(),
+        );
+        assert!(!report.passed());
+    }
+}
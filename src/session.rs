@@ -0,0 +1,246 @@
+//! State kept between runs of `pick`, in `session.json` in the data directory of the platform
+//! (see `crate::paths`)
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::path::Path;
+
+use log::info;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::list::ListEntry;
+use crate::lock::FileLock;
+use crate::selection::{eligible_themes, select, Pick, SelectionOptions};
+use crate::{read_json_file, write_json_file, Show, ShowId};
+
+/// The name of the session file in the data directory
+pub const FILE_NAME: &str = "session.json";
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Session {
+    /// The themes played in the current cycle of the `exhaust-then-reshuffle` strategy
+    #[serde(default)]
+    pub played: Vec<PlayedTheme>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PlayedTheme {
+    pub id: ShowId,
+    pub song: String,
+}
+
+impl Session {
+    /// Reads the session, which is empty until the first run that needs it
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Session, Box<dyn Error>> {
+        if path.as_ref().exists() {
+            read_json_file(path)
+        } else {
+            Ok(Session::default())
+        }
+    }
+
+    /// Locks the session until the lock is dropped, so runs at the same time don't undo each
+    /// other's updates, creating its directory if needed
+    pub fn lock<P: AsRef<Path>>(path: P) -> Result<FileLock, Box<dyn Error>> {
+        if let Some(dir) = path.as_ref().parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(FileLock::acquire(path)?)
+    }
+
+    /// Writes the session, creating its directory if needed
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.as_ref().parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        write_json_file(path, self)
+    }
+
+    /// Selects themes that weren't played yet in the current cycle, and marks them as played
+    ///
+    /// Once every eligible theme was played a new cycle starts, so the remaining picks may be
+    /// themes that were played before, but never of a show that was already picked in this run.
+    pub fn select_unplayed<'a>(
+        &mut self,
+        options: &mut SelectionOptions,
+        number_of_results: usize,
+        list: &[ListEntry],
+        dictionary: &'a HashMap<ShowId, Show>,
+        rng: &mut impl Rng,
+    ) -> Vec<Pick<'a>> {
+        options.played = self
+            .played
+            .iter()
+            .map(|theme| (theme.id.clone(), theme.song.clone()))
+            .collect();
+        let mut picks = select(options, number_of_results, list, dictionary, rng);
+
+        if picks.len() < number_of_results && !self.played.is_empty() {
+            info!("every eligible theme was played, starting over");
+            self.played.clear();
+            options.played.clear();
+
+            let picked: HashSet<&ShowId> = picks.iter().map(|pick| &pick.show.id).collect();
+            let rest: Vec<ListEntry> = list
+                .iter()
+                .filter(|entry| !picked.contains(&entry.id))
+                .cloned()
+                .collect();
+            let drawn = picks.len();
+            let more = select(options, number_of_results - drawn, &rest, dictionary, rng);
+            picks.extend(more.into_iter().map(|pick| Pick {
+                draw: pick.draw + drawn,
+                ..pick
+            }));
+            // The picks before the reshuffle finished the previous cycle
+            self.played = picks[drawn..].iter().map(PlayedTheme::from).collect();
+        } else {
+            self.played.extend(picks.iter().map(PlayedTheme::from));
+        }
+        picks
+    }
+
+    /// The number of eligible themes played in the current cycle, and the number of eligible
+    /// themes in total
+    pub fn progress(
+        &self,
+        options: &SelectionOptions,
+        list: &[ListEntry],
+        dictionary: &HashMap<ShowId, Show>,
+    ) -> (usize, usize) {
+        let mut seen = HashSet::with_capacity(list.len());
+        let eligible: HashSet<(&ShowId, &String)> = list
+            .iter()
+            .filter(|entry| seen.insert(&entry.id))
+            .filter(|entry| options.is_listed(entry))
+            .filter_map(|entry| dictionary.get(&entry.id))
+            .filter(|show| options.is_eligible(show))
+            .flat_map(|show| {
+                eligible_themes(options, show)
+                    .into_iter()
+                    .map(move |theme| (&show.id, theme))
+            })
+            .collect();
+        let played = self
+            .played
+            .iter()
+            .filter(|theme| eligible.contains(&(&theme.id, &theme.song)))
+            .count();
+        (played, eligible.len())
+    }
+}
+
+impl From<&Pick<'_>> for PlayedTheme {
+    fn from(pick: &Pick) -> Self {
+        PlayedTheme {
+            id: pick.show.id.clone(),
+            song: pick.song.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::SelectionStrategy;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn exhaust_then_reshuffle() {
+        let mut dictionary = HashMap::new();
+        for id in 1usize..=3 {
+            let show = Show {
+                id: id.into(),
+                title: format!("Show {}", id),
+                opening_themes: vec![format!("#1: OP {}", id), format!("#2: OP {}", id)],
+                ..Default::default()
+            };
+            dictionary.insert(show.id.clone(), show);
+        }
+        let list: Vec<ListEntry> = [1usize, 2, 3]
+            .iter()
+            .map(|&id| ShowId::from(id).into())
+            .collect();
+        let mut options = SelectionOptions {
+            strategy: SelectionStrategy::ExhaustThenReshuffle,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(239);
+        let mut session = Session::default();
+
+        // Every theme is played once before any is repeated
+        let mut played = vec![];
+        for _ in 0..2 {
+            let picks = session.select_unplayed(&mut options, 3, &list, &dictionary, &mut rng);
+            assert_eq!(picks.len(), 3);
+            played.extend(picks.iter().map(PlayedTheme::from));
+        }
+        let mut songs: Vec<&String> = played.iter().map(|theme| &theme.song).collect();
+        songs.sort();
+        songs.dedup();
+        assert_eq!(songs.len(), 6);
+        assert_eq!(session.progress(&options, &list, &dictionary), (6, 6));
+
+        // The next cycle starts over
+        let picks = session.select_unplayed(&mut options, 2, &list, &dictionary, &mut rng);
+        assert_eq!(picks.len(), 2);
+        assert_eq!(session.progress(&options, &list, &dictionary), (2, 6));
+        let serialized = serde_json::to_string(&session).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Session>(&serialized).unwrap(),
+            session
+        );
+    }
+
+    #[test]
+    fn reshuffle_mid_run() {
+        let mut dictionary = HashMap::new();
+        for id in 1usize..=3 {
+            let show = Show {
+                id: id.into(),
+                title: format!("Show {}", id),
+                opening_themes: vec![format!("OP {}", id)],
+                ..Default::default()
+            };
+            dictionary.insert(show.id.clone(), show);
+        }
+        let list: Vec<ListEntry> = [1usize, 2, 3]
+            .iter()
+            .map(|&id| ShowId::from(id).into())
+            .collect();
+        let mut options = SelectionOptions {
+            strategy: SelectionStrategy::ExhaustThenReshuffle,
+            ..Default::default()
+        };
+        let mut session = Session {
+            played: vec![
+                PlayedTheme {
+                    id: 1usize.into(),
+                    song: "OP 1".to_owned(),
+                },
+                PlayedTheme {
+                    id: 2usize.into(),
+                    song: "OP 2".to_owned(),
+                },
+            ],
+        };
+
+        // The last unplayed theme finishes the cycle, the others come from the next one
+        let picks = session.select_unplayed(
+            &mut options,
+            3,
+            &list,
+            &dictionary,
+            &mut StdRng::seed_from_u64(239),
+        );
+        assert_eq!(picks[0].song, "OP 3");
+        let mut shows: Vec<&ShowId> = picks.iter().map(|pick| &pick.show.id).collect();
+        shows.sort();
+        shows.dedup();
+        assert_eq!(shows.len(), 3);
+        assert_eq!(session.played.len(), 2);
+        assert!(session.played.iter().all(|theme| theme.song != "OP 3"));
+    }
+}
@@ -0,0 +1,38 @@
+/// The name of the variable holding a column of the pick at the 1-based position `number`,
+/// e.g. `PICK_1_SONG`
+pub fn variable_name(number: usize, header: &str) -> String {
+    let header: String = header
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("PICK_{}_{}", number, header)
+}
+
+/// Quotes a value so that POSIX shells read it back unchanged
+///
+/// Everything is single quoted, single quotes themselves are written as `'\''`.
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_assignments() {
+        assert_eq!(variable_name(1, "Song"), "PICK_1_SONG");
+        assert_eq!(variable_name(12, "my-links"), "PICK_12_MY_LINKS");
+        assert_eq!(quote("Tank!"), "'Tank!'");
+        assert_eq!(
+            quote(r#""Ai no Uta" by Stereo's $HOME"#),
+            r#"'"Ai no Uta" by Stereo'\''s $HOME'"#
+        );
+    }
+}
@@ -0,0 +1,401 @@
+//! Where output goes with `--out`, each in a format of its own
+//!
+//! A sink is `stdout`, `file=PATH`, `command=COMMAND` (which gets the output on stdin) or
+//! `clipboard`, optionally followed by `:format=NAME` with one of `OutputMode::NAMES`. Without
+//! a format a sink gets the output the other flags choose, readable text by default.
+//!
+//! Sinks are delivered to one after the other, so a failing sink can't take back what the ones
+//! before it got. `SinkFailure` decides what happens to the ones after it.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::ArgMatches;
+use log::warn;
+
+use crate::exec::shell;
+use crate::{write_file_atomically, OutputMode};
+
+const FORMAT: &str = ":format=";
+
+/// How often a sink is tried with `SinkFailure::Retry`
+const ATTEMPTS: u32 = 3;
+/// The wait before the first retry, which doubles for every retry after it
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, PartialEq)]
+pub enum Target {
+    Stdout,
+    File(PathBuf),
+    /// A file the output is added to the end of, with `--output` and `--append`
+    Append(PathBuf),
+    /// A shell command line
+    Command(String),
+    Clipboard,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Sink {
+    pub target: Target,
+    pub format: Option<String>,
+}
+
+impl Sink {
+    /// Writes the whole output to the target, replacing the file of a file sink atomically
+    pub fn deliver(&self, output: &[u8]) -> Result<(), Box<dyn Error>> {
+        match &self.target {
+            Target::Stdout => {
+                let mut stdout = io::stdout();
+                stdout
+                    .write_all(output)
+                    .and_then(|()| stdout.flush())
+                    .map_err(|e| format!("couldn't write to stdout: {}", e))?;
+            }
+            Target::File(path) => write_file_atomically(path, output)
+                .map_err(|e| format!("couldn't write {}: {}", path.display(), e))?,
+            Target::Append(path) => OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(output))
+                .map_err(|e| format!("couldn't append to {}: {}", path.display(), e))?,
+            Target::Command(command_line) => pipe(shell(command_line), command_line, output)?,
+            Target::Clipboard => {
+                let (program, args) = clipboard_command();
+                let mut command = Command::new(program);
+                command.args(args);
+                pipe(command, program, output)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `deliver`, trying again after a growing wait if it fails and `failure` says to retry
+    pub fn deliver_with(&self, output: &[u8], failure: SinkFailure) -> Result<(), Box<dyn Error>> {
+        let attempts = if failure == SinkFailure::Retry {
+            ATTEMPTS
+        } else {
+            1
+        };
+        let mut delay = RETRY_DELAY;
+        for _ in 1..attempts {
+            match self.deliver(output) {
+                Ok(()) => return Ok(()),
+                Err(e) => warn!("{}, trying {} again in {:?}", e, self.target, delay),
+            }
+            std::thread::sleep(delay);
+            delay *= 2;
+        }
+        self.deliver(output)
+    }
+
+    /// Checks if the sink is the terminal, if anything is, so colors are only written there
+    pub fn is_stdout(&self) -> bool {
+        self.target == Target::Stdout
+    }
+
+    /// Checks if the sink adds to a file that already has output, which shouldn't get another
+    /// header row
+    pub fn continues_file(&self) -> bool {
+        match &self.target {
+            Target::Append(path) => path.metadata().is_ok_and(|m| m.len() > 0),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stdout => write!(f, "stdout"),
+            Self::File(path) | Self::Append(path) => write!(f, "file={}", path.display()),
+            Self::Command(command_line) => write!(f, "command={}", command_line),
+            Self::Clipboard => write!(f, "clipboard"),
+        }
+    }
+}
+
+/// The output modes whose output can follow earlier output in the same file, for `--append`
+pub const APPENDABLE: &[&str] = &["plain", "csv", "tsv", "ndjson"];
+
+/// The output mode of a file by its extension, e.g. `csv` for `picks.csv`
+pub fn format_of_path(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()?.to_lowercase().as_str() {
+        "csv" => Some("csv"),
+        "tsv" => Some("tsv"),
+        "md" | "markdown" => Some("markdown"),
+        "json" => Some("json"),
+        "ndjson" | "jsonl" => Some("ndjson"),
+        "msgpack" => Some("msgpack"),
+        "m3u" | "m3u8" => Some("m3u"),
+        "sql" => Some("sql"),
+        "sh" => Some("emit-shell"),
+        _ => None,
+    }
+}
+
+/// The file sinks of `--output`, each in the output mode of its extension, or else in the one
+/// the other flags choose
+///
+/// Fails if `--append` is given for a file whose output couldn't follow earlier output.
+pub fn output_sinks(matches: &ArgMatches) -> Result<Vec<Sink>, String> {
+    let append = matches.is_present("append");
+    matches
+        .values_of("output")
+        .into_iter()
+        .flatten()
+        .map(|path| {
+            let path = PathBuf::from(path);
+            let format = format_of_path(&path);
+            let appendable = format
+                .or_else(|| OutputMode::chosen_name(matches))
+                .is_some_and(|format| APPENDABLE.contains(&format));
+            if append && !appendable {
+                return Err(format!(
+                    "--append only works with plain, CSV, TSV and NDJSON output, which {} wouldn't get",
+                    path.display()
+                ));
+            }
+            Ok(Sink {
+                target: if append {
+                    Target::Append(path)
+                } else {
+                    Target::File(path)
+                },
+                format: format.map(str::to_owned),
+            })
+        })
+        .collect()
+}
+
+/// What happens to the sinks after one that can't be delivered to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SinkFailure {
+    /// Skip them and exit with exit code 1
+    Abort,
+    /// Deliver to them anyway
+    Continue,
+    /// Try the failing sink again a few times first, then deliver to them anyway
+    Retry,
+}
+
+impl SinkFailure {
+    pub const NAMES: &'static [&'static str] = &["abort", "continue", "retry"];
+}
+
+impl FromStr for SinkFailure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "abort" => Ok(Self::Abort),
+            "continue" => Ok(Self::Continue),
+            "retry" => Ok(Self::Retry),
+            _ => Err(format!(
+                "invalid sink failure policy '{}', expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// What became of the output for a sink
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Delivery {
+    Delivered,
+    Failed,
+    /// Not tried, since an earlier sink failed with `SinkFailure::Abort`
+    Skipped,
+}
+
+impl Delivery {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Delivered => "delivered",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        }
+    }
+}
+
+/// A line with what became of the output for every sink, e.g.
+/// `delivered to 1 of 2 sinks: stdout delivered, clipboard failed`
+pub fn summary(deliveries: &[(&Sink, Delivery)]) -> String {
+    let delivered = deliveries
+        .iter()
+        .filter(|(_, delivery)| *delivery == Delivery::Delivered)
+        .count();
+    let statuses: Vec<String> = deliveries
+        .iter()
+        .map(|(sink, delivery)| format!("{} {}", sink.target, delivery.name()))
+        .collect();
+    format!(
+        "delivered to {} of {} sinks: {}",
+        delivered,
+        deliveries.len(),
+        statuses.join(", ")
+    )
+}
+
+/// Runs a command with the output on its stdin
+fn pipe(mut command: Command, name: &str, output: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("couldn't run '{}': {}", name, e))?;
+    // Taken so stdin is closed before waiting, otherwise the command never sees the end of it
+    let write_result = child.stdin.take().unwrap().write_all(output);
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("'{}' exited with {}", name, status).into());
+    }
+    match write_result {
+        // A command may well succeed without reading everything
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+            Err(format!("couldn't write to '{}': {}", name, e).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The program that copies its stdin to the clipboard on this platform
+fn clipboard_command() -> (&'static str, &'static [&'static str]) {
+    if cfg!(windows) {
+        ("clip", &[])
+    } else if cfg!(target_os = "macos") {
+        ("pbcopy", &[])
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        ("wl-copy", &[])
+    } else {
+        ("xclip", &["-selection", "clipboard"])
+    }
+}
+
+impl FromStr for Sink {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (target, format) = match s.rfind(FORMAT) {
+            Some(i) => (&s[..i], Some(&s[i + FORMAT.len()..])),
+            None => (s, None),
+        };
+        if let Some(format) = format {
+            if !OutputMode::NAMES.contains(&format) {
+                return Err(format!(
+                    "invalid format '{}' in '{}', expected one of: {}",
+                    format,
+                    s,
+                    OutputMode::NAMES.join(", ")
+                ));
+            }
+        }
+
+        let target = match target.split_once('=') {
+            None if target == "stdout" => Target::Stdout,
+            None if target == "clipboard" => Target::Clipboard,
+            Some(("file", path)) if !path.is_empty() => Target::File(path.into()),
+            Some(("command", command)) if !command.is_empty() => {
+                Target::Command(command.to_owned())
+            }
+            Some(("webhook", _)) => {
+                return Err(
+                    "webhook sinks aren't supported, post the output with a command sink instead, e.g. 'command=curl --data-binary @- URL'"
+                        .to_owned(),
+                )
+            }
+            _ => {
+                return Err(format!(
+                    "invalid sink '{}', expected stdout, file=PATH, command=COMMAND or clipboard, optionally followed by :format=NAME",
+                    s
+                ))
+            }
+        };
+        Ok(Sink {
+            target,
+            format: format.map(str::to_owned),
+        })
+    }
+}
+
+/// Validates an output sink argument for clap
+pub fn sink_validate(value: String) -> Result<(), String> {
+    value.parse::<Sink>().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sinks() {
+        assert_eq!(
+            "stdout".parse(),
+            Ok(Sink {
+                target: Target::Stdout,
+                format: None
+            })
+        );
+        assert_eq!(
+            "file=picks.md:format=markdown".parse(),
+            Ok(Sink {
+                target: Target::File("picks.md".into()),
+                format: Some("markdown".to_owned())
+            })
+        );
+        assert_eq!(
+            "command=jq -c '.[]':format=json".parse::<Sink>().unwrap(),
+            Sink {
+                target: Target::Command("jq -c '.[]'".to_owned()),
+                format: Some("json".to_owned())
+            }
+        );
+        assert!("file=".parse::<Sink>().is_err());
+        assert!("clipboard:format=xml".parse::<Sink>().is_err());
+        assert!("webhook=https://example.com".parse::<Sink>().is_err());
+        assert!("printer".parse::<Sink>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_sink() {
+        let sink: Sink = "command=grep -q Tank".parse().unwrap();
+        assert!(sink.deliver(b"\"Tank!\" by The Seatbelts\n").is_ok());
+        assert!(sink.deliver(b"\"Rush\" by The Seatbelts\n").is_err());
+    }
+
+    #[test]
+    fn summaries() {
+        let stdout: Sink = "stdout".parse().unwrap();
+        let file: Sink = "file=picks.md:format=markdown".parse().unwrap();
+        let command: Sink = "command=curl --data-binary @- https://example.com"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            summary(&[
+                (&stdout, Delivery::Delivered),
+                (&command, Delivery::Failed),
+                (&file, Delivery::Skipped),
+            ]),
+            "delivered to 1 of 3 sinks: stdout delivered, command=curl --data-binary @- https://example.com failed, file=picks.md skipped"
+        );
+        assert_eq!("retry".parse(), Ok(SinkFailure::Retry));
+        assert!("ignore".parse::<SinkFailure>().is_err());
+    }
+
+    #[test]
+    fn formats_of_paths() {
+        assert_eq!(format_of_path(Path::new("picks.csv")), Some("csv"));
+        assert_eq!(format_of_path(Path::new("log/PICKS.JSONL")), Some("ndjson"));
+        assert_eq!(format_of_path(Path::new("night.m3u8")), Some("m3u"));
+        assert_eq!(format_of_path(Path::new("picks.txt")), None);
+        assert_eq!(format_of_path(Path::new("picks")), None);
+        assert_eq!(format_of_path(Path::new("picks.md")), Some("markdown"));
+    }
+}
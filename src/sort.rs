@@ -0,0 +1,135 @@
+//! Sorting the results before they are output, with `--sort`
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::normalize::Normalization;
+use crate::selection::Pick;
+use crate::theme::Theme;
+use crate::ShowId;
+
+/// What the results are sorted by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    /// The title of the song, ignoring case and width
+    Song,
+    /// The title of the show, ignoring case and width
+    Show,
+    /// Openings, then endings, then other soundtrack
+    Type,
+}
+
+impl SortKey {
+    pub const NAMES: &'static [&'static str] = &["song", "show", "type"];
+
+    /// Sorts the picks, or sorts them backwards with `reverse`, picks that are equal stay in the
+    /// order they were selected in either way
+    pub fn sort(self, picks: &mut [Pick], reverse: bool) {
+        picks.sort_by(|a, b| {
+            let ordering = self.compare(a, b);
+            if reverse {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+
+    fn compare(self, a: &Pick, b: &Pick) -> Ordering {
+        match self {
+            Self::Song => {
+                let title =
+                    |pick: &Pick| Normalization::SORTING.apply(Theme::parse(pick.song).title);
+                title(a).cmp(&title(b))
+            }
+            Self::Show => {
+                let title = |pick: &Pick| Normalization::SORTING.apply(&pick.show.title);
+                title(a).cmp(&title(b))
+            }
+            Self::Type => {
+                let rank = |pick: &Pick| match pick.show.song_type(pick.song) {
+                    "OP" => 0,
+                    "ED" => 1,
+                    _ => 2,
+                };
+                rank(a).cmp(&rank(b))
+            }
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "song" => Ok(Self::Song),
+            "show" => Ok(Self::Show),
+            "type" => Ok(Self::Type),
+            _ => Err(format!(
+                "invalid sort key '{}', expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Moves the picks of every show together, after the first pick of the show, otherwise the
+/// picks keep their order
+pub fn group_by_show(picks: &mut [Pick]) {
+    let mut firsts: HashMap<ShowId, usize> = HashMap::with_capacity(picks.len());
+    for (i, pick) in picks.iter().enumerate() {
+        firsts.entry(pick.show.id.clone()).or_insert(i);
+    }
+    picks.sort_by_key(|pick| firsts[&pick.show.id]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::fixtures;
+    use crate::Show;
+
+    #[test]
+    fn sorting() {
+        let bebop = Show {
+            id: 1usize.into(),
+            title: "Cowboy Bebop".to_owned(),
+            opening_themes: vec!["\"Tank!\" by The Seatbelts".to_owned()],
+            ending_themes: vec!["\"The Real Folk Blues\" by The Seatbelts".to_owned()],
+            ..Default::default()
+        };
+        let ghoul = Show {
+            id: 3usize.into(),
+            title: "tokyo Ghoul".to_owned(),
+            opening_themes: vec!["#1: \"unravel\" by TK".to_owned()],
+            ..Default::default()
+        };
+        let mut picks = vec![
+            fixtures::pick(&bebop.ending_themes[0], &bebop),
+            fixtures::pick(&ghoul.opening_themes[0], &ghoul),
+            fixtures::pick(&bebop.opening_themes[0], &bebop),
+        ];
+        let songs = |picks: &[Pick]| -> Vec<String> {
+            picks
+                .iter()
+                .map(|pick| Theme::parse(pick.song).title.to_owned())
+                .collect()
+        };
+
+        SortKey::Song.sort(&mut picks, false);
+        assert_eq!(songs(&picks), ["Tank!", "The Real Folk Blues", "unravel"]);
+        // Equal picks keep their order, also in reverse
+        SortKey::Show.sort(&mut picks, true);
+        assert_eq!(songs(&picks), ["unravel", "Tank!", "The Real Folk Blues"]);
+        SortKey::Type.sort(&mut picks, true);
+        assert_eq!(songs(&picks), ["The Real Folk Blues", "unravel", "Tank!"]);
+
+        assert!("artist".parse::<SortKey>().is_err());
+
+        group_by_show(&mut picks);
+        assert_eq!(songs(&picks), ["The Real Folk Blues", "Tank!", "unravel"]);
+    }
+}
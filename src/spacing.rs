@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use log::warn;
+
+use crate::normalize::Normalization;
+use crate::selection::Pick;
+use crate::theme::Theme;
+
+/// The attribute neighbouring picks should not share
+pub enum Adjacency {
+    Artist,
+    Franchise,
+    Show,
+}
+
+impl FromStr for Adjacency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "artist" => Ok(Self::Artist),
+            "franchise" => Ok(Self::Franchise),
+            "show" => Ok(Self::Show),
+            _ => Err(format!("unknown attribute '{}'", s)),
+        }
+    }
+}
+
+impl Adjacency {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Artist => "artist",
+            Self::Franchise => "franchise",
+            Self::Show => "show",
+        }
+    }
+
+    /// The value compared between neighbours, picks without one never conflict
+    pub(crate) fn key(&self, pick: &Pick) -> Option<String> {
+        match self {
+            Self::Artist => Theme::parse(pick.song)
+                .artist
+                .map(|a| Normalization::MATCHING.apply(a)),
+            Self::Franchise => pick
+                .show
+                .franchise
+                .as_ref()
+                .map(|f| Normalization::MATCHING.apply(f)),
+            Self::Show => Some(pick.show.id.to_string()),
+        }
+    }
+}
+
+/// Reorders the picks so that no two neighbours share the attribute, if possible
+///
+/// Picks keep their relative order as much as possible. If there is no valid order a warning is
+/// logged and the remaining conflicts are left in place.
+pub fn spread_out<'a>(picks: Vec<Pick<'a>>, by: &Adjacency) -> Vec<Pick<'a>> {
+    let keys: Vec<Option<String>> = picks.iter().map(|p| by.key(p)).collect();
+    let mut remaining: HashMap<&String, usize> = HashMap::new();
+    for key in keys.iter().flatten() {
+        *remaining.entry(key).or_insert(0) += 1;
+    }
+
+    let mut slots: Vec<Option<Pick>> = picks.into_iter().map(Some).collect();
+    let mut order = Vec::with_capacity(slots.len());
+    let mut previous: Option<&String> = None;
+    let mut conflicted = false;
+    while order.len() < slots.len() {
+        // Prefer the pick whose attribute is the most common of the ones left, otherwise it
+        // might run out of other picks to be placed between
+        let allowed = (0..slots.len())
+            .filter(|i| !order.contains(i))
+            .filter(|&i| keys[i].is_none() || keys[i].as_ref() != previous)
+            .max_by_key(|&i| {
+                let count = keys[i].as_ref().map_or(0, |key| remaining[key]);
+                // Earlier picks win ties
+                (count, std::cmp::Reverse(i))
+            });
+
+        let next = allowed.unwrap_or_else(|| {
+            conflicted = true;
+            (0..slots.len()).find(|i| !order.contains(i)).unwrap()
+        });
+
+        if let Some(key) = &keys[next] {
+            *remaining.get_mut(key).unwrap() -= 1;
+        }
+        previous = keys[next].as_ref();
+        order.push(next);
+    }
+
+    if conflicted {
+        warn!("couldn't order the results so that no two neighbours share the same attribute");
+    }
+
+    order
+        .into_iter()
+        .map(|i| slots[i].take().unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::fixtures;
+    use crate::Show;
+
+    fn show(id: usize, franchise: Option<&str>) -> Show {
+        Show {
+            id: id.into(),
+            title: format!("Show {}", id),
+            franchise: franchise.map(|f| f.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    fn franchises(picks: &[Pick]) -> Vec<Option<String>> {
+        picks.iter().map(|p| p.show.franchise.clone()).collect()
+    }
+
+    #[test]
+    fn spreads_out_franchises() {
+        let song = "\"Song\" by Artist".to_owned();
+        let shows = [
+            show(1, Some("A")),
+            show(2, Some("A")),
+            show(3, Some("A")),
+            show(4, Some("B")),
+            show(5, None),
+        ];
+        let picks = shows
+            .iter()
+            .map(|show| fixtures::pick(&song, show))
+            .collect();
+        let picks = spread_out(picks, &Adjacency::Franchise);
+        let a = Some("A".to_owned());
+        assert_eq!(franchises(&picks)[0], a);
+        assert_eq!(franchises(&picks)[2], a);
+        assert_eq!(franchises(&picks)[4], a);
+
+        // Not possible, but every pick is still there
+        let picks = shows[..3]
+            .iter()
+            .map(|show| fixtures::pick(&song, show))
+            .collect();
+        let picks = spread_out(picks, &Adjacency::Franchise);
+        assert_eq!(picks.len(), 3);
+    }
+
+    #[test]
+    fn spreads_out_artists() {
+        let songs = [
+            "\"One\" by Artist".to_owned(),
+            "\"Two\" by artist".to_owned(),
+            "\"Three\" by Someone Else".to_owned(),
+        ];
+        let show = show(1, None);
+        let picks = songs
+            .iter()
+            .map(|song| fixtures::pick(song, &show))
+            .collect();
+        let picks = spread_out(picks, &Adjacency::Artist);
+        let titles: Vec<&str> = picks.iter().map(|p| Theme::parse(p.song).title).collect();
+        assert_eq!(titles, vec!["One", "Three", "Two"]);
+    }
+}
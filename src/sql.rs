@@ -0,0 +1,70 @@
+//! SQL `INSERT` statements for `--sql`, which SQLite and PostgreSQL both accept
+
+/// The table rows are inserted into without a name for it
+pub const DEFAULT_TABLE: &str = "picks";
+
+/// A value of a row
+#[derive(Debug, PartialEq)]
+pub enum Value<'a> {
+    Integer(u64),
+    Text(&'a str),
+    Null,
+}
+
+/// The statement that inserts a row, with a value for each column
+pub fn insert(table: &str, columns: &[&str], values: &[Value]) -> String {
+    let columns: Vec<String> = columns.iter().map(|c| identifier(c)).collect();
+    let values: Vec<String> = values
+        .iter()
+        .map(|value| match value {
+            Value::Integer(n) => n.to_string(),
+            Value::Text(text) => format!("'{}'", text.replace('\'', "''")),
+            Value::Null => "NULL".to_owned(),
+        })
+        .collect();
+    format!(
+        "INSERT INTO {} ({}) VALUES ({});",
+        identifier(table),
+        columns.join(", "),
+        values.join(", ")
+    )
+}
+
+/// Quotes a table or column name, so any name works and none is a keyword
+fn identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The column name of a header, e.g. `song` for `Song`
+pub fn column_name(header: &str) -> String {
+    header
+        .to_lowercase()
+        .replace(|c: char| !c.is_alphanumeric(), "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserts() {
+        assert_eq!(
+            insert(
+                DEFAULT_TABLE,
+                &["number", "song", "url"],
+                &[
+                    Value::Integer(1),
+                    Value::Text("\"Tank!\" by The Seatbelts"),
+                    Value::Null
+                ]
+            ),
+            r#"INSERT INTO "picks" ("number", "song", "url") VALUES (1, '"Tank!" by The Seatbelts', NULL);"#
+        );
+        assert_eq!(
+            insert("weekly \"picks\"", &["show"], &[Value::Text("JoJo's")]),
+            r#"INSERT INTO "weekly ""picks""" ("show") VALUES ('JoJo''s');"#
+        );
+        assert_eq!(column_name("Song"), "song");
+        assert_eq!(column_name("last.fm"), "last_fm");
+    }
+}
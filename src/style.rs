@@ -0,0 +1,365 @@
+//! How readable output looks, usually set once in the config file
+//!
+//! `--readable-format` replaces the line of a pick with a template (see `crate::template`), which
+//! can also use `{favorite}`: a star for shows in the favorites. Lines are colored by the type of
+//! theme when writing to a terminal, as `--color` and `--type-color` choose, and the show title of
+//! the default line is dimmed.
+
+use std::collections::HashSet;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+
+use clap::ArgMatches;
+
+use crate::selection::Pick;
+use crate::template::Template;
+use crate::theme::SongLang;
+use crate::{favorites, ShowId};
+
+/// What `{favorite}` is replaced with for favorites
+const STAR: &str = "★";
+
+/// The colors of the types of theme that `--type-color` doesn't set
+const DEFAULT_COLORS: &[TypeColor] = &[
+    TypeColor {
+        song_type: "OP",
+        color: Color::Green,
+    },
+    TypeColor {
+        song_type: "ED",
+        color: Color::Blue,
+    },
+    TypeColor {
+        song_type: "ST",
+        color: Color::Yellow,
+    },
+];
+
+/// When output is colored, as set with `--color`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Coloring {
+    /// Only on a terminal, and only if `NO_COLOR` isn't set
+    Auto,
+    Always,
+    Never,
+}
+
+impl Coloring {
+    pub const NAMES: &'static [&'static str] = &["auto", "always", "never"];
+
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        match matches.value_of("color") {
+            Some("always") => Self::Always,
+            Some("never") => Self::Never,
+            // Validated by clap
+            _ => Self::Auto,
+        }
+    }
+
+    /// Checks if output is colored, `is_terminal` being whether it goes to a terminal
+    pub fn is_enabled(self, is_terminal: bool) -> bool {
+        match self {
+            Self::Auto => is_terminal && std::env::var_os("NO_COLOR").is_none(),
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ReadableStyle {
+    format: Option<Template>,
+    colors: Vec<TypeColor>,
+    favorites: HashSet<ShowId>,
+    song_lang: SongLang,
+}
+
+impl ReadableStyle {
+    /// Fails if the favorites, which are only read for formats with `{favorite}`, can't be read
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self, String> {
+        let song_lang = SongLang::from_matches(matches);
+        // Validated by clap
+        let format: Option<Template> = matches.value_of("readable-format").map(|format| {
+            format
+                .parse::<Template>()
+                .unwrap()
+                .with_song_lang(song_lang)
+        });
+        let mut favorites = HashSet::new();
+        let wants_favorites = matches
+            .value_of("readable-format")
+            .is_some_and(|format| format.contains("{favorite}"));
+        if let (true, Some(dir)) = (wants_favorites, matches.value_of("data-dir")) {
+            let path = std::path::Path::new(dir).join(favorites::FILE_NAME);
+            favorites = favorites::load(&path)
+                .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?
+                .into_iter()
+                .map(|entry| entry.id)
+                .collect();
+        }
+
+        // Colors are only for people, not for pipes
+        let colors = if Coloring::from_matches(matches).is_enabled(io::stdout().is_terminal()) {
+            let mut colors: Vec<TypeColor> = matches
+                .values_of("type-color")
+                // Validated by clap, later values win
+                .map(|values| values.rev().map(|v| v.parse().unwrap()).collect())
+                .unwrap_or_default();
+            colors.extend_from_slice(DEFAULT_COLORS);
+            colors
+        } else {
+            vec![]
+        };
+
+        Ok(ReadableStyle {
+            format,
+            colors,
+            favorites,
+            song_lang,
+        })
+    }
+
+    /// The same style without colors
+    pub fn without_colors(self) -> Self {
+        ReadableStyle {
+            colors: vec![],
+            ..self
+        }
+    }
+
+    /// Checks if the line of a pick is a `--readable-format`
+    pub fn has_format(&self) -> bool {
+        self.format.is_some()
+    }
+
+    /// The line of a pick, without the newline
+    ///
+    /// `extra` are the values of any columns beyond the default ones, which are appended unless
+    /// they're empty, like the url of a show without one.
+    pub fn render(&self, number: usize, pick: &Pick, extra: &[String]) -> String {
+        self.render_line(number, pick, extra, true)
+    }
+
+    /// The line of a pick under its show with `--group-by-show`, which leaves the show out
+    /// unless there is a format
+    pub fn render_under_show(&self, number: usize, pick: &Pick, extra: &[String]) -> String {
+        self.render_line(number, pick, extra, false)
+    }
+
+    fn render_line(&self, number: usize, pick: &Pick, extra: &[String], with_show: bool) -> String {
+        let song_type = pick.show.song_type(pick.song);
+        let song = self.song_lang.apply(pick.song, &pick.show.native_titles);
+        let color = self.color(pick);
+        let mut line = match &self.format {
+            Some(format) => format.render_pick_with(number, pick, |name| match name {
+                "favorite" if self.favorites.contains(&pick.show.id) => Some(STAR.to_owned()),
+                "favorite" => Some(String::new()),
+                _ => None,
+            }),
+            None if !with_show => format!("{} [{}]", song, song_type),
+            // Dimmed, then back to the normal intensity of the color of the line
+            None if color.is_some() => format!(
+                "{} [{}] from \x1b[2m{}\x1b[22m",
+                song, song_type, pick.show.title
+            ),
+            None => format!("{} [{}] from {}", song, song_type, pick.show.title),
+        };
+        for value in extra.iter().filter(|value| !value.is_empty()) {
+            line.push(' ');
+            line.push_str(value);
+        }
+        paint(line, color)
+    }
+
+    /// The line of a pick with the values of the columns chosen with `--columns`, separated by
+    /// dashes, unless there is a format, which decides on its own what to show
+    pub fn render_columns(&self, number: usize, pick: &Pick, values: &[String]) -> String {
+        if self.format.is_some() {
+            return self.render(number, pick, &[]);
+        }
+        paint(values.join(" - "), self.color(pick))
+    }
+
+    /// The color of the type of theme of a pick, if lines are colored
+    fn color(&self, pick: &Pick) -> Option<&TypeColor> {
+        let song_type = pick.show.song_type(pick.song);
+        self.colors.iter().find(|c| c.song_type == song_type)
+    }
+}
+
+fn paint(line: String, color: Option<&TypeColor>) -> String {
+    match color {
+        Some(type_color) => format!("\x1b[{}m{}\x1b[0m", type_color.color.code(), line),
+        None => line,
+    }
+}
+
+/// A terminal color
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+impl Color {
+    pub const NAMES: &'static [&'static str] =
+        &["red", "green", "yellow", "blue", "magenta", "cyan"];
+
+    /// The ANSI code of the foreground color
+    fn code(self) -> u8 {
+        match self {
+            Self::Red => 31,
+            Self::Green => 32,
+            Self::Yellow => 33,
+            Self::Blue => 34,
+            Self::Magenta => 35,
+            Self::Cyan => 36,
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "red" => Ok(Self::Red),
+            "green" => Ok(Self::Green),
+            "yellow" => Ok(Self::Yellow),
+            "blue" => Ok(Self::Blue),
+            "magenta" => Ok(Self::Magenta),
+            "cyan" => Ok(Self::Cyan),
+            _ => Err(format!(
+                "invalid color '{}', expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// A single `--type-color` value, e.g. `op=green`
+#[derive(Debug, Clone, PartialEq)]
+struct TypeColor {
+    /// As returned by `Show::song_type`
+    song_type: &'static str,
+    color: Color,
+}
+
+impl FromStr for TypeColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (song_type, color) = s.split_once('=').ok_or_else(|| {
+            format!(
+                "invalid type color '{}', expected e.g. 'op=green' or 'ost=cyan'",
+                s
+            )
+        })?;
+        let song_type = match song_type.trim().to_lowercase().as_str() {
+            "op" => "OP",
+            "ed" => "ED",
+            "ost" | "st" => "ST",
+            other => {
+                return Err(format!(
+                    "invalid type '{}' in '{}', expected op, ed or ost",
+                    other, s
+                ))
+            }
+        };
+        Ok(TypeColor {
+            song_type,
+            color: color.parse()?,
+        })
+    }
+}
+
+/// Validates a type color argument for clap
+pub fn type_color_validate(value: String) -> Result<(), String> {
+    value.parse::<TypeColor>().map(|_| ())
+}
+
+/// Validates a readable format argument for clap, which is a template of a single pick
+pub fn readable_format_validate(value: String) -> Result<(), String> {
+    if value.contains("{#picks}") || value.contains("{/picks}") {
+        return Err("a readable format is output for every pick, it can't have a {#picks} block, use --format-file for that".to_owned());
+    }
+    value.parse::<Template>().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::fixtures;
+
+    #[test]
+    fn readable_styles() {
+        let show = fixtures::bebop();
+        let pick = fixtures::opening(&show);
+
+        let plain = ReadableStyle::default();
+        assert_eq!(
+            plain.render(1, &pick, &["https://example.com".to_owned()]),
+            "\"Tank!\" by The Seatbelts [OP] from Cowboy Bebop https://example.com"
+        );
+        assert_eq!(
+            plain.render(1, &pick, &[String::new()]),
+            "\"Tank!\" by The Seatbelts [OP] from Cowboy Bebop"
+        );
+
+        let colored = ReadableStyle {
+            colors: DEFAULT_COLORS.to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(
+            colored.render(1, &pick, &[]),
+            "\x1b[32m\"Tank!\" by The Seatbelts [OP] from \x1b[2mCowboy Bebop\x1b[22m\x1b[0m"
+        );
+        assert_eq!(
+            colored.render_columns(1, &pick, &["Tank!".to_owned(), "Cowboy Bebop".to_owned()]),
+            "\x1b[32mTank! - Cowboy Bebop\x1b[0m"
+        );
+        assert!(Coloring::Always.is_enabled(false));
+        assert!(!Coloring::Never.is_enabled(true));
+        assert!(!Coloring::Auto.is_enabled(false));
+
+        let mut style = ReadableStyle {
+            format: Some("{number}. {song} ({show}){favorite}".parse().unwrap()),
+            colors: vec!["op=green".parse().unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(
+            style.render(2, &pick, &[]),
+            "\x1b[32m2. Tank! (Cowboy Bebop)\x1b[0m"
+        );
+        style.favorites.insert(1usize.into());
+        style.colors.clear();
+        assert_eq!(style.render(2, &pick, &[]), "2. Tank! (Cowboy Bebop)★");
+
+        let mut ghoul = fixtures::show("Tokyo Ghoul", "#1: \"unravel\" by TK");
+        ghoul
+            .native_titles
+            .insert("unravel".to_owned(), "アンラヴェル".to_owned());
+        let native = ReadableStyle {
+            song_lang: SongLang::Native,
+            ..Default::default()
+        };
+        assert_eq!(
+            native.render(1, &fixtures::opening(&ghoul), &[]),
+            "#1: \"アンラヴェル\" by TK [OP] from Tokyo Ghoul"
+        );
+        assert_eq!(
+            native.render_under_show(1, &fixtures::opening(&ghoul), &[]),
+            "#1: \"アンラヴェル\" by TK [OP]"
+        );
+
+        assert!(type_color_validate("ost=cyan".to_owned()).is_ok());
+        assert!(type_color_validate("op=pink".to_owned()).is_err());
+        assert!(type_color_validate("insert=red".to_owned()).is_err());
+        assert!(readable_format_validate("{#picks}{song}{/picks}".to_owned()).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+//! Tables with rounded borders, laid out by how wide their characters are on the terminal
+
+use std::str::FromStr;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// How wide characters are on the terminal, chosen with `--width-mode`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WidthMode {
+    /// Japanese, Chinese and Korean characters take two columns, everything else one
+    #[default]
+    Standard,
+    /// Like standard, but characters of ambiguous width like `…`, `☆`, `♪` and `×` take two
+    /// columns as well, like they do in terminals set up for Japanese, Chinese or Korean
+    Cjk,
+}
+
+impl WidthMode {
+    pub const NAMES: &'static [&'static str] = &["standard", "cjk"];
+
+    /// How many columns the string takes up
+    pub fn str_width(self, s: &str) -> usize {
+        match self {
+            Self::Standard => s.width(),
+            Self::Cjk => s.width_cjk(),
+        }
+    }
+
+    /// How many columns the character takes up, control characters take none
+    pub fn char_width(self, c: char) -> usize {
+        match self {
+            Self::Standard => c.width(),
+            Self::Cjk => c.width_cjk(),
+        }
+        .unwrap_or(0)
+    }
+}
+
+impl FromStr for WidthMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(Self::Standard),
+            "cjk" => Ok(Self::Cjk),
+            _ => Err(format!(
+                "invalid width mode '{}', expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// A table of rows of cells, with a line between every two rows
+///
+/// Cells are padded with a space on either side. Columns are as wide as their widest cell, but
+/// at most `max_column_width` unless a single character needs more, and longer cells wrap.
+#[derive(Debug)]
+pub struct Table {
+    rows: Vec<Vec<String>>,
+    pub max_column_width: usize,
+    pub width_mode: WidthMode,
+}
+
+impl Table {
+    pub fn new(max_column_width: usize, width_mode: WidthMode) -> Self {
+        Table {
+            rows: vec![],
+            max_column_width,
+            width_mode,
+        }
+    }
+
+    pub fn add_row<I: IntoIterator<Item = S>, S: Into<String>>(&mut self, cells: I) {
+        self.rows.push(cells.into_iter().map(Into::into).collect());
+    }
+
+    /// The whole table, every line ended by a newline
+    pub fn render(&self) -> String {
+        let widths = self.column_widths();
+        let mut out = String::new();
+        for (i, row) in self.rows.iter().enumerate() {
+            let (start, middle, end) = if i == 0 {
+                ('╭', '┬', '╮')
+            } else {
+                ('├', '┼', '┤')
+            };
+            out.push_str(&border(&widths, start, middle, end));
+            out.push_str(&self.render_row(row, &widths));
+        }
+        if !self.rows.is_empty() {
+            out.push_str(&border(&widths, '╰', '┴', '╯'));
+        }
+        out
+    }
+
+    fn column_widths(&self) -> Vec<usize> {
+        let columns = self.rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut widths = vec![0; columns];
+        let mut min_widths = vec![0; columns];
+        for row in &self.rows {
+            for (i, cell) in row.iter().enumerate() {
+                let widest_char = cell
+                    .chars()
+                    .map(|c| self.width_mode.char_width(c))
+                    .max()
+                    .unwrap_or(0);
+                min_widths[i] = min_widths[i].max(widest_char + 2);
+                let max_width = self.max_column_width.max(min_widths[i]);
+                widths[i] = max_width.min(widths[i].max(self.cell_width(cell)));
+            }
+        }
+        widths
+    }
+
+    /// The width a cell takes up without wrapping, with room for a space more on either side
+    fn cell_width(&self, cell: &str) -> usize {
+        self.wrap(cell, usize::MAX)
+            .iter()
+            .map(|line| self.width_mode.str_width(line))
+            .max()
+            .unwrap_or(0)
+            + 2
+    }
+
+    /// The lines of a cell in a column `width` wide, each with a space on either side
+    fn wrap(&self, cell: &str, width: usize) -> Vec<String> {
+        let mut lines = vec![];
+        let mut line = " ".to_owned();
+        for c in cell.chars() {
+            // Room for the character and the space after it
+            let full = self.width_mode.str_width(&line) + self.width_mode.char_width(c)
+                > width.saturating_sub(1);
+            if full || c == '\n' {
+                line.push(' ');
+                lines.push(line);
+                line = " ".to_owned();
+                if c == '\n' {
+                    continue;
+                }
+            }
+            line.push(c);
+        }
+        line.push(' ');
+        lines.push(line);
+        lines
+    }
+
+    fn render_row(&self, row: &[String], widths: &[usize]) -> String {
+        let cells: Vec<Vec<String>> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, &width)| self.wrap(cell, width))
+            .collect();
+        let height = cells.iter().map(Vec::len).max().unwrap_or(0);
+        let mut out = String::new();
+        for i in 0..height {
+            for (column, &width) in widths.iter().enumerate() {
+                out.push('│');
+                let line = cells.get(column).and_then(|lines| lines.get(i));
+                let line = line.map_or("", String::as_str);
+                out.push_str(line);
+                let padding = width.saturating_sub(self.width_mode.str_width(line));
+                out.push_str(&" ".repeat(padding));
+            }
+            out.push_str("│\n");
+        }
+        out
+    }
+}
+
+/// A horizontal line of the table between `start` and `end`, with `middle` between columns
+fn border(widths: &[usize], start: char, middle: char, end: char) -> String {
+    let lines: Vec<String> = widths.iter().map(|&width| "─".repeat(width)).collect();
+    format!("{}{}{}\n", start, lines.join(&middle.to_string()), end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendering() {
+        let mut table = Table::new(12, WidthMode::Standard);
+        table.add_row(vec!["Song", "Type"]);
+        table.add_row(vec!["\"Tank!\" by The Seatbelts", "OP"]);
+        assert_eq!(
+            table.render(),
+            "╭────────────┬────────╮\n\
+             │ Song       │ Type   │\n\
+             ├────────────┼────────┤\n\
+             │ \"Tank!\" by │ OP     │\n\
+             │  The Seatb │        │\n\
+             │ elts       │        │\n\
+             ╰────────────┴────────╯\n"
+        );
+    }
+
+    #[test]
+    fn width_modes() {
+        // Kana take two columns either way, stars only on CJK terminals
+        let cells = vec!["アンラヴェル", "☆"];
+        let mut standard = Table::new(usize::MAX, WidthMode::Standard);
+        standard.add_row(cells.clone());
+        let mut cjk = Table::new(usize::MAX, WidthMode::Cjk);
+        cjk.add_row(cells);
+        assert_eq!(
+            standard.render().lines().next(),
+            Some("╭────────────────┬─────╮")
+        );
+        assert_eq!(
+            cjk.render().lines().next(),
+            Some("╭────────────────┬──────╮")
+        );
+
+        // Wide characters wrap before they would stick out of their column
+        for mode in [WidthMode::Standard, WidthMode::Cjk] {
+            let mut table = Table::new(7, mode);
+            table.add_row(vec!["アアア", "x"]);
+            table.add_row(vec!["aアbアcアd…e☆", "OP"]);
+            let rendered = table.render();
+            let border: Vec<usize> = rendered
+                .lines()
+                .next()
+                .unwrap()
+                .trim_matches(|c| c == '╭' || c == '╮')
+                .split('┬')
+                .map(|line| line.chars().count())
+                .collect();
+            for row in rendered.lines().filter(|line| line.starts_with('│')) {
+                let cells: Vec<usize> = row
+                    .trim_matches('│')
+                    .split('│')
+                    .map(|cell| mode.str_width(cell))
+                    .collect();
+                assert_eq!(cells, border, "{}", rendered);
+            }
+        }
+
+        assert_eq!(WidthMode::Cjk.str_width("…"), 2);
+        assert_eq!(WidthMode::Standard.str_width("…"), 1);
+        assert!("wide".parse::<WidthMode>().is_err());
+    }
+}
@@ -0,0 +1,271 @@
+//! Output templates for `--format-file` (also called `--template`) and `--format`
+//!
+//! A template is text with placeholders in braces. The part between `{#picks}` and `{/picks}` is
+//! output once for every pick, the parts before and after it once. Without a `{#picks}` block
+//! the whole template is output for every pick.
+//!
+//! Within the block `{song}`, `{artist}`, `{show}`, `{id}`, `{type}`, the other fields of the
+//! show like `{url}` or `{genres}`, `{custom.FIELD}` (a custom field of the show) and `{number}`
+//! (the 1-based position of the pick) can be used, and the `Totals` of all picks before and after
+//! it. Any other braces are output as they are, so templates can be LaTeX or contain CSS.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use crate::links::{is_placeholder, placeholder_value};
+use crate::selection::Pick;
+use crate::theme::SongLang;
+use crate::ShowId;
+
+const OPEN: &str = "{#picks}";
+const CLOSE: &str = "{/picks}";
+
+#[derive(Debug, PartialEq)]
+pub struct Template {
+    header: String,
+    block: String,
+    footer: String,
+    /// The script of `{song}`
+    song_lang: SongLang,
+}
+
+impl Template {
+    /// A template of a single line for every pick, as given to `--format`
+    pub fn line(format: &str) -> Self {
+        Template {
+            header: String::new(),
+            block: format!("{}\n", format),
+            footer: String::new(),
+            song_lang: SongLang::Both,
+        }
+    }
+
+    /// The same template with `{song}` in the script of `song_lang`
+    pub fn with_song_lang(self, song_lang: SongLang) -> Self {
+        Template { song_lang, ..self }
+    }
+
+    /// The text before the picks
+    pub fn render_header(&self, totals: &Totals) -> String {
+        fill(&self.header, |name| totals.value(name))
+    }
+
+    /// The text of the pick at the 1-based position `number`
+    pub fn render_pick(&self, number: usize, pick: &Pick) -> String {
+        self.render_pick_with(number, pick, |_| None)
+    }
+
+    /// Like `render_pick`, with more placeholders that `extra` knows the values of
+    pub fn render_pick_with(
+        &self,
+        number: usize,
+        pick: &Pick,
+        extra: impl Fn(&str) -> Option<String>,
+    ) -> String {
+        fill(&self.block, |name| match name {
+            "number" => Some(number.to_string()),
+            _ if is_placeholder(name) => Some(placeholder_value(name, pick, self.song_lang)),
+            _ => extra(name),
+        })
+    }
+
+    /// The text after the picks
+    pub fn render_footer(&self, totals: &Totals) -> String {
+        fill(&self.footer, |name| totals.value(name))
+    }
+}
+
+/// What all picks add up to, as `{count}`, `{shows}`, `{openings}`, `{endings}` and
+/// `{soundtracks}` before and after the picks
+#[derive(Debug, Default)]
+pub struct Totals {
+    pub count: usize,
+    shows: HashSet<ShowId>,
+    openings: usize,
+    endings: usize,
+    soundtracks: usize,
+}
+
+impl Totals {
+    pub fn of(picks: &[Pick]) -> Self {
+        let mut totals = Self::default();
+        for pick in picks {
+            totals.add(pick);
+        }
+        totals
+    }
+
+    pub fn add(&mut self, pick: &Pick) {
+        self.count += 1;
+        self.shows.insert(pick.show.id.clone());
+        match pick.show.song_type(pick.song) {
+            "OP" => self.openings += 1,
+            "ED" => self.endings += 1,
+            _ => self.soundtracks += 1,
+        }
+    }
+
+    /// The line of `--summary`, e.g. `3 picks from 2 shows: 2 OP, 1 ED, 0 ST`
+    pub fn summary(&self) -> String {
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        format!(
+            "{} pick{} from {} show{}: {} OP, {} ED, {} ST",
+            self.count,
+            plural(self.count),
+            self.shows.len(),
+            plural(self.shows.len()),
+            self.openings,
+            self.endings,
+            self.soundtracks
+        )
+    }
+
+    fn value(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "count" => self.count,
+            "shows" => self.shows.len(),
+            "openings" => self.openings,
+            "endings" => self.endings,
+            "soundtracks" => self.soundtracks,
+            _ => return None,
+        };
+        Some(value.to_string())
+    }
+}
+
+/// Replaces every `{name}` that `value` knows, in a single pass so values are never replaced
+/// themselves
+fn fill(text: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let replaced = rest
+            .find('}')
+            .and_then(|end| value(&rest[1..end]).map(|value| (end, value)));
+        match replaced {
+            Some((end, value)) => {
+                out.push_str(&value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let open = s.find(OPEN);
+        let close = s.find(CLOSE);
+        let (open, close) = match (open, close) {
+            (None, None) => {
+                return Ok(Template {
+                    header: String::new(),
+                    block: s.to_owned(),
+                    footer: String::new(),
+                    song_lang: SongLang::Both,
+                })
+            }
+            (Some(open), Some(close)) if open < close => (open, close),
+            (Some(_), None) => return Err(format!("{} is never closed with {}", OPEN, CLOSE)),
+            _ => return Err(format!("{} without a {} before it", CLOSE, OPEN)),
+        };
+        if s[open + OPEN.len()..].contains(OPEN) || s[close + CLOSE.len()..].contains(CLOSE) {
+            return Err(format!("only one {} block is supported", OPEN));
+        }
+
+        // Tags on a line of their own don't leave an empty line behind
+        Ok(Template {
+            header: s[..open].to_owned(),
+            block: strip_newline(&s[open + OPEN.len()..close]).to_owned(),
+            footer: strip_newline(&s[close + CLOSE.len()..]).to_owned(),
+            song_lang: SongLang::Both,
+        })
+    }
+}
+
+fn strip_newline(s: &str) -> &str {
+    s.strip_prefix("\r\n")
+        .or_else(|| s.strip_prefix('\n'))
+        .unwrap_or(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::fixtures;
+    use crate::Show;
+
+    #[test]
+    fn template_rendering() {
+        let template: Template = "\\begin{enumerate} % {count} themes\n{#picks}\n  \\item {song} ({type}) from \\emph{{show}}\n{/picks}\n\\end{enumerate}\n"
+            .parse()
+            .unwrap();
+        let show = fixtures::bebop();
+        let pick = fixtures::opening(&show);
+        let totals = Totals::of(&[Pick { ..pick }, Pick { ..pick }]);
+        let mut out = template.render_header(&totals);
+        out.push_str(&template.render_pick(1, &pick));
+        out.push_str(&template.render_pick(2, &pick));
+        out.push_str(&template.render_footer(&totals));
+        let totals_template: Template =
+            "{shows} shows, {openings} OP, {endings} ED{#picks}{/picks}"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            totals_template.render_header(&totals),
+            "1 shows, 2 OP, 0 ED"
+        );
+        assert_eq!(
+            out,
+            "\\begin{enumerate} % 2 themes\n  \\item Tank! (OP) from \\emph{Cowboy Bebop}\n  \\item Tank! (OP) from \\emph{Cowboy Bebop}\n\\end{enumerate}\n"
+        );
+
+        // Without a block every pick gets the whole template
+        let template: Template = "{number}: {artist} {{id}}\n".parse().unwrap();
+        assert_eq!(template.render_pick(3, &pick), "3: The Seatbelts {1}\n");
+
+        let show_with_fields = Show {
+            title: "Cowboy Bebop".to_owned(),
+            opening_themes: show.opening_themes.clone(),
+            genres: vec!["Action".to_owned(), "Sci-Fi".to_owned()],
+            members: Some(1_800_000),
+            ..Default::default()
+        };
+        let pick = Pick {
+            show: &show_with_fields,
+            ..pick
+        };
+        let template = Template::line("{song} [{type}] - {show} ({genres}; {members}; {rating})");
+        assert_eq!(
+            template.render_pick(1, &pick),
+            "Tank! [OP] - Cowboy Bebop (Action, Sci-Fi; 1800000; )\n"
+        );
+
+        let ghoul = fixtures::show("Tokyo Ghoul", "#1: \"unravel (アンラヴェル)\" by TK");
+        let template = Template::line("{song} by {artist}");
+        assert_eq!(
+            template.render_pick(1, &fixtures::opening(&ghoul)),
+            "unravel (アンラヴェル) by TK\n"
+        );
+        let template = template.with_song_lang(SongLang::Native);
+        assert_eq!(
+            template.render_pick(1, &fixtures::opening(&ghoul)),
+            "アンラヴェル by TK\n"
+        );
+
+        assert!("{#picks} never closed".parse::<Template>().is_err());
+        assert!("{/picks}{#picks}".parse::<Template>().is_err());
+        assert!("{#picks}{/picks}{#picks}{/picks}"
+            .parse::<Template>()
+            .is_err());
+    }
+}
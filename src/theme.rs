@@ -0,0 +1,266 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use clap::ArgMatches;
+
+/// The parts of a theme string as formatted by MAL, e.g. `#1: "unravel" by TK from Ling tosite sigure (eps 1-11)`
+///
+/// Every part except the title is optional, anything that can't be recognised ends up in the title.
+#[derive(Debug, PartialEq)]
+pub struct Theme<'a> {
+    /// The `#N` numbering of the theme within its type
+    pub index: Option<usize>,
+    pub title: &'a str,
+    pub artist: Option<&'a str>,
+    /// The episode range, without the surrounding parentheses
+    pub episodes: Option<&'a str>,
+}
+
+impl<'a> Theme<'a> {
+    pub fn parse(theme: &'a str) -> Self {
+        let mut rest = theme.trim();
+
+        let mut index = None;
+        if let Some(numbered) = rest.strip_prefix('#') {
+            if let Some((number, after)) = numbered.split_once(':') {
+                if let Ok(number) = number.trim().parse() {
+                    index = Some(number);
+                    rest = after.trim_start();
+                }
+            }
+        }
+
+        // The artist and the episodes are only looked for after a quoted title, which can have
+        // " by " or parentheses of its own
+        let mut quoted = None;
+        if let Some(after_quote) = rest.strip_prefix('"') {
+            // Quotes within the title are followed by something else
+            let end = after_quote.match_indices('"').map(|(i, _)| i).find(|&i| {
+                let after = &after_quote[i + 1..];
+                after.is_empty() || after.starts_with(" by ") || after.starts_with(" (ep")
+            });
+            if let Some(end) = end {
+                quoted = Some(&after_quote[..end]);
+                rest = &after_quote[end + 1..];
+            }
+        }
+
+        let mut episodes = None;
+        if rest.ends_with(')') {
+            let start = rest.rfind(" (ep").filter(|&start| {
+                let after = &rest[start + 4..];
+                after.starts_with(' ') || after.starts_with("s ")
+            });
+            if let Some(start) = start {
+                episodes = Some(&rest[start + 2..rest.len() - 1]);
+                rest = rest[..start].trim_end();
+            }
+        }
+
+        let (title, artist) = match quoted {
+            Some(title) => (title, rest.trim_start().strip_prefix("by ").map(str::trim)),
+            None => {
+                let (title, artist) = match rest.rfind(" by ") {
+                    Some(split) => (&rest[..split], Some(rest[split + 4..].trim())),
+                    None => (rest, None),
+                };
+                let title = title.trim();
+                let title = title
+                    .strip_prefix('"')
+                    .and_then(|t| t.strip_suffix('"'))
+                    .unwrap_or(title);
+                (title, artist)
+            }
+        };
+
+        Theme {
+            index,
+            title,
+            artist: artist.filter(|a| !a.is_empty()),
+            episodes,
+        }
+    }
+
+    /// Splits a title like `Seijatachi (聖者たち)` into the romanized and the native title
+    ///
+    /// Only a parenthesized part with letters outside of ASCII counts as the native title.
+    pub fn title_variants(&self) -> (&'a str, Option<&'a str>) {
+        if let Some(start) = self.title.rfind(" (").filter(|_| self.title.ends_with(')')) {
+            let native = &self.title[start + 2..self.title.len() - 1];
+            if native.chars().any(|c| c.is_alphabetic() && !c.is_ascii()) {
+                return (self.title[..start].trim_end(), Some(native));
+            }
+        }
+        (self.title, None)
+    }
+}
+
+/// The script song titles are shown in, where the theme has both
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SongLang {
+    /// As the theme is written, usually `romaji (native)`
+    #[default]
+    Both,
+    Romaji,
+    Native,
+}
+
+impl SongLang {
+    pub const NAMES: [&'static str; 3] = ["both", "romaji", "native"];
+
+    /// The script chosen with `--song-lang`, both unless it's given
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        // Validated by clap
+        matches
+            .value_of("song-lang")
+            .map_or(Self::Both, |lang| lang.parse().unwrap())
+    }
+
+    /// Rewrites the title of a theme string in this script
+    ///
+    /// `native_titles` has native titles by romanized title, for themes that only have the
+    /// latter. Titles without a known native variant are always left as they are.
+    pub fn apply(&self, song: &str, native_titles: &BTreeMap<String, String>) -> String {
+        let theme = Theme::parse(song);
+        let (romaji, native) = theme.title_variants();
+        let native = native.or_else(|| native_titles.get(romaji).map(String::as_str));
+        let title = match (self, native) {
+            (Self::Romaji, Some(_)) => romaji.to_owned(),
+            (Self::Native, Some(native)) => native.to_owned(),
+            (Self::Both, Some(native)) if native != romaji => format!("{} ({})", romaji, native),
+            _ => return song.to_owned(),
+        };
+        song.replacen(theme.title, &title, 1)
+    }
+}
+
+impl FromStr for SongLang {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "both" => Ok(Self::Both),
+            "romaji" => Ok(Self::Romaji),
+            "native" => Ok(Self::Native),
+            _ => Err(format!(
+                "invalid song language '{}', expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn theme_parsing() {
+        assert_eq!(
+            Theme::parse("#1: \"unravel\" by TK from Ling tosite sigure (eps 1-11)"),
+            Theme {
+                index: Some(1),
+                title: "unravel",
+                artist: Some("TK from Ling tosite sigure"),
+                episodes: Some("eps 1-11"),
+            }
+        );
+        assert_eq!(
+            Theme::parse("\"Seijatachi (聖者たち)\" by People In The Box"),
+            Theme {
+                index: None,
+                title: "Seijatachi (聖者たち)",
+                artist: Some("People In The Box"),
+                episodes: None,
+            }
+        );
+        assert_eq!(
+            Theme::parse("#12: \"Stand by Me\" by Someone (ep 5)"),
+            Theme {
+                index: Some(12),
+                title: "Stand by Me",
+                artist: Some("Someone"),
+                episodes: Some("ep 5"),
+            }
+        );
+        // Only what comes after the title is the artist and the episodes
+        assert_eq!(
+            Theme::parse("#2: \"Stand by Me\""),
+            Theme {
+                index: Some(2),
+                title: "Stand by Me",
+                artist: None,
+                episodes: None,
+            }
+        );
+        assert_eq!(
+            Theme::parse("\"Stand by Me\" (eps 2-3)"),
+            Theme {
+                index: None,
+                title: "Stand by Me",
+                artist: None,
+                episodes: Some("eps 2-3"),
+            }
+        );
+        assert_eq!(
+            Theme::parse("\"Tabi no Tochuu (epilogue)\" by Natsumi Kiyoura (CV: Ayane)"),
+            Theme {
+                index: None,
+                title: "Tabi no Tochuu (epilogue)",
+                artist: Some("Natsumi Kiyoura (CV: Ayane)"),
+                episodes: None,
+            }
+        );
+        assert_eq!(
+            Theme::parse("Prologue (epilogue)"),
+            Theme {
+                index: None,
+                title: "Prologue (epilogue)",
+                artist: None,
+                episodes: None,
+            }
+        );
+        assert_eq!(
+            Theme::parse("Just a title"),
+            Theme {
+                index: None,
+                title: "Just a title",
+                artist: None,
+                episodes: None,
+            }
+        );
+    }
+
+    #[test]
+    fn song_languages() {
+        let song = "#1: \"Seijatachi (聖者たち)\" by People In The Box (eps 1-11)";
+        let none = BTreeMap::new();
+        assert_eq!(
+            SongLang::Romaji.apply(song, &none),
+            "#1: \"Seijatachi\" by People In The Box (eps 1-11)"
+        );
+        assert_eq!(
+            SongLang::Native.apply(song, &none),
+            "#1: \"聖者たち\" by People In The Box (eps 1-11)"
+        );
+        assert_eq!(SongLang::Both.apply(song, &none), song);
+
+        // Parentheses without a native title are part of the title
+        let song = "\"Ready Steady Go (Remix)\" by L'Arc~en~Ciel";
+        assert_eq!(SongLang::Native.apply(song, &none), song);
+
+        let native_titles: BTreeMap<String, String> =
+            [("unravel".to_owned(), "アンラヴェル".to_owned())].into();
+        let song = "#1: \"unravel\" by TK";
+        assert_eq!(
+            SongLang::Native.apply(song, &native_titles),
+            "#1: \"アンラヴェル\" by TK"
+        );
+        assert_eq!(
+            SongLang::Both.apply(song, &native_titles),
+            "#1: \"unravel (アンラヴェル)\" by TK"
+        );
+        assert_eq!(SongLang::Romaji.apply(song, &native_titles), song);
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat, TimeZone, Utc};
+
+/// The time zone timestamps are written in, set with `--timezone`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Timezone {
+    /// The time zone of the system
+    #[default]
+    Local,
+    Utc,
+    /// A fixed offset from UTC, e.g. `+09:00`
+    Fixed(FixedOffset),
+}
+
+impl Timezone {
+    /// The current time as an RFC 3339 timestamp, e.g. `2020-02-14T21:30:00+01:00`
+    pub fn now(&self) -> String {
+        self.format(Utc::now())
+    }
+
+    /// Formats a point in time as an RFC 3339 timestamp in this time zone, to the second
+    pub fn format(&self, time: DateTime<Utc>) -> String {
+        match self {
+            Self::Local => rfc3339(time.with_timezone(&Local)),
+            // `Z` instead of `+00:00`
+            Self::Utc => time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            Self::Fixed(offset) => rfc3339(time.with_timezone(offset)),
+        }
+    }
+
+    /// Formats a point in time as an RFC 2822 date in this time zone, as feeds need
+    pub fn format_rfc2822(&self, time: DateTime<Utc>) -> String {
+        match self {
+            Self::Local => time.with_timezone(&Local).to_rfc2822(),
+            Self::Utc => time.to_rfc2822(),
+            Self::Fixed(offset) => time.with_timezone(offset).to_rfc2822(),
+        }
+    }
+}
+
+fn rfc3339<Tz: TimeZone>(time: DateTime<Tz>) -> String
+where
+    Tz::Offset: fmt::Display,
+{
+    time.to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            format!(
+                "invalid time zone '{}', expected 'local', 'utc' or an offset like '+09:00'",
+                s
+            )
+        };
+        match s.to_lowercase().as_str() {
+            "local" => return Ok(Self::Local),
+            "utc" | "z" => return Ok(Self::Utc),
+            _ => {}
+        }
+
+        let sign = match s.chars().next() {
+            Some('+') => 1,
+            Some('-') => -1,
+            _ => return Err(error()),
+        };
+        let (hours, minutes) = s[1..].split_once(':').unwrap_or((&s[1..], "0"));
+        let hours: i32 = hours.parse().map_err(|_| error())?;
+        let minutes: i32 = minutes.parse().map_err(|_| error())?;
+        if hours > 23 || minutes > 59 {
+            return Err(error());
+        }
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+            .map(Self::Fixed)
+            .ok_or_else(error)
+    }
+}
+
+/// Validates a time zone argument for clap
+pub fn timezone_validate(value: String) -> Result<(), String> {
+    value.parse::<Timezone>().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timezones() {
+        let time = Utc.ymd(2020, 2, 14).and_hms(20, 30, 5);
+        assert_eq!(Timezone::Utc.format(time), "2020-02-14T20:30:05Z");
+        let tokyo: Timezone = "+09:00".parse().unwrap();
+        assert_eq!(tokyo.format(time), "2020-02-15T05:30:05+09:00");
+        let newfoundland: Timezone = "-03:30".parse().unwrap();
+        assert_eq!(newfoundland.format(time), "2020-02-14T17:00:05-03:30");
+        assert_eq!(
+            tokyo.format_rfc2822(time),
+            "Sat, 15 Feb 2020 05:30:05 +0900"
+        );
+
+        assert_eq!("UTC".parse(), Ok(Timezone::Utc));
+        assert_eq!("local".parse(), Ok(Timezone::Local));
+        assert!("Europe/Berlin".parse::<Timezone>().is_err());
+        assert!("+25:00".parse::<Timezone>().is_err());
+        assert!(DateTime::parse_from_rfc3339(&Timezone::Local.now()).is_ok());
+    }
+}
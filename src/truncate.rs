@@ -0,0 +1,126 @@
+//! Cutting long values of table columns short, with `--max-title-width` and `--column-width`
+
+use clap::ArgMatches;
+
+use crate::columns::Column;
+use crate::table::WidthMode;
+use crate::width_mode;
+
+const ELLIPSIS: char = '…';
+
+/// The widths table columns are cut off at, in terminal columns
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnWidths {
+    /// The width of the song and show columns
+    titles: Option<usize>,
+    /// Widths by lowercase column header, later ones win
+    columns: Vec<(String, usize)>,
+    /// How the width of values is measured
+    pub width_mode: WidthMode,
+}
+
+impl ColumnWidths {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        ColumnWidths {
+            // Validated by clap
+            titles: matches
+                .value_of("max-title-width")
+                .map(|v| v.parse().unwrap()),
+            columns: matches
+                .values_of("column-width")
+                .map(|values| values.map(|v| parse_column_width(v).unwrap()).collect())
+                .unwrap_or_default(),
+            width_mode: width_mode(matches),
+        }
+    }
+
+    /// The width of the column if it has one, `--column-width` before `--max-title-width`
+    pub fn of(&self, column: &Column) -> Option<usize> {
+        let name = column.header().to_lowercase();
+        self.columns
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .map(|&(_, width)| width)
+            .or(match column {
+                Column::Song(_) | Column::Show => self.titles,
+                _ => None,
+            })
+    }
+}
+
+/// Cuts the value off with an ellipsis so it's at most `width` terminal columns wide, counting
+/// wide characters like those of Japanese titles twice
+pub fn truncate(value: &str, width: usize, mode: WidthMode) -> String {
+    if mode.str_width(value) <= width {
+        return value.to_owned();
+    }
+    let mut truncated = String::new();
+    let mut used = mode.char_width(ELLIPSIS);
+    for c in value.chars() {
+        used += mode.char_width(c);
+        if used > width {
+            break;
+        }
+        truncated.push(c);
+    }
+    truncated.truncate(truncated.trim_end().len());
+    truncated.push(ELLIPSIS);
+    truncated
+}
+
+/// Parses a width like `show=40`, the name being a column header in any case
+fn parse_column_width(s: &str) -> Result<(String, usize), String> {
+    let error = || {
+        format!(
+            "invalid column width '{}', expected a column and a positive width like 'show=40'",
+            s
+        )
+    };
+    let (name, width) = s.split_once('=').ok_or_else(error)?;
+    let width: usize = width.trim().parse().map_err(|_| error())?;
+    if name.trim().is_empty() || width == 0 {
+        return Err(error());
+    }
+    Ok((name.trim().to_lowercase(), width))
+}
+
+/// Validates a column width argument for clap
+pub fn column_width_validate(value: String) -> Result<(), String> {
+    parse_column_width(&value).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::SongLang;
+
+    #[test]
+    fn truncating() {
+        let standard = |value, width| truncate(value, width, WidthMode::Standard);
+        assert_eq!(standard("Cowboy Bebop", 12), "Cowboy Bebop");
+        assert_eq!(standard("Cowboy Bebop", 8), "Cowboy…");
+        assert_eq!(standard("Cowboy Bebop", 1), "…");
+        // Two columns for every kana
+        assert_eq!(standard("カウボーイビバップ", 7), "カウボ…");
+        assert_eq!(standard("カウボーイビバップ", 6), "カウ…");
+        // And for the ellipsis on CJK terminals
+        assert_eq!(truncate("カウボーイビバップ", 7, WidthMode::Cjk), "カウ…");
+    }
+
+    #[test]
+    fn column_widths() {
+        let widths = ColumnWidths {
+            titles: Some(30),
+            columns: vec![parse_column_width("Show=40").unwrap()],
+            width_mode: WidthMode::Standard,
+        };
+        assert_eq!(widths.of(&Column::Show), Some(40));
+        assert_eq!(widths.of(&Column::Song(SongLang::Both)), Some(30));
+        assert_eq!(widths.of(&Column::Type), None);
+
+        assert!(parse_column_width("show").is_err());
+        assert!(parse_column_width("show=0").is_err());
+        assert!(parse_column_width("=40").is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// How likely each type of theme is to be chosen within a show, e.g. `op=3 ed=1 ost=0.2`
+///
+/// Types that aren't given keep a weight of 1, a weight of 0 leaves the type out entirely.
+#[derive(Debug, PartialEq)]
+pub struct TypeWeights {
+    pub op: f64,
+    pub ed: f64,
+    pub ost: f64,
+}
+
+impl Default for TypeWeights {
+    fn default() -> Self {
+        TypeWeights {
+            op: 1.0,
+            ed: 1.0,
+            ost: 1.0,
+        }
+    }
+}
+
+impl TypeWeights {
+    /// Combines `--type-weight` values, later values replace earlier ones of the same type
+    pub fn from_values<'a>(values: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let mut weights = TypeWeights::default();
+        for value in values {
+            let TypeWeight { song_type, weight } = value.parse()?;
+            *weights.get_mut(song_type) = weight;
+        }
+        Ok(weights)
+    }
+
+    /// The weight of a song type as returned by `Show::song_type`
+    pub fn weight(&self, song_type: &str) -> f64 {
+        match song_type {
+            "OP" => self.op,
+            "ED" => self.ed,
+            _ => self.ost,
+        }
+    }
+
+    fn get_mut(&mut self, song_type: &str) -> &mut f64 {
+        match song_type {
+            "OP" => &mut self.op,
+            "ED" => &mut self.ed,
+            _ => &mut self.ost,
+        }
+    }
+}
+
+impl fmt::Display for TypeWeights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "op={} ed={} ost={}", self.op, self.ed, self.ost)
+    }
+}
+
+/// A single `--type-weight` value
+struct TypeWeight {
+    song_type: &'static str,
+    weight: f64,
+}
+
+impl FromStr for TypeWeight {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error_msg = || {
+            format!(
+                "invalid type weight '{}', expected e.g. 'op=3', 'ed=1' or 'ost=0.2'",
+                s
+            )
+        };
+
+        let (song_type, weight) = s.split_once('=').ok_or_else(error_msg)?;
+        let song_type = parse_song_type(song_type).ok_or_else(error_msg)?;
+        let weight: f64 = weight.trim().parse().map_err(|_| error_msg())?;
+        if !weight.is_finite() || weight < 0.0 {
+            return Err(format!(
+                "invalid type weight '{}', weights must be non-negative numbers",
+                s
+            ));
+        }
+
+        Ok(TypeWeight { song_type, weight })
+    }
+}
+
+/// Parses `op`, `ed`, `ost` or `st` (case insensitive) into a type as returned by `Show::song_type`
+pub fn parse_song_type(s: &str) -> Option<&'static str> {
+    match s.trim().to_lowercase().as_str() {
+        "op" => Some("OP"),
+        "ed" => Some("ED"),
+        "ost" | "st" => Some("ST"),
+        _ => None,
+    }
+}
+
+/// Validates a type of an `--interleave-types` pattern for clap
+pub fn song_type_validate(value: String) -> Result<(), String> {
+    parse_song_type(&value)
+        .map(|_| ())
+        .ok_or_else(|| format!("invalid type '{}', expected op, ed or ost", value))
+}
+
+/// Validates a type weight argument for clap
+pub fn type_weight_validate(value: String) -> Result<(), String> {
+    value.parse::<TypeWeight>().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_weights() {
+        let weights = TypeWeights::from_values(vec!["op=3", "ED=0", "ost=0.2", "op=2"]).unwrap();
+        assert_eq!(weights.weight("OP"), 2.0);
+        assert_eq!(weights.weight("ED"), 0.0);
+        assert_eq!(weights.weight("ST"), 0.2);
+        assert_eq!(weights.to_string(), "op=2 ed=0 ost=0.2");
+
+        assert!(type_weight_validate("op=-1".to_owned()).is_err());
+        assert!(type_weight_validate("op=inf".to_owned()).is_err());
+        assert!(type_weight_validate("insert=1".to_owned()).is_err());
+        assert!(type_weight_validate("op".to_owned()).is_err());
+
+        assert_eq!(parse_song_type(" St"), Some("ST"));
+        assert!(song_type_validate("insert".to_owned()).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+//! Excel workbooks for `--xlsx`
+//!
+//! A workbook is a zip archive of a few XML files. The archive is written without compression,
+//! which every spreadsheet application reads, so no zip or spreadsheet library is needed. Strings
+//! are stored inline in their cells, numbers as numbers.
+
+use std::error::Error;
+use std::path::Path;
+
+use crate::columns::Column;
+use crate::selection::Pick;
+use crate::xml::escape;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#;
+
+const RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Picks" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+
+const WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+
+/// A regular and a bold font, the latter for the header row as style 1
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="2"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/></cellXfs></styleSheet>"#;
+
+/// A value of a cell
+#[derive(Debug, PartialEq)]
+enum Cell {
+    Number(u64),
+    Text(String),
+    Empty,
+}
+
+/// Writes the picks to a workbook at `path`, replacing any file there
+///
+/// Besides the columns the sheet has the number of each pick, the id of its show and the members
+/// of the show, when known.
+pub fn write(path: &Path, columns: &[Column], picks: &[Pick]) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, workbook(columns, picks))
+        .map_err(|e| format!("couldn't write {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+fn workbook(columns: &[Column], picks: &[Pick]) -> Vec<u8> {
+    // The number and id are always there as numbers, so they aren't written twice
+    let columns: Vec<&Column> = columns
+        .iter()
+        .filter(|c| !matches!(c, Column::Number | Column::Id))
+        .collect();
+    let mut header = vec![Cell::Text("#".to_owned())];
+    header.extend(columns.iter().map(|c| Cell::Text(c.header().to_owned())));
+    header.push(Cell::Text("ID".to_owned()));
+    header.push(Cell::Text("Members".to_owned()));
+
+    let rows = picks.iter().enumerate().map(|(i, pick)| {
+        let mut row = vec![Cell::Number(i as u64 + 1)];
+        row.extend(columns.iter().map(|c| Cell::Text(c.value(i + 1, pick))));
+        row.push(match pick.show.id.as_number() {
+            Some(id) => Cell::Number(id),
+            None => Cell::Text(pick.show.id.to_string()),
+        });
+        row.push(pick.show.members.map_or(Cell::Empty, Cell::Number));
+        row
+    });
+
+    let sheet = sheet(std::iter::once(header).chain(rows));
+    zip(&[
+        ("[Content_Types].xml", CONTENT_TYPES.as_bytes()),
+        ("_rels/.rels", RELS.as_bytes()),
+        ("xl/workbook.xml", WORKBOOK.as_bytes()),
+        ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS.as_bytes()),
+        ("xl/styles.xml", STYLES.as_bytes()),
+        ("xl/worksheets/sheet1.xml", sheet.as_bytes()),
+    ])
+}
+
+/// The worksheet XML of `rows`, the first of which is the header and stays in view when scrolling
+fn sheet(rows: impl Iterator<Item = Vec<Cell>>) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetViews><sheetView workbookViewId="0"><pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/></sheetView></sheetViews><sheetData>"#,
+    );
+    for (r, row) in rows.enumerate() {
+        let style = if r == 0 { r#" s="1""# } else { "" };
+        xml.push_str(&format!(r#"<row r="{}">"#, r + 1));
+        for (c, cell) in row.iter().enumerate() {
+            let reference = format!("{}{}", column_name(c), r + 1);
+            match cell {
+                Cell::Number(n) => xml.push_str(&format!(
+                    r#"<c r="{}"{}><v>{}</v></c>"#,
+                    reference, style, n
+                )),
+                Cell::Text(text) => xml.push_str(&format!(
+                    r#"<c r="{}"{} t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                    reference,
+                    style,
+                    escape(text)
+                )),
+                Cell::Empty => {}
+            }
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// The letters of a column from its index, e.g. `A` for 0 and `AA` for 26
+fn column_name(mut index: usize) -> String {
+    let mut name = vec![];
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap()
+}
+
+/// A zip archive of uncompressed files
+fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    // The earliest time zip can have, so the same picks give the same file
+    const TIME: u16 = 0;
+    const DATE: u16 = (1 << 5) | 1;
+
+    let mut archive = vec![];
+    let mut directory = vec![];
+    for (name, data) in files {
+        let offset = archive.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+
+        archive.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        // Version needed, flags, method (stored), time, date
+        for field in &[20u16, 0, 0, TIME, DATE] {
+            archive.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in &[crc, size, size] {
+            archive.extend_from_slice(&field.to_le_bytes());
+        }
+        archive.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        archive.extend_from_slice(&0u16.to_le_bytes());
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(data);
+
+        directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        // Version made by, version needed, flags, method, time, date
+        for field in &[20u16, 20, 0, 0, TIME, DATE] {
+            directory.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in &[crc, size, size] {
+            directory.extend_from_slice(&field.to_le_bytes());
+        }
+        // Name length, extra length, comment length, disk, internal attributes
+        for field in &[name.len() as u16, 0, 0, 0, 0] {
+            directory.extend_from_slice(&field.to_le_bytes());
+        }
+        // External attributes and the offset of the local header
+        for field in &[0u32, offset] {
+            directory.extend_from_slice(&field.to_le_bytes());
+        }
+        directory.extend_from_slice(name.as_bytes());
+    }
+
+    let directory_offset = archive.len() as u32;
+    let directory_size = directory.len() as u32;
+    archive.append(&mut directory);
+    archive.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    // Disk, disk with the directory, entries on this disk, entries
+    for field in &[0u16, 0, files.len() as u16, files.len() as u16] {
+        archive.extend_from_slice(&field.to_le_bytes());
+    }
+    for field in &[directory_size, directory_offset] {
+        archive.extend_from_slice(&field.to_le_bytes());
+    }
+    // Comment length
+    archive.extend_from_slice(&0u16.to_le_bytes());
+    archive
+}
+
+/// The CRC-32 of `data` as used by zip
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::fixtures;
+    use crate::Show;
+
+    #[test]
+    fn workbooks() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(702), "AAA");
+
+        let show = Show {
+            members: Some(1_800_000),
+            ..fixtures::bebop()
+        };
+        let pick = fixtures::opening(&show);
+        let workbook = workbook(&Column::DEFAULT, &[pick]);
+        assert!(workbook.starts_with(b"PK\x03\x04"));
+        let sheet = String::from_utf8_lossy(&workbook);
+        assert!(sheet.contains(
+            r#"<c r="B2" t="inlineStr"><is><t xml:space="preserve">&quot;Tank!&quot; by The Seatbelts</t></is></c>"#
+        ));
+        assert!(sheet.contains(r#"<c r="E2"><v>1</v></c><c r="F2"><v>1800000</v></c>"#));
+    }
+}
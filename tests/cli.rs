@@ -0,0 +1,979 @@
+//! End-to-end tests of the binary against the data in `tests/fixtures`
+//!
+//! Expected output is kept in `tests/golden`, named after the test. Run the tests with
+//! `UPDATE_GOLDEN=1` to write the current output there instead, and review the diff.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use assert_cmd::assert::Assert;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+/// Runs the binary with `args`, isolated from the config and state of the user
+fn run(args: &[&str]) -> Assert {
+    run_in(
+        &Path::new(env!("CARGO_TARGET_TMPDIR")).join("cli-home"),
+        args,
+    )
+}
+
+/// Runs the binary with `args` and `home` as the home directory, for tests of state
+fn run_in(home: &Path, args: &[&str]) -> Assert {
+    command(home).args(args).assert()
+}
+
+/// Runs the binary with `args` and the config file `config` of `tests/fixtures`
+fn run_with_config(config: &str, args: &[&str]) -> Assert {
+    command(&Path::new(env!("CARGO_TARGET_TMPDIR")).join("cli-home"))
+        .env("RANDOM_SHOW_THEMES_CONFIG", config)
+        .args(args)
+        .assert()
+}
+
+fn command(home: &Path) -> Command {
+    let mut command = Command::cargo_bin("random-show-themes").unwrap();
+    command
+        .current_dir(FIXTURES)
+        .env_remove("RANDOM_SHOW_THEMES_CONFIG")
+        .env_remove("COLUMNS")
+        .env_remove("RUST_BACKTRACE")
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", home.join("data"));
+    command
+}
+
+/// Compares `actual` with the golden file `name`, byte for byte
+fn assert_golden(name: &str, actual: &[u8]) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name]
+        .iter()
+        .collect();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected =
+        std::fs::read(&path).unwrap_or_else(|e| panic!("couldn't read {}: {}", path.display(), e));
+    // As text first, for a readable diff
+    assert_eq!(
+        String::from_utf8_lossy(actual),
+        String::from_utf8_lossy(&expected),
+        "output differs from {}",
+        path.display()
+    );
+    assert!(actual == expected, "output differs from {}", path.display());
+}
+
+/// Checks a successful run that writes `name`.stdout and nothing to stderr
+fn assert_stdout(name: &str, args: &[&str]) {
+    assert_success(name, run(args));
+}
+
+/// Checks that `run` succeeded and wrote `name`.stdout and nothing to stderr
+fn assert_success(name: &str, run: Assert) {
+    let run = run.success().stderr("");
+    assert_golden(&format!("{}.stdout", name), &run.get_output().stdout);
+}
+
+/// Checks a failed run that exits with `code` and writes `name`.stderr and nothing to stdout
+fn assert_failure(name: &str, code: i32, args: &[&str]) {
+    let run = run(args).code(code).stdout("");
+    assert_golden(&format!("{}.stderr", name), &run.get_output().stderr);
+}
+
+/// The number of lines `run` wrote to stdout
+fn stdout_lines(run: &Assert) -> usize {
+    run.get_output()
+        .stdout
+        .iter()
+        .filter(|&&b| b == b'\n')
+        .count()
+}
+
+#[test]
+fn readable() {
+    assert_stdout(
+        "readable",
+        &["pick", "2", "--take-first", "--data-dir", "."],
+    );
+}
+
+#[test]
+fn table() {
+    assert_stdout(
+        "table",
+        &[
+            "pick",
+            "2",
+            "--take-first",
+            "-t",
+            "--table-width",
+            "30",
+            "--data-dir",
+            ".",
+        ],
+    );
+    assert_stdout(
+        "table_cjk",
+        &[
+            "pick",
+            "2",
+            "--take-first",
+            "-t",
+            "--table-width",
+            "0",
+            "--max-title-width",
+            "12",
+            "--width-mode",
+            "cjk",
+            "--data-dir",
+            ".",
+        ],
+    );
+    assert_stdout(
+        "table_truncated",
+        &[
+            "pick",
+            "2",
+            "--take-first",
+            "-t",
+            "--table-width",
+            "0",
+            "--max-title-width",
+            "12",
+            "--column-width",
+            "show=8",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn csv() {
+    assert_stdout(
+        "csv",
+        &["pick", "2", "--take-first", "--csv", "--data-dir", "."],
+    );
+}
+
+#[test]
+fn ndjson() {
+    assert_stdout(
+        "ndjson",
+        &["pick", "2", "--take-first", "--ndjson", "--data-dir", "."],
+    );
+}
+
+#[test]
+fn emit_shell() {
+    assert_stdout(
+        "emit_shell",
+        &[
+            "pick",
+            "3",
+            "--take-first",
+            "--emit-shell",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn all() {
+    assert_stdout("all", &["pick", "--all", "--data-dir", "."]);
+    assert_stdout("all_csv", &["pick", "--all", "--csv", "--data-dir", "."]);
+}
+
+#[test]
+fn fmt_check() {
+    assert_stdout(
+        "fmt_check",
+        &["fmt", "--check", "list.json", "dictionary.json"],
+    );
+}
+
+#[test]
+fn too_few_results() {
+    // Warned about, but only an error with --expect-exact and --hard-fail
+    let partial = run(&["pick", "9", "--take-first", "--data-dir", "."]).success();
+    assert_golden("too_few_results.stderr", &partial.get_output().stderr);
+    assert_eq!(stdout_lines(&partial), 3);
+
+    assert_failure(
+        "too_few_results_error",
+        3,
+        &["pick", "9", "--expect-exact", "--data-dir", "."],
+    );
+
+    // Everything that could be picked is still output
+    let partial = run(&["pick", "9", "--hard-fail", "--data-dir", "."]).code(1);
+    assert_golden("too_few_results_error.stderr", &partial.get_output().stderr);
+    assert_eq!(stdout_lines(&partial), 3);
+
+    // The same when filters leave too few shows, rather than the list
+    run(&[
+        "pick",
+        "3",
+        "--expect-exact",
+        "--data-dir",
+        ".",
+        "--status",
+        "completed",
+    ])
+    .code(3);
+
+    // Quiet only leaves out warnings and less
+    assert_failure(
+        "too_few_results_error",
+        3,
+        &["-q", "pick", "9", "--expect-exact", "--data-dir", "."],
+    );
+}
+
+#[test]
+fn invalid_number() {
+    assert_failure("invalid_number", 1, &["pick", "0", "--data-dir", "."]);
+}
+
+#[test]
+fn missing_arguments() {
+    assert_failure("missing_arguments", 1, &["pick"]);
+}
+
+#[test]
+fn template() {
+    assert_stdout(
+        "template",
+        &[
+            "pick",
+            "3",
+            "--take-first",
+            "--template",
+            "post.tmpl",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn missing_format_file() {
+    assert_failure(
+        "missing_format_file",
+        1,
+        &[
+            "pick",
+            "1",
+            "--format-file",
+            "missing.tmpl",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn missing_dictionary() {
+    run(&["pick", "1", "-d", "nonexistent.json", "-l", "list.json"])
+        .code(1)
+        .stdout("")
+        .stderr(contains("couldn't parse dictionary"));
+}
+
+#[test]
+fn json() {
+    assert_stdout(
+        "json",
+        &["pick", "2", "--take-first", "--json", "--data-dir", "."],
+    );
+    assert_stdout("all_json", &["pick", "--all", "--json", "--data-dir", "."]);
+}
+
+#[test]
+fn msgpack() {
+    assert_stdout(
+        "msgpack",
+        &["pick", "2", "--take-first", "--msgpack", "--data-dir", "."],
+    );
+}
+
+#[test]
+fn markdown() {
+    assert_stdout(
+        "markdown",
+        &["pick", "2", "--take-first", "--markdown", "--data-dir", "."],
+    );
+}
+
+#[test]
+fn tsv() {
+    assert_stdout(
+        "tsv",
+        &["pick", "3", "--take-first", "--tsv", "--data-dir", "."],
+    );
+}
+
+#[test]
+fn out() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("out.md");
+    let _ = std::fs::remove_file(&path);
+    let file_sink = format!("file={}:format=markdown", path.display());
+    assert_stdout(
+        "out",
+        &[
+            "pick",
+            "2",
+            "--take-first",
+            "--data-dir",
+            ".",
+            "--out",
+            "stdout:format=csv",
+            "--out",
+            &file_sink,
+        ],
+    );
+    assert_golden("markdown.stdout", &std::fs::read(&path).unwrap());
+}
+
+#[test]
+fn output() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("output.csv");
+    std::fs::write(&path, "earlier output").unwrap();
+    run(&[
+        "pick",
+        "2",
+        "--take-first",
+        "--csv",
+        "--data-dir",
+        ".",
+        "-o",
+        path.to_str().unwrap(),
+    ])
+    .success()
+    .stdout("");
+    assert_golden("csv.stdout", &std::fs::read(&path).unwrap());
+    assert!(!path.with_extension("csv.tmp").exists());
+}
+
+#[test]
+fn outputs() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let (csv, json) = (dir.join("outputs.csv"), dir.join("outputs.json"));
+    assert_stdout(
+        "readable",
+        &[
+            "pick",
+            "2",
+            "--take-first",
+            "--data-dir",
+            ".",
+            "--out",
+            "stdout",
+            "-o",
+            csv.to_str().unwrap(),
+            "--output",
+            json.to_str().unwrap(),
+        ],
+    );
+    assert_golden("csv.stdout", &std::fs::read(&csv).unwrap());
+    assert_golden("json.stdout", &std::fs::read(&json).unwrap());
+}
+
+#[test]
+fn append() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("append.csv");
+    let _ = std::fs::remove_file(&path);
+    let args = [
+        "pick",
+        "2",
+        "--take-first",
+        "--csv",
+        "--data-dir",
+        ".",
+        "-o",
+        path.to_str().unwrap(),
+        "--append",
+    ];
+    for _ in 0..2 {
+        run(&args).success();
+    }
+    // Only one header row
+    assert_golden("append.stdout", &std::fs::read(&path).unwrap());
+}
+
+#[cfg(unix)]
+#[test]
+fn sink_failure() {
+    assert_failure(
+        "sink_failure",
+        1,
+        &[
+            "pick",
+            "2",
+            "--take-first",
+            "--data-dir",
+            ".",
+            "--out",
+            "command=exit 3",
+            "--out",
+            "stdout",
+            "--sink-failure",
+            "abort",
+        ],
+    );
+}
+
+#[test]
+fn closed_stdout_sink() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let home = dir.join("cli-home");
+    for (policy, code, delivered) in [("continue", 0, true), ("abort", 1, false)] {
+        let path = dir.join(format!("closed-stdout-{}.csv", policy));
+        let _ = std::fs::remove_file(&path);
+        let file = format!("file={}:format=csv", path.display());
+        // Closed before anything is written, like `| head -0`
+        let (reader, writer) = os_pipe::pipe().unwrap();
+        drop(reader);
+        command(&home)
+            .args([
+                "pick",
+                "2",
+                "--take-first",
+                "--data-dir",
+                ".",
+                "--out",
+                "stdout",
+                "--out",
+                &file,
+                "--sink-failure",
+                policy,
+            ])
+            .stdout(writer)
+            .assert()
+            .code(code)
+            .stderr(contains("couldn't write to stdout"));
+        // What happens to the other sinks is up to --sink-failure
+        assert_eq!(path.exists(), delivered, "{}", path.display());
+        if delivered {
+            assert_golden("csv.stdout", &std::fs::read(&path).unwrap());
+        }
+    }
+}
+
+#[test]
+fn m3u() {
+    assert_stdout(
+        "m3u",
+        &["pick", "2", "--take-first", "--m3u", "--data-dir", "."],
+    );
+}
+
+#[test]
+fn sql() {
+    assert_stdout(
+        "sql",
+        &["pick", "--sql", "2", "--take-first", "--data-dir", "."],
+    );
+    assert_stdout(
+        "sql_table",
+        &[
+            "pick",
+            "1",
+            "--take-first",
+            "--sql=weekly",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn format() {
+    assert_stdout(
+        "format",
+        &[
+            "pick",
+            "3",
+            "--take-first",
+            "--format",
+            "{number}. {song} [{type}] - {show} ({id}) {unknown}",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn global_options_before_subcommand() {
+    // Their values aren't taken for the subcommand
+    assert_stdout(
+        "readable",
+        &[
+            "--log-level",
+            "error",
+            "pick",
+            "2",
+            "--take-first",
+            "--data-dir",
+            ".",
+        ],
+    );
+    assert_stdout(
+        "readable",
+        &[
+            "--color",
+            "never",
+            "pick",
+            "2",
+            "--take-first",
+            "--data-dir",
+            ".",
+        ],
+    );
+
+    run(&["--progress", "json", "selftest", "--iterations", "10"])
+        .stdout(predicate::str::starts_with("Self-test: 10 iterations"))
+        .stderr(predicate::str::starts_with(
+            r#"{"event":"start","task":"selftest","total":10}"#,
+        ));
+}
+
+#[test]
+fn with_replacement_conflicts() {
+    // Rejected like with --sample-with-replacement, instead of being ignored
+    for args in [
+        ["--quota", "genre:Music=1"].as_slice(),
+        ["--unique-artist"].as_slice(),
+    ] {
+        for strategy in [
+            ["--sample-with-replacement"].as_slice(),
+            ["--pick-strategy", "with-replacement"].as_slice(),
+        ] {
+            let mut all = vec!["pick", "2", "--data-dir", "."];
+            all.extend(strategy);
+            all.extend(args);
+            run(&all).code(1).stderr(contains("cannot be used with"));
+        }
+    }
+    assert_failure(
+        "with_replacement_quota",
+        1,
+        &[
+            "pick",
+            "2",
+            "--pick-strategy",
+            "with-replacement",
+            "--quota",
+            "genre:Music=1",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn song_lang() {
+    // Every output that shows song titles follows --song-lang
+    for format in [&[][..], &["--format", "{song}"], &["--csv"]] {
+        let mut args = vec![
+            "pick",
+            "8",
+            "--take-first",
+            "--demo",
+            "--song-lang",
+            "native",
+        ];
+        args.extend(format);
+        run(&args)
+            .success()
+            .stdout(contains("アンラヴェル").and(contains("unravel").not()));
+    }
+}
+
+#[test]
+fn concurrent_favorites() {
+    // Runs at the same time all get their favorite in
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("concurrent-favorites");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let data_dir = dir.to_str().unwrap().to_owned();
+    let runs: Vec<_> = (1..=8)
+        .map(|id| {
+            let data_dir = data_dir.clone();
+            std::thread::spawn(move || {
+                let id = id.to_string();
+                run(&["fav", "--data-dir", &data_dir, "add", &id]).success();
+            })
+        })
+        .collect();
+    for run in runs {
+        run.join().unwrap();
+    }
+    let list = run(&["fav", "--data-dir", &data_dir, "list"]).success();
+    let mut ids: Vec<u32> = String::from_utf8_lossy(&list.get_output().stdout)
+        .lines()
+        .map(|line| line.parse().unwrap())
+        .collect();
+    ids.sort_unstable();
+    assert_eq!(ids, (1..=8).collect::<Vec<_>>());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+}
+
+#[test]
+fn concurrent_sessions() {
+    // Runs at the same time never pick a theme another one already played
+    let home = Path::new(env!("CARGO_TARGET_TMPDIR")).join("concurrent-sessions");
+    let _ = std::fs::remove_dir_all(&home);
+    let runs: Vec<_> = (0..16)
+        .map(|_| {
+            let home = home.clone();
+            std::thread::spawn(move || {
+                let args = [
+                    "pick",
+                    "1",
+                    "--pick-strategy",
+                    "exhaust-then-reshuffle",
+                    "--csv",
+                    "--no-header",
+                    "-q",
+                    "--demo",
+                ];
+                let run = run_in(&home, &args).success();
+                String::from_utf8_lossy(&run.get_output().stdout).into_owned()
+            })
+        })
+        .collect();
+    let mut songs: Vec<String> = runs.into_iter().map(|run| run.join().unwrap()).collect();
+    songs.sort();
+    songs.dedup();
+    assert_eq!(songs.len(), 16, "{:?}", songs);
+}
+
+#[test]
+fn session_after_output() {
+    let home = Path::new(env!("CARGO_TARGET_TMPDIR")).join("session-after-output");
+    let _ = std::fs::remove_dir_all(&home);
+    let pick = [
+        "pick",
+        "1",
+        "--pick-strategy",
+        "exhaust-then-reshuffle",
+        "--demo",
+    ];
+
+    // Nothing is marked as played when the output fails
+    let missing = home.join("missing").join("picks.csv");
+    let mut args = pick.to_vec();
+    let sink = format!("file={}", missing.display());
+    args.extend(["-q", "--out", &sink]);
+    run_in(&home, &args).stdout("");
+    let mut args = pick.to_vec();
+    args.push("-v");
+    run_in(&home, &args).success().stderr(contains("INFO - 1/"));
+}
+
+#[test]
+fn columns() {
+    assert_stdout(
+        "columns",
+        &[
+            "pick",
+            "3",
+            "--take-first",
+            "--columns",
+            "show,song,id",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn with_url() {
+    assert_stdout(
+        "with_url",
+        &[
+            "pick",
+            "1",
+            "--take-first",
+            "--with-url",
+            "--csv",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn with_id() {
+    assert_stdout(
+        "with_id",
+        &["pick", "2", "--take-first", "--with-id", "--data-dir", "."],
+    );
+}
+
+#[test]
+fn interleave_types() {
+    assert_stdout(
+        "interleave_types",
+        &[
+            "pick",
+            "2",
+            "--take-first",
+            "--interleave-types",
+            "ed,op",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn notes() {
+    assert_stdout(
+        "notes",
+        &[
+            "pick",
+            "3",
+            "--take-first",
+            "--notes",
+            "notes.json",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn sort() {
+    assert_stdout(
+        "sort",
+        &[
+            "pick",
+            "3",
+            "--take-first",
+            "--sort",
+            "show",
+            "--reverse",
+            "--numbered",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn config() {
+    // Settings of the config file apply like their flags
+    assert_success(
+        "config",
+        run_with_config("config.json", &["pick", "2", "--take-first"]),
+    );
+    // And flags of the command line override them
+    assert_success(
+        "csv",
+        run_with_config(
+            "config.json",
+            &["pick", "2", "--take-first", "--csv", "--no-numbered"],
+        ),
+    );
+}
+
+#[test]
+fn config_sort() {
+    // The sort of the config file applies to picks
+    assert_success(
+        "sort",
+        run_with_config(
+            "config_sort.json",
+            &["pick", "3", "--take-first", "--numbered", "--data-dir", "."],
+        ),
+    );
+    // But not to --all, which is always in list order
+    assert_success(
+        "all_csv",
+        run_with_config(
+            "config_sort.json",
+            &["pick", "--all", "--csv", "--data-dir", "."],
+        ),
+    );
+}
+
+#[test]
+fn group_by_show() {
+    assert_stdout(
+        "group_by_show",
+        &["pick", "--all", "--group-by-show", "--data-dir", "."],
+    );
+}
+
+#[test]
+fn config_group_by_show() {
+    let config = "config_group_by_show.json";
+    assert_success(
+        "group_by_show",
+        run_with_config(config, &["pick", "--all", "--data-dir", "."]),
+    );
+    // Output modes that can't group are left as they are
+    assert_success(
+        "csv",
+        run_with_config(
+            config,
+            &["pick", "2", "--take-first", "--csv", "--data-dir", "."],
+        ),
+    );
+    assert_success(
+        "all",
+        run_with_config(
+            config,
+            &["pick", "--all", "--no-group-by-show", "--data-dir", "."],
+        ),
+    );
+}
+
+#[test]
+fn summary() {
+    assert_stdout(
+        "summary",
+        &["pick", "--all", "--summary", "--data-dir", "."],
+    );
+}
+
+#[test]
+fn csv_delimiter() {
+    assert_stdout(
+        "csv_delimiter",
+        &[
+            "pick",
+            "--all",
+            "--csv",
+            "--csv-delimiter",
+            ";",
+            "--csv-quote-style",
+            "non-numeric",
+            "--with-id",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn demo() {
+    assert_stdout("demo", &["pick", "--all", "--demo", "--summary"]);
+}
+
+#[test]
+fn no_header() {
+    assert_stdout(
+        "no_header",
+        &[
+            "pick",
+            "2",
+            "--take-first",
+            "--tsv",
+            "--no-header",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn numbered() {
+    assert_stdout(
+        "numbered",
+        &["pick", "2", "--take-first", "--numbered", "--data-dir", "."],
+    );
+    assert_stdout(
+        "numbered_csv",
+        &[
+            "pick",
+            "2",
+            "--take-first",
+            "--numbered",
+            "--csv",
+            "--data-dir",
+            ".",
+        ],
+    );
+}
+
+#[test]
+fn plain() {
+    assert_stdout(
+        "plain",
+        &["pick", "3", "--take-first", "--plain", "--data-dir", "."],
+    );
+}
+
+#[test]
+fn null() {
+    assert_stdout(
+        "null",
+        &[
+            "pick",
+            "2",
+            "--take-first",
+            "--plain",
+            "-0",
+            "--data-dir",
+            ".",
+        ],
+    );
+    assert_failure(
+        "null_json",
+        1,
+        &["pick", "2", "--json", "--null", "--data-dir", "."],
+    );
+}
+
+#[test]
+fn check() {
+    assert_stdout("check", &["pick", "--check", "--data-dir", "."]);
+
+    let failure = run(&[
+        "pick",
+        "5",
+        "--check",
+        "--family-friendly",
+        "-d",
+        "dictionary.json",
+        "-l",
+        "missing.json",
+    ])
+    .code(1);
+    assert_golden("check_failure.stdout", &failure.get_output().stdout);
+
+    // A show without themes doesn't count as eligible
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let (dictionary, list) = (dir.join("themeless.json"), dir.join("themeless-list.json"));
+    std::fs::write(
+        &dictionary,
+        r#"{
+            "1": {"id": 1, "title": "Themeless"},
+            "2": {"id": 2, "title": "Cowboy Bebop", "opening_themes": ["Tank!", "Rush", "Ask DNA"]}
+        }"#,
+    )
+    .unwrap();
+    std::fs::write(&list, "[1, 2]").unwrap();
+    run(&[
+        "pick",
+        "2",
+        "--check",
+        "-d",
+        dictionary.to_str().unwrap(),
+        "-l",
+        list.to_str().unwrap(),
+    ])
+    .code(1)
+    .stdout(contains("eligible: 1 shows, 3 themes\n").and(contains(
+        "2 results were requested, however only 1 shows can be picked",
+    )));
+}